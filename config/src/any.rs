@@ -0,0 +1,363 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+/// An error of whichever format an [`AnyFormat`] dispatched to.
+#[derive(Error, Debug)]
+#[error(transparent)]
+pub struct AnyFormatError(Box<dyn Error + Send + Sync>);
+
+/// Generates [`AnyFormat`] from every built-in format, with the feature enabling it, its canonical
+/// extension and then any alternative extensions.
+macro_rules! any_format {
+    ($($variant:ident: $feature:literal => $extension:literal $(| $alias:literal)*;)*) => {
+        #[cfg(any($(feature = $feature),*))]
+        use crate::formats::Format;
+
+        #[cfg(any($(feature = $feature),*))]
+        impl AnyFormatError {
+            fn new(error: impl Error + Send + Sync + 'static) -> Self {
+                Self(Box::new(error))
+            }
+        }
+
+        /// The extensions of the built-in formats, with the feature enabling each, including the
+        /// formats which aren't enabled.
+        const EXTENSIONS: &[(&str, &str)] = &[
+            $(
+            ($extension, $feature),
+            $(($alias, $feature),)*
+            )*
+        ];
+
+        /// Alternative extensions of the built-in formats, with the extension they stand for.
+        const ALIASES: &[(&str, &str)] = &[
+            $($(($alias, $extension),)*)*
+        ];
+
+        /// A [`Format`](crate::Format) picked at runtime, e.g. by the extension of a config file
+        /// supplied by the user. Only the formats whose feature is enabled are available.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum AnyFormat {
+            $(
+            #[cfg(feature = $feature)]
+            $variant,
+            )*
+        }
+
+        impl AnyFormat {
+            /// Every available format.
+            pub const ALL: &'static [Self] = &[
+                $(
+                #[cfg(feature = $feature)]
+                Self::$variant,
+                )*
+            ];
+
+            /// The cargo feature enabling the format.
+            pub fn feature(self) -> &'static str {
+                match self {
+                    $(
+                    #[cfg(feature = $feature)]
+                    Self::$variant => $feature,
+                    )*
+                }
+            }
+
+            /// The canonical file extension of the format. See
+            /// [`Format::extension`](crate::Format::extension).
+            pub fn extension(self) -> &'static str {
+                match self {
+                    $(
+                    #[cfg(feature = $feature)]
+                    Self::$variant => crate::$variant::extension(),
+                    )*
+                }
+            }
+
+            /// The MIME type of the format. See
+            /// [`Format::content_type`](crate::Format::content_type).
+            pub fn content_type(self) -> &'static str {
+                match self {
+                    $(
+                    #[cfg(feature = $feature)]
+                    Self::$variant => crate::$variant::content_type(),
+                    )*
+                }
+            }
+
+            pub fn from_str<T: DeserializeOwned>(self, s: &str) -> Result<T, AnyFormatError> {
+                // nothing is left to dispatch to when no format is enabled
+                #[cfg(not(any($(feature = $feature),*)))]
+                let _ = s;
+
+                match self {
+                    $(
+                    #[cfg(feature = $feature)]
+                    Self::$variant => crate::$variant::from_str(s).map_err(AnyFormatError::new),
+                    )*
+                }
+            }
+
+            pub fn to_string<T: Serialize>(self, t: &T) -> Result<String, AnyFormatError> {
+                #[cfg(not(any($(feature = $feature),*)))]
+                let _ = t;
+
+                match self {
+                    $(
+                    #[cfg(feature = $feature)]
+                    Self::$variant => crate::$variant::to_string(t).map_err(AnyFormatError::new),
+                    )*
+                }
+            }
+
+            pub fn from_slice<T: DeserializeOwned>(self, s: &[u8]) -> Result<T, AnyFormatError> {
+                #[cfg(not(any($(feature = $feature),*)))]
+                let _ = s;
+
+                match self {
+                    $(
+                    #[cfg(feature = $feature)]
+                    Self::$variant => crate::$variant::from_slice(s).map_err(AnyFormatError::new),
+                    )*
+                }
+            }
+
+            pub fn to_vec<T: Serialize>(self, t: &T) -> Result<Vec<u8>, AnyFormatError> {
+                #[cfg(not(any($(feature = $feature),*)))]
+                let _ = t;
+
+                match self {
+                    $(
+                    #[cfg(feature = $feature)]
+                    Self::$variant => crate::$variant::to_vec(t).map_err(AnyFormatError::new),
+                    )*
+                }
+            }
+
+            pub fn from_reader<R: Read, T: DeserializeOwned>(self, r: R) -> Result<T, AnyFormatError> {
+                #[cfg(not(any($(feature = $feature),*)))]
+                let _ = r;
+
+                match self {
+                    $(
+                    #[cfg(feature = $feature)]
+                    Self::$variant => crate::$variant::from_reader(r).map_err(AnyFormatError::new),
+                    )*
+                }
+            }
+
+            pub fn to_writer<W: Write, T: Serialize>(self, w: W, t: &T) -> Result<(), AnyFormatError> {
+                #[cfg(not(any($(feature = $feature),*)))]
+                let _ = (w, t);
+
+                match self {
+                    $(
+                    #[cfg(feature = $feature)]
+                    Self::$variant => crate::$variant::to_writer(w, t).map_err(AnyFormatError::new),
+                    )*
+                }
+            }
+        }
+    };
+}
+
+any_format! {
+    Toml: "toml" => "toml";
+    Json: "json" => "json";
+    Yaml: "yaml" => "yaml" | "yml";
+    Ini: "ini" => "ini";
+    Ron: "ron" => "ron";
+    Json5: "json5" => "json5";
+    Xml: "xml" => "xml";
+    Cbor: "cbor" => "cbor";
+    MessagePack: "messagepack" => "msgpack" | "mpk";
+    Bincode: "bincode" => "bincode";
+    Bencode: "bencode" => "torrent";
+    Bson: "bson" => "bson";
+    Csv: "csv" => "csv";
+    Dhall: "dhall" => "dhall";
+    Postcard: "postcard" => "postcard";
+    Properties: "properties" => "properties";
+    Kdl: "kdl" => "kdl";
+    Hcl: "hcl" => "hcl";
+}
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum AutoLoadError {
+    #[error("the format of the config '{}' can't be detected from its extension", path.display())]
+    UnknownFormat { path: PathBuf },
+
+    #[error("the config '{}' is in a format which isn't enabled; enable the '{feature}' feature of alptk-config", path.display())]
+    FormatDisabled { path: PathBuf, feature: &'static str },
+
+    #[error("failed to read the config '{}'", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to deserialize the config '{}'", path.display())]
+    Deserialize {
+        path: PathBuf,
+        #[source]
+        source: AnyFormatError,
+    },
+}
+
+/// Detects the format of `path` from its extension, like [`AnyFormat::from_path`].
+pub fn detect(path: impl AsRef<Path>) -> Option<AnyFormat> {
+    AnyFormat::from_path(path)
+}
+
+/// Loads the config at `path` in the format [detected](detect) from its extension, for paths
+/// supplied by the user. Unlike [`detect`], tells unknown extensions apart from the extensions of
+/// built-in formats whose feature isn't enabled.
+pub fn load_auto<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, AutoLoadError> {
+    let path = path.as_ref();
+    let Some(format) = detect(path) else {
+        return Err(undetected(path, EXTENSIONS))
+    };
+    let bytes = fs::read(path).map_err(|source| AutoLoadError::Read { path: path.to_owned(), source })?;
+
+    format.from_slice(&bytes).map_err(|source| AutoLoadError::Deserialize { path: path.to_owned(), source })
+}
+
+/// The error for `path` when no available format was detected, which tells the `extensions` of
+/// formats which aren't enabled apart from unknown ones.
+fn undetected(path: &Path, extensions: &[(&str, &'static str)]) -> AutoLoadError {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+
+    match extensions.iter().find(|(known, _)| known.eq_ignore_ascii_case(extension)) {
+        Some(&(_, feature)) => AutoLoadError::FormatDisabled { path: path.to_owned(), feature },
+        None => AutoLoadError::UnknownFormat { path: path.to_owned() },
+    }
+}
+
+impl AnyFormat {
+    /// Returns the available format whose [extension](Self::extension) is `extension`, compared
+    /// case insensitively and without the leading dot. `yml` is recognized as YAML and `mpk` as
+    /// MessagePack as well.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let extension = ALIASES
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(extension))
+            .map_or(extension, |(_, extension)| extension);
+
+        Self::ALL.iter().copied().find(|format| format.extension().eq_ignore_ascii_case(extension))
+    }
+
+    /// Like [`from_extension`](Self::from_extension), with the extension of `path`.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        Self::from_extension(path.as_ref().extension()?.to_str()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AnyFormat;
+
+    #[cfg(all(feature = "toml", feature = "json"))]
+    #[test]
+    fn detects_by_extension() {
+        assert_eq!(AnyFormat::from_path("config.toml"), Some(AnyFormat::Toml));
+        assert_eq!(AnyFormat::from_path("/etc/app/config.JSON"), Some(AnyFormat::Json));
+        assert_eq!(AnyFormat::from_extension("json"), Some(AnyFormat::Json));
+        assert_eq!(AnyFormat::Json.content_type(), "application/json");
+    }
+
+    #[test]
+    fn unknown_extension_is_none() {
+        assert_eq!(AnyFormat::from_extension("docx"), None);
+        assert_eq!(AnyFormat::from_path("config"), None);
+        assert_eq!(AnyFormat::from_path("config.toml.bak"), None);
+    }
+
+    #[test]
+    fn detects_every_enabled_extension() {
+        use crate::detect;
+
+        for (extension, feature) in super::EXTENSIONS {
+            let format = detect(format!("config.{extension}"));
+
+            assert_eq!(format.is_some(), AnyFormat::ALL.iter().any(|format| format.feature() == *feature), "{extension}");
+            assert_eq!(format, detect(format!("CONFIG.{}", extension.to_uppercase())));
+        }
+
+        #[cfg(feature = "yaml")]
+        assert_eq!(detect("config.yml"), Some(AnyFormat::Yaml));
+        #[cfg(feature = "messagepack")]
+        assert_eq!(detect("state.mpk"), Some(AnyFormat::MessagePack));
+        #[cfg(feature = "cbor")]
+        assert_eq!(detect("firmware/config.cbor"), Some(AnyFormat::Cbor));
+    }
+
+    #[test]
+    fn load_auto_errors() {
+        use crate::{load_auto, AutoLoadError};
+
+        assert!(matches!(load_auto::<()>("config.docx"), Err(AutoLoadError::UnknownFormat { .. })));
+        assert!(matches!(load_auto::<()>("config"), Err(AutoLoadError::UnknownFormat { .. })));
+
+        // every feature may be enabled, so pretend that ini isn't
+        let error = super::undetected("config.INI".as_ref(), &[("ini", "ini")]);
+
+        assert!(matches!(error, AutoLoadError::FormatDisabled { feature: "ini", .. }));
+        assert_eq!(error.to_string(), "the config 'config.INI' is in a format which isn't enabled; enable the 'ini' feature of alptk-config");
+        assert!(matches!(super::undetected("config.docx".as_ref(), &[("ini", "ini")]), AutoLoadError::UnknownFormat { .. }));
+
+        #[cfg(not(feature = "ini"))]
+        assert!(matches!(load_auto::<()>("config.ini"), Err(AutoLoadError::FormatDisabled { feature: "ini", .. })));
+    }
+
+    #[test]
+    fn canonical_extensions_match_the_formats() {
+        for format in AnyFormat::ALL {
+            assert!(super::EXTENSIONS.contains(&(format.extension(), format.feature())), "{format:?}");
+        }
+
+        for (alias, extension) in super::ALIASES {
+            assert!(super::EXTENSIONS.iter().any(|(known, _)| known == extension), "{alias}");
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn loads_by_extension() {
+        use std::fs;
+        use crate::{load_auto, AutoLoadError};
+
+        let root = tempfile::tempdir().unwrap();
+        let tmp = root.path();
+        fs::write(tmp.join("config.Toml"), "port = 8080").unwrap();
+        fs::write(tmp.join("broken.toml"), "port =").unwrap();
+
+        let value: toml::Table = load_auto(tmp.join("config.Toml")).unwrap();
+
+        assert_eq!(value["port"].as_integer(), Some(8080));
+        assert!(matches!(load_auto::<toml::Table>(tmp.join("broken.toml")), Err(AutoLoadError::Deserialize { .. })));
+        assert!(matches!(load_auto::<toml::Table>(tmp.join("missing.toml")), Err(AutoLoadError::Read { .. })));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn dispatches() {
+        let format = AnyFormat::from_extension("json").unwrap();
+
+        assert_eq!(format.to_string(&[1, 2]).unwrap(), "[1,2]");
+        assert_eq!(format.from_str::<Vec<u8>>("[1,2]").unwrap(), [1, 2]);
+        assert!(format.from_str::<Vec<u8>>("[").is_err());
+
+        let mut vec = Vec::new();
+        format.to_writer(&mut vec, &[3, 4]).unwrap();
+
+        assert_eq!(format.from_reader::<_, Vec<u8>>(&vec[..]).unwrap(), [3, 4]);
+    }
+}
@@ -0,0 +1,153 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Writes `bytes` to `path` atomically, creating its parent directories if needed. See
+/// [`atomic_write_with`].
+pub fn atomic_write(path: impl AsRef<Path>, bytes: impl AsRef<[u8]>) -> io::Result<()> {
+    atomic_write_with(path, |file| file.write_all(bytes.as_ref()))
+}
+
+/// Writes `path` by letting `write` fill a temporary file in the same directory, which replaces
+/// `path` only once it was written and synced to disk, so that a crash or power loss leaves
+/// either the old or the new file behind, but never a partially written one.
+///
+/// The temporary file gets the permissions of the file it replaces, and is removed if anything
+/// fails. On unix, the directory is synced as well afterwards, so that the rename itself is
+/// durable; this is best effort, as not every filesystem supports it. On windows, the rename is
+/// retried for a short while if another process, such as a virus scanner, holds the file open.
+pub fn atomic_write_with(path: impl AsRef<Path>, write: impl FnOnce(&mut File) -> io::Result<()>) -> io::Result<()> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let path = path.as_ref();
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file_name = path.file_name().ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "the path has no file name"))?;
+    fs::create_dir_all(parent)?;
+
+    let mut temp_name = file_name.to_owned();
+    temp_name.push(format!(".{}-{}.tmp", process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    let temp = parent.join(temp_name);
+
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp)
+        .and_then(|mut file| {
+            if let Ok(metadata) = fs::metadata(path) {
+                file.set_permissions(metadata.permissions())?;
+            }
+
+            write(&mut file)?;
+            file.sync_all()
+        })
+        .and_then(|()| rename(&temp, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+
+    #[cfg(unix)]
+    if result.is_ok() {
+        let _ = File::open(parent).and_then(|parent| parent.sync_all());
+    }
+
+    result
+}
+
+#[cfg(not(windows))]
+use fs::rename;
+
+#[cfg(windows)]
+fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    const ATTEMPTS: u32 = 5;
+
+    let mut attempt = 1;
+
+    loop {
+        match fs::rename(from, to) {
+            Err(error) if error.kind() == ErrorKind::PermissionDenied && attempt < ATTEMPTS => {
+                std::thread::sleep(std::time::Duration::from_millis(10 << attempt));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{self, Write};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use crate::{atomic_write, atomic_write_with};
+
+    #[test]
+    fn readers_never_see_a_partial_file() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path();
+        let path = dir.join("nested/settings.toml");
+        let old = "a".repeat(1 << 20);
+        let new = "b".repeat(1 << 20);
+        atomic_write(&path, &old).unwrap();
+
+        let done = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    let contents = fs::read_to_string(&path).unwrap();
+
+                    assert!(contents == old || contents == new, "read a partial file of {} bytes", contents.len());
+                }
+            });
+
+            for i in 0..20 {
+                atomic_write(&path, if i % 2 == 0 { &new } else { &old }).unwrap();
+            }
+
+            done.store(true, Ordering::Relaxed);
+        });
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), old);
+        assert_eq!(fs::read_dir(dir.join("nested")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn interrupted_writes_keep_the_original() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path();
+        let path = dir.join("settings.toml");
+        atomic_write(&path, "theme = \"light\"").unwrap();
+
+        let result = atomic_write_with(&path, |file| {
+            file.write_all(b"theme = \"da")?;
+            Err(io::Error::other("disk full"))
+        });
+
+        assert_eq!(result.unwrap_err().to_string(), "disk full");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "theme = \"light\"");
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 1);
+        assert_eq!(atomic_write("..", "").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert!(atomic_write(dir, "").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn permissions_are_preserved() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path();
+        let path = dir.join("secrets.toml");
+        atomic_write(&path, "token = \"old\"").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        atomic_write(&path, "token = \"new\"").unwrap();
+
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "token = \"new\"");
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs::{self, File};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use crate::atomic_write;
+
+/// The path of the backup of `path` with the given index, which is `<name>.<index>.bak` next to
+/// it. Backups are numbered from 1, the most recent one.
+pub fn backup_path(path: impl AsRef<Path>, index: usize) -> PathBuf {
+    let path = path.as_ref();
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(".{index}.bak"));
+
+    path.with_file_name(name)
+}
+
+/// Copies the file at `path` to its first [backup](backup_path) before it is overwritten, keeping
+/// at most `keep` backups: the existing backups are shifted to the next index, and those which
+/// would end up beyond `keep` are deleted. Nothing happens if the file doesn't exist, and a `keep`
+/// of 0 deletes every backup.
+///
+/// The backup keeps the modification time of the file where the platform allows it, so that it
+/// shows when that version was saved.
+pub fn back_up(path: impl AsRef<Path>, keep: usize) -> io::Result<()> {
+    let path = path.as_ref();
+    let modified = match fs::metadata(path) {
+        Ok(metadata) => metadata.modified().ok(),
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    };
+
+    for (index, backup) in backups(path)? {
+        if index >= keep {
+            fs::remove_file(backup)?;
+        }
+    }
+
+    if keep == 0 {
+        return Ok(())
+    }
+
+    for index in (1..keep).rev() {
+        match fs::rename(backup_path(path, index), backup_path(path, index + 1)) {
+            Err(error) if error.kind() != ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+    }
+
+    let backup = backup_path(path, 1);
+    fs::copy(path, &backup)?;
+
+    if let Some(modified) = modified {
+        let _ = File::options().write(true).open(&backup).and_then(|file| file.set_modified(modified));
+    }
+
+    Ok(())
+}
+
+/// Replaces the file at `path` [atomically](atomic_write) with its [backup](backup_path) with the
+/// given index, e.g. to recover from a bad save. The backups themselves are left as they are.
+pub fn restore_backup(path: impl AsRef<Path>, index: usize) -> io::Result<()> {
+    let path = path.as_ref();
+
+    atomic_write(path, fs::read(backup_path(path, index))?)
+}
+
+/// The existing backups of `path` with their indices.
+fn backups(path: &Path) -> io::Result<Vec<(usize, PathBuf)>> {
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let Some(prefix) = path.file_name().and_then(|name| name.to_str()).map(|name| format!("{name}.")) else {
+        return Ok(Vec::new())
+    };
+    let mut backups = Vec::new();
+
+    for entry in fs::read_dir(parent)? {
+        let entry = entry?;
+        let index = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix(&prefix)?.strip_suffix(".bak")?.parse().ok());
+
+        if let Some(index) = index {
+            backups.push((index, entry.path()));
+        }
+    }
+
+    Ok(backups)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::time::{Duration, SystemTime};
+    use crate::{atomic_write, back_up, backup_path, restore_backup};
+
+    #[test]
+    fn rotation_and_pruning() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path();
+        let path = dir.join("settings.toml");
+        let save = |contents: &str, keep| {
+            back_up(&path, keep).unwrap();
+            atomic_write(&path, contents).unwrap();
+        };
+        let read = |index| fs::read_to_string(backup_path(&path, index)).ok();
+
+        save("v1", 3);
+
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 1, "a missing file was backed up");
+
+        for version in 2..=5 {
+            save(&format!("v{version}"), 3);
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v5");
+        assert_eq!([read(1), read(2), read(3), read(4)], [Some("v4".to_owned()), Some("v3".to_owned()), Some("v2".to_owned()), None]);
+
+        save("v6", 3);
+
+        assert_eq!([read(1), read(3), read(4)], [Some("v5".to_owned()), Some("v3".to_owned()), None]);
+
+        save("v7", 1);
+
+        assert_eq!([read(1), read(2)], [Some("v6".to_owned()), None]);
+
+        let saved = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        File::options().write(true).open(&path).unwrap().set_modified(saved).unwrap();
+        save("v8", 1);
+
+        assert_eq!(fs::metadata(backup_path(&path, 1)).unwrap().modified().unwrap(), saved);
+
+        restore_backup(&path, 1).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v7");
+        assert!(restore_backup(&path, 2).is_err());
+
+        save("v9", 0);
+
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 1);
+        assert_eq!(backup_path("config/app.toml", 2), std::path::Path::new("config/app.toml.2.bak"));
+    }
+}
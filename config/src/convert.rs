@@ -0,0 +1,75 @@
+use serde_json::Value;
+use thiserror::Error;
+use crate::error::FormatError;
+use crate::formats::Format;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ConvertError {
+    #[error("failed to read the input as {format}")]
+    Deserialize {
+        format: &'static str,
+        #[source]
+        source: FormatError,
+    },
+
+    #[error("the input can't be represented in {format}")]
+    Serialize {
+        format: &'static str,
+        #[source]
+        source: FormatError,
+    },
+}
+
+/// Converts `input` from the format `Src` to the format `Dst`, e.g. to migrate a config from YAML
+/// to TOML, without knowing its type.
+///
+/// The input goes through a [`serde_json::Value`], so anything JSON can't represent, such as
+/// sequences or maps as map keys in YAML, fails to be read. Likewise, anything `Dst` can't
+/// represent, such as a null in TOML or nested maps in INI, fails to be written. Comments and
+/// formatting are lost, and the keys of maps end up sorted, unless the `bson` feature is enabled,
+/// which makes them keep the order of the input (see [`Bson`](crate::Bson)).
+pub fn convert<Src: Format, Dst: Format>(input: &str) -> Result<String, ConvertError> {
+    let value: Value = Src::from_str_boxed(input)
+        .map_err(|source| ConvertError::Deserialize { format: Src::extension(), source })?;
+
+    Dst::to_string_boxed(&value).map_err(|source| ConvertError::Serialize { format: Dst::extension(), source })
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(feature = "json", feature = "toml"))]
+    #[test]
+    fn json_to_toml() {
+        use crate::{convert, ConvertError, Json, Toml};
+
+        let toml = convert::<Json, Toml>(r#"{ "theme": "dark", "server": { "port": 8080 }, "plugins": ["git"] }"#).unwrap();
+
+        if cfg!(feature = "bson") {
+            assert_eq!(toml, "theme = \"dark\"\nplugins = [\"git\"]\n\n[server]\nport = 8080\n");
+        } else {
+            assert_eq!(toml, "plugins = [\"git\"]\ntheme = \"dark\"\n\n[server]\nport = 8080\n");
+        }
+
+        let error = convert::<Json, Toml>(r#"{ "theme": null }"#).unwrap_err();
+
+        assert!(matches!(error, ConvertError::Serialize { format: "toml", .. }));
+        assert_eq!(error.to_string(), "the input can't be represented in toml");
+        assert!(matches!(convert::<Json, Toml>("{"), Err(ConvertError::Deserialize { format: "json", .. })));
+    }
+
+    #[cfg(all(feature = "yaml", feature = "json"))]
+    #[test]
+    fn yaml_to_json() {
+        use crate::{convert, ConvertError, Json, Yaml};
+
+        let json = convert::<Yaml, Json>("theme: dark\nserver:\n  port: 8080\nplugins:\n  - git\n").unwrap();
+
+        if cfg!(feature = "bson") {
+            assert_eq!(json, r#"{"theme":"dark","server":{"port":8080},"plugins":["git"]}"#);
+        } else {
+            assert_eq!(json, r#"{"plugins":["git"],"server":{"port":8080},"theme":"dark"}"#);
+        }
+        assert!(matches!(convert::<Yaml, Json>("[1, 2]: pair"), Err(ConvertError::Deserialize { format: "yaml", .. })));
+    }
+}
@@ -0,0 +1,197 @@
+use std::error::Error;
+use std::io;
+use std::str::Utf8Error;
+use thiserror::Error;
+use crate::formats::{FromReaderError, FromSliceError, ToWriterError};
+
+macro_rules! format_error {
+    (
+        // errors which are large enough to bloat every `FormatError`, stored in a box
+        box {
+            $($boxed_variant:ident($boxed_ty:ty): $boxed_feature:literal;)*
+        }
+        $($variant:ident($ty:ty): $feature:literal;)*
+    ) => {
+        /// An error of any format, for code which is generic over the [`Format`](crate::Format)
+        /// and wants a single error type. See [`Format::from_str_boxed`](crate::Format::from_str_boxed).
+        ///
+        /// Errors of the built-in formats get their own variant, available with the feature of the
+        /// format. Errors of other formats end up in [`Other`](Self::Other).
+        #[derive(Error, Debug)]
+        #[non_exhaustive]
+        pub enum FormatError {
+            #[error("failed to read or write the data")]
+            Io(#[from] io::Error),
+
+            #[error("the data is not valid UTF-8")]
+            Utf8(#[from] Utf8Error),
+
+            $(
+            #[cfg(feature = $feature)]
+            #[error(transparent)]
+            $variant(#[from] $ty),
+            )*
+
+            $(
+            #[cfg(feature = $boxed_feature)]
+            #[error(transparent)]
+            $boxed_variant(Box<$boxed_ty>),
+            )*
+
+            #[error(transparent)]
+            Other(Box<dyn Error + Send + Sync>),
+        }
+
+        impl FormatError {
+            /// Wraps `error`, picking its variant by its type.
+            pub fn new(error: impl Error + Send + Sync + 'static) -> Self {
+                let error: Box<dyn Error + Send + Sync> = Box::new(error);
+                let error = match error.downcast::<Self>() {
+                    Ok(error) => return *error,
+                    Err(error) => error,
+                };
+                let error = match error.downcast::<io::Error>() {
+                    Ok(error) => return Self::Io(*error),
+                    Err(error) => error,
+                };
+                let error = match error.downcast::<Utf8Error>() {
+                    Ok(error) => return Self::Utf8(*error),
+                    Err(error) => error,
+                };
+                $(
+                #[cfg(feature = $feature)]
+                let error = match error.downcast::<$ty>() {
+                    Ok(error) => return Self::$variant(*error),
+                    Err(error) => error,
+                };
+                )*
+                $(
+                #[cfg(feature = $boxed_feature)]
+                let error = match error.downcast::<$boxed_ty>() {
+                    Ok(error) => return Self::$boxed_variant(error),
+                    Err(error) => error,
+                };
+                )*
+
+                Self::Other(error)
+            }
+        }
+
+        $(
+        #[cfg(feature = $boxed_feature)]
+        impl From<$boxed_ty> for FormatError {
+            fn from(value: $boxed_ty) -> Self {
+                Self::$boxed_variant(Box::new(value))
+            }
+        }
+        )*
+    };
+}
+
+format_error! {
+    box {
+        BsonSerialize(crate::BinaryError<bson::ser::Error>): "bson";
+        DhallDeserialize(serde_dhall::Error): "dhall";
+    }
+
+    TomlSerialize(toml::ser::Error): "toml";
+    TomlDeserialize(toml::de::Error): "toml";
+    Json(serde_json::Error): "json";
+    Yaml(serde_yaml::Error): "yaml";
+    IniSerialize(serde_ini::ser::Error): "ini";
+    IniDeserialize(serde_ini::de::Error): "ini";
+    RonSerialize(ron::Error): "ron";
+    RonDeserialize(ron::de::SpannedError): "ron";
+    Json5(json5::Error): "json5";
+    Xml(quick_xml::DeError): "xml";
+    CborSerialize(crate::BinaryError<ciborium::ser::Error<io::Error>>): "cbor";
+    CborDeserialize(crate::BinaryError<ciborium::de::Error<io::Error>>): "cbor";
+    MessagePackSerialize(crate::BinaryError<rmp_serde::encode::Error>): "messagepack";
+    MessagePackDeserialize(crate::BinaryError<rmp_serde::decode::Error>): "messagepack";
+    Bincode(crate::BinaryError<bincode::Error>): "bincode";
+    Bencode(serde_bencode::Error): "bencode";
+    BsonDeserialize(crate::BinaryError<bson::de::Error>): "bson";
+    Csv(crate::CsvError): "csv";
+    DhallSerialize(crate::DhallSerializeError): "dhall";
+    Postcard(crate::BinaryError<postcard::Error>): "postcard";
+    VersionedPostcard(crate::BinaryError<crate::PostcardError>): "postcard";
+    Properties(crate::PropertiesError): "properties";
+    KdlSerialize(crate::KdlSerializeError): "kdl";
+    KdlDeserialize(crate::KdlDeserializeError): "kdl";
+    Hcl(hcl::Error): "hcl";
+}
+
+impl<E: Error + Send + Sync + 'static> From<FromSliceError<E>> for FormatError {
+    fn from(value: FromSliceError<E>) -> Self {
+        match value {
+            FromSliceError::Utf8(error) => Self::Utf8(error),
+            FromSliceError::Deserialize(error) => Self::new(error),
+        }
+    }
+}
+
+impl<E: Error + Send + Sync + 'static> From<FromReaderError<E>> for FormatError {
+    fn from(value: FromReaderError<E>) -> Self {
+        match value {
+            FromReaderError::Io(error) => Self::Io(error),
+            FromReaderError::Utf8(error) => Self::Utf8(error),
+            FromReaderError::Deserialize(error) => Self::new(error),
+        }
+    }
+}
+
+impl<E: Error + Send + Sync + 'static> From<ToWriterError<E>> for FormatError {
+    fn from(value: ToWriterError<E>) -> Self {
+        match value {
+            ToWriterError::Io(error) => Self::Io(error),
+            ToWriterError::Serialize(error) => Self::new(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use crate::FormatError;
+
+    #[derive(Debug)]
+    struct Custom;
+
+    impl fmt::Display for Custom {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("custom")
+        }
+    }
+
+    impl std::error::Error for Custom {}
+
+    #[test]
+    fn unknown_errors_are_other() {
+        let error = FormatError::new(Custom);
+
+        assert!(matches!(error, FormatError::Other(_)));
+        assert_eq!(error.to_string(), "custom");
+        assert!(matches!(FormatError::new(error), FormatError::Other(_)));
+    }
+
+    #[cfg(all(feature = "toml", feature = "json"))]
+    #[test]
+    fn deserialize_failures_match_their_format() {
+        use crate::{Format, Json, JsonPretty, Toml};
+
+        assert!(matches!(Toml::from_str_boxed::<u8>("= 1"), Err(FormatError::TomlDeserialize(_))));
+        assert!(matches!(Json::from_str_boxed::<u8>("["), Err(FormatError::Json(_))));
+        assert!(matches!(JsonPretty::from_str_boxed::<u8>("["), Err(FormatError::Json(_))));
+        assert!(matches!(FormatError::from(Json::from_slice::<u8>(b"\xff").unwrap_err()), FormatError::Utf8(_)));
+    }
+
+    #[cfg(feature = "bson")]
+    #[test]
+    fn large_errors_are_boxed() {
+        use crate::{Bson, Format};
+
+        assert!(matches!(Bson::to_string_boxed(&[1, 2]), Err(FormatError::BsonSerialize(_))));
+        assert!(matches!(Bson::from_str_boxed::<u8>("\0"), Err(FormatError::BsonDeserialize(_))));
+        assert!(matches!(FormatError::from(Bson::to_vec(&[1, 2]).unwrap_err()), FormatError::BsonSerialize(_)));
+    }
+}
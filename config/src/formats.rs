@@ -1,6 +1,11 @@
 use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::{self, Utf8Error};
 use serde::Serialize;
 use serde::de::DeserializeOwned;
+use crate::error::FormatError;
 
 #[cfg(feature = "toml")]
 mod toml {
@@ -8,12 +13,21 @@ mod toml {
     use serde::Serialize;
     use crate::formats::Format;
 
+    /// Compact TOML, e.g. for the wire. See [`TomlPretty`] for configs meant for humans.
     pub enum Toml {}
 
     impl Format for Toml {
         type SerializeError = toml::ser::Error;
         type DeserializeError = toml::de::Error;
 
+        fn extension() -> &'static str {
+            "toml"
+        }
+
+        fn content_type() -> &'static str {
+            "application/toml"
+        }
+
         fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
             toml::from_str(s)
         }
@@ -21,24 +35,88 @@ mod toml {
         fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
             toml::to_string(t)
         }
+
+        fn to_string_canonical<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            toml::to_string(&sorted(toml::Value::try_from(t)?))
+        }
+    }
+
+    /// Like [`Toml`], but pretty printed, e.g. with arrays spread over several lines. Nested
+    /// structs and arrays of structs are expanded into `[section]` and `[[array]]` headers rather
+    /// than inline tables, and deserialization is the same as [`Toml`].
+    pub enum TomlPretty {}
+
+    impl Format for TomlPretty {
+        type SerializeError = toml::ser::Error;
+        type DeserializeError = toml::de::Error;
+
+        fn extension() -> &'static str {
+            Toml::extension()
+        }
+
+        fn content_type() -> &'static str {
+            Toml::content_type()
+        }
+
+        fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+            Toml::from_str(s)
+        }
+
+        fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            toml::to_string_pretty(t)
+        }
+
+        fn to_string_canonical<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            toml::to_string_pretty(&sorted(toml::Value::try_from(t)?))
+        }
+    }
+
+    /// Sorts the keys of every table in `value`, which may keep their insertion order otherwise.
+    fn sorted(value: toml::Value) -> toml::Value {
+        match value {
+            toml::Value::Table(table) => {
+                let mut entries: Vec<_> = table.into_iter().map(|(key, value)| (key, sorted(value))).collect();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+                toml::Value::Table(entries.into_iter().collect())
+            }
+            toml::Value::Array(array) => toml::Value::Array(array.into_iter().map(sorted).collect()),
+            value => value,
+        }
     }
 }
 
 #[cfg(feature = "toml")]
-pub use toml::Toml;
+pub use toml::{Toml, TomlPretty};
+
+#[cfg(feature = "toml")]
+mod preserve;
+
+#[cfg(feature = "toml")]
+pub use preserve::SavePreservingError;
 
 #[cfg(feature = "json")]
 mod json {
+    use std::io::{Read, Write};
     use serde::de::DeserializeOwned;
     use serde::Serialize;
-    use crate::formats::Format;
+    use crate::formats::{Format, FromReaderError, ToWriterError};
 
+    /// Compact JSON, e.g. for the wire. See [`JsonPretty`] for configs meant for humans.
     pub enum Json {}
 
     impl Format for Json {
         type SerializeError = serde_json::Error;
         type DeserializeError = serde_json::Error;
 
+        fn extension() -> &'static str {
+            "json"
+        }
+
+        fn content_type() -> &'static str {
+            "application/json"
+        }
+
         fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
             serde_json::from_str(s)
         }
@@ -46,16 +124,78 @@ mod json {
         fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
             serde_json::to_string(t)
         }
+
+        fn to_string_canonical<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            serde_json::to_string(&sorted(t)?)
+        }
+
+        fn from_reader<R: Read, T: DeserializeOwned>(r: R) -> Result<T, FromReaderError<Self::DeserializeError>> {
+            serde_json::from_reader(r).map_err(FromReaderError::Deserialize)
+        }
+
+        fn to_writer<W: Write, T: Serialize>(w: W, t: &T) -> Result<(), ToWriterError<Self::SerializeError>> {
+            serde_json::to_writer(w, t).map_err(ToWriterError::Serialize)
+        }
+    }
+
+    /// Like [`Json`], but pretty printed with newlines and indentation, and ending with a newline
+    /// like files edited by hand. Deserialization is the same as [`Json`].
+    pub enum JsonPretty {}
+
+    impl Format for JsonPretty {
+        type SerializeError = serde_json::Error;
+        type DeserializeError = serde_json::Error;
+
+        fn extension() -> &'static str {
+            Json::extension()
+        }
+
+        fn content_type() -> &'static str {
+            Json::content_type()
+        }
+
+        fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+            Json::from_str(s)
+        }
+
+        fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            serde_json::to_string_pretty(t).map(|s| s + "\n")
+        }
+
+        fn to_string_canonical<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            Self::to_string(&sorted(t)?)
+        }
+
+        fn from_reader<R: Read, T: DeserializeOwned>(r: R) -> Result<T, FromReaderError<Self::DeserializeError>> {
+            Json::from_reader(r)
+        }
+
+        fn to_writer<W: Write, T: Serialize>(w: W, t: &T) -> Result<(), ToWriterError<Self::SerializeError>> {
+            let mut w = w;
+            serde_json::to_writer_pretty(&mut w, t).map_err(ToWriterError::Serialize)?;
+
+            Ok(w.write_all(b"\n")?)
+        }
+    }
+
+    /// `t` as a value whose objects are sorted by key, as they may keep their insertion order.
+    fn sorted<T: Serialize>(t: &T) -> serde_json::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(t)?;
+        value.sort_all_objects();
+
+        Ok(value)
     }
 }
 
 #[cfg(feature = "json")]
-pub use json::Json;
+pub use json::{Json, JsonPretty};
 
 #[cfg(feature = "yaml")]
 mod yaml {
+    use std::cmp::Ordering;
     use serde::de::DeserializeOwned;
     use serde::Serialize;
+    use serde_yaml::Value;
     use crate::formats::Format;
 
     pub enum Yaml {}
@@ -64,6 +204,14 @@ mod yaml {
         type SerializeError = serde_yaml::Error;
         type DeserializeError = serde_yaml::Error;
 
+        fn extension() -> &'static str {
+            "yaml"
+        }
+
+        fn content_type() -> &'static str {
+            "application/yaml"
+        }
+
         fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
             serde_yaml::from_str(s)
         }
@@ -71,6 +219,47 @@ mod yaml {
         fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
             serde_yaml::to_string(t)
         }
+
+        fn to_string_canonical<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            serde_yaml::to_string(&sorted(serde_yaml::to_value(t)?))
+        }
+    }
+
+    /// Sorts the keys of every mapping in `value`, which keep their insertion order otherwise.
+    fn sorted(value: Value) -> Value {
+        match value {
+            Value::Mapping(mapping) => {
+                let mut entries: Vec<_> = mapping.into_iter().map(|(key, value)| (sorted(key), sorted(value))).collect();
+                entries.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+                Value::Mapping(entries.into_iter().collect())
+            }
+            Value::Sequence(sequence) => Value::Sequence(sequence.into_iter().map(sorted).collect()),
+            Value::Tagged(mut tagged) => {
+                tagged.value = sorted(tagged.value);
+
+                Value::Tagged(tagged)
+            }
+            value => value,
+        }
+    }
+
+    impl Yaml {
+        /// Deserializes every document of a stream of `---`-separated documents, in order.
+        pub fn from_str_multi<T: DeserializeOwned>(s: &str) -> Result<Vec<T>, serde_yaml::Error> {
+            serde_yaml::Deserializer::from_str(s).map(T::deserialize).collect()
+        }
+
+        /// Serializes every value as a separate document, separated by `---`.
+        pub fn to_string_multi<T: Serialize>(ts: &[T]) -> Result<String, serde_yaml::Error> {
+            let mut serializer = serde_yaml::Serializer::new(Vec::new());
+
+            for t in ts {
+                t.serialize(&mut serializer)?;
+            }
+
+            Ok(String::from_utf8(serializer.into_inner()?).expect("serde_yaml produced invalid UTF-8"))
+        }
     }
 }
 
@@ -89,6 +278,14 @@ mod ini {
         type SerializeError = serde_ini::ser::Error;
         type DeserializeError = serde_ini::de::Error;
 
+        fn extension() -> &'static str {
+            "ini"
+        }
+
+        fn content_type() -> &'static str {
+            "application/x-ini"
+        }
+
         fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
             serde_ini::from_str(s)
         }
@@ -114,6 +311,14 @@ mod ron {
         type SerializeError = ron::Error;
         type DeserializeError = ron::de::SpannedError;
 
+        fn extension() -> &'static str {
+            "ron"
+        }
+
+        fn content_type() -> &'static str {
+            "application/x-ron"
+        }
+
         fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
             ron::from_str(s)
         }
@@ -139,6 +344,14 @@ mod json5 {
         type SerializeError = json5::Error;
         type DeserializeError = json5::Error;
 
+        fn extension() -> &'static str {
+            "json5"
+        }
+
+        fn content_type() -> &'static str {
+            "application/json5"
+        }
+
         fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
             json5::from_str(s)
         }
@@ -152,10 +365,1434 @@ mod json5 {
 #[cfg(feature = "json5")]
 pub use json5::Json5;
 
-pub trait Format {
-    type SerializeError: Error;
-    type DeserializeError: Error;
+#[cfg(feature = "xml")]
+mod xml {
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use crate::formats::Format;
 
-    fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError>;
-    fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError>;
+    /// The root element is named after the serialized type.
+    pub enum Xml {}
+
+    impl Format for Xml {
+        type SerializeError = quick_xml::DeError;
+        type DeserializeError = quick_xml::DeError;
+
+        fn extension() -> &'static str {
+            "xml"
+        }
+
+        fn content_type() -> &'static str {
+            "application/xml"
+        }
+
+        fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+            quick_xml::de::from_str(s)
+        }
+
+        fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            quick_xml::se::to_string(t)
+        }
+    }
+}
+
+#[cfg(feature = "xml")]
+pub use xml::Xml;
+
+#[cfg(feature = "cbor")]
+mod cbor {
+    use std::io::{self, Read, Write};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use crate::formats::{BinaryError, Format, FromReaderError, FromSliceError, ToWriterError};
+
+    /// A binary format, so [`to_string`](Format::to_string) and [`from_str`](Format::from_str)
+    /// always fail with [`BinaryError::NotText`]. Use [`to_vec`](Format::to_vec) and
+    /// [`from_slice`](Format::from_slice) instead.
+    pub enum Cbor {}
+
+    impl Format for Cbor {
+        type SerializeError = BinaryError<ciborium::ser::Error<io::Error>>;
+        type DeserializeError = BinaryError<ciborium::de::Error<io::Error>>;
+
+        fn extension() -> &'static str {
+            "cbor"
+        }
+
+        fn content_type() -> &'static str {
+            "application/cbor"
+        }
+
+        fn from_str<T: DeserializeOwned>(_s: &str) -> Result<T, Self::DeserializeError> {
+            Err(BinaryError::NotText { format: "CBOR" })
+        }
+
+        fn to_string<T: Serialize>(_t: &T) -> Result<String, Self::SerializeError> {
+            Err(BinaryError::NotText { format: "CBOR" })
+        }
+
+        fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+            ciborium::from_reader(s).map_err(|error| FromSliceError::Deserialize(BinaryError::Format(error)))
+        }
+
+        fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+            let mut vec = Vec::new();
+            ciborium::into_writer(t, &mut vec).map_err(BinaryError::Format)?;
+
+            Ok(vec)
+        }
+
+        fn from_reader<R: Read, T: DeserializeOwned>(r: R) -> Result<T, FromReaderError<Self::DeserializeError>> {
+            ciborium::from_reader(r).map_err(|error| FromReaderError::Deserialize(BinaryError::Format(error)))
+        }
+
+        fn to_writer<W: Write, T: Serialize>(w: W, t: &T) -> Result<(), ToWriterError<Self::SerializeError>> {
+            ciborium::into_writer(t, w).map_err(|error| ToWriterError::Serialize(BinaryError::Format(error)))
+        }
+    }
+}
+
+#[cfg(feature = "cbor")]
+pub use cbor::Cbor;
+
+#[cfg(feature = "messagepack")]
+mod messagepack {
+    use std::io::{Read, Write};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use crate::formats::{BinaryError, Format, FromReaderError, FromSliceError, ToWriterError};
+
+    /// A binary format, so [`to_string`](Format::to_string) and [`from_str`](Format::from_str)
+    /// always fail with [`BinaryError::NotText`]. Use [`to_vec`](Format::to_vec) and
+    /// [`from_slice`](Format::from_slice) instead.
+    ///
+    /// Structs are serialized as maps keyed by field name rather than as arrays, so fields can be
+    /// reordered without breaking existing configs.
+    pub enum MessagePack {}
+
+    impl Format for MessagePack {
+        type SerializeError = BinaryError<rmp_serde::encode::Error>;
+        type DeserializeError = BinaryError<rmp_serde::decode::Error>;
+
+        fn extension() -> &'static str {
+            "msgpack"
+        }
+
+        fn content_type() -> &'static str {
+            "application/msgpack"
+        }
+
+        fn from_str<T: DeserializeOwned>(_s: &str) -> Result<T, Self::DeserializeError> {
+            Err(BinaryError::NotText { format: "MessagePack" })
+        }
+
+        fn to_string<T: Serialize>(_t: &T) -> Result<String, Self::SerializeError> {
+            Err(BinaryError::NotText { format: "MessagePack" })
+        }
+
+        fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+            rmp_serde::from_slice(s).map_err(|error| FromSliceError::Deserialize(BinaryError::Format(error)))
+        }
+
+        fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+            rmp_serde::to_vec_named(t).map_err(BinaryError::Format)
+        }
+
+        fn from_reader<R: Read, T: DeserializeOwned>(r: R) -> Result<T, FromReaderError<Self::DeserializeError>> {
+            rmp_serde::from_read(r).map_err(|error| FromReaderError::Deserialize(BinaryError::Format(error)))
+        }
+
+        fn to_writer<W: Write, T: Serialize>(mut w: W, t: &T) -> Result<(), ToWriterError<Self::SerializeError>> {
+            rmp_serde::encode::write_named(&mut w, t).map_err(|error| ToWriterError::Serialize(BinaryError::Format(error)))
+        }
+    }
+}
+
+#[cfg(feature = "messagepack")]
+pub use messagepack::MessagePack;
+
+#[cfg(feature = "bincode")]
+mod bincode {
+    use std::io::{Read, Write};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use crate::formats::{BinaryError, Format, FromReaderError, FromSliceError, ToWriterError};
+
+    /// A compact binary format without any schema information, so the data must be deserialized
+    /// into the same type it was serialized from. Being binary, [`to_string`](Format::to_string)
+    /// and [`from_str`](Format::from_str) always fail with [`BinaryError::NotText`], so use
+    /// [`to_vec`](Format::to_vec) and [`from_slice`](Format::from_slice) instead.
+    pub enum Bincode {}
+
+    impl Format for Bincode {
+        type SerializeError = BinaryError<bincode::Error>;
+        type DeserializeError = BinaryError<bincode::Error>;
+
+        fn extension() -> &'static str {
+            "bincode"
+        }
+
+        fn content_type() -> &'static str {
+            "application/octet-stream"
+        }
+
+        fn from_str<T: DeserializeOwned>(_s: &str) -> Result<T, Self::DeserializeError> {
+            Err(BinaryError::NotText { format: "bincode" })
+        }
+
+        fn to_string<T: Serialize>(_t: &T) -> Result<String, Self::SerializeError> {
+            Err(BinaryError::NotText { format: "bincode" })
+        }
+
+        fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+            bincode::deserialize(s).map_err(|error| FromSliceError::Deserialize(BinaryError::Format(error)))
+        }
+
+        fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+            bincode::serialize(t).map_err(BinaryError::Format)
+        }
+
+        fn from_reader<R: Read, T: DeserializeOwned>(r: R) -> Result<T, FromReaderError<Self::DeserializeError>> {
+            bincode::deserialize_from(r).map_err(|error| FromReaderError::Deserialize(BinaryError::Format(error)))
+        }
+
+        fn to_writer<W: Write, T: Serialize>(w: W, t: &T) -> Result<(), ToWriterError<Self::SerializeError>> {
+            bincode::serialize_into(w, t).map_err(|error| ToWriterError::Serialize(BinaryError::Format(error)))
+        }
+    }
+}
+
+#[cfg(feature = "bincode")]
+pub use self::bincode::Bincode;
+
+#[cfg(feature = "bencode")]
+mod bencode {
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use crate::formats::{Format, FromSliceError};
+
+    /// The encoding of BitTorrent metainfo files, e.g. for tooling reading or writing `.torrent`
+    /// files. Strings in bencode are byte strings, so use [`to_vec`](Format::to_vec) and
+    /// [`from_slice`](Format::from_slice) for data which isn't UTF-8, and `serde_bytes` to
+    /// (de)serialize byte fields as byte strings rather than lists of integers.
+    ///
+    /// Bencode only has integers, byte strings, lists and dictionaries, so floats can't be
+    /// serialized, and the keys of dictionaries must be strings.
+    pub enum Bencode {}
+
+    impl Format for Bencode {
+        type SerializeError = serde_bencode::Error;
+        type DeserializeError = serde_bencode::Error;
+
+        fn extension() -> &'static str {
+            "torrent"
+        }
+
+        fn content_type() -> &'static str {
+            "application/x-bittorrent"
+        }
+
+        fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+            serde_bencode::from_str(s)
+        }
+
+        fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            serde_bencode::to_string(t)
+        }
+
+        fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+            serde_bencode::from_bytes(s).map_err(FromSliceError::Deserialize)
+        }
+
+        fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+            serde_bencode::to_bytes(t)
+        }
+    }
+}
+
+#[cfg(feature = "bencode")]
+pub use self::bencode::Bencode;
+
+#[cfg(feature = "bson")]
+mod bson {
+    use std::io::{Read, Write};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use crate::formats::{BinaryError, Format, FromReaderError, FromSliceError, ToWriterError};
+
+    /// A binary format whose root must be a document, i.e. a struct or a map. Like any binary
+    /// format, [`to_string`](Format::to_string) and [`from_str`](Format::from_str) always fail
+    /// with [`BinaryError::NotText`], so use [`to_vec`](Format::to_vec) and
+    /// [`from_slice`](Format::from_slice) instead.
+    ///
+    /// The `bson` crate enables the `preserve_order` feature of `serde_json`, so with the `bson`
+    /// feature, maps of a [`serde_json::Value`] keep the order they were read in instead of being
+    /// sorted by key, which changes the output of e.g. [`convert`](crate::convert).
+    pub enum Bson {}
+
+    impl Format for Bson {
+        type SerializeError = BinaryError<bson::ser::Error>;
+        type DeserializeError = BinaryError<bson::de::Error>;
+
+        fn extension() -> &'static str {
+            "bson"
+        }
+
+        fn content_type() -> &'static str {
+            "application/bson"
+        }
+
+        fn from_str<T: DeserializeOwned>(_s: &str) -> Result<T, Self::DeserializeError> {
+            Err(BinaryError::NotText { format: "BSON" })
+        }
+
+        fn to_string<T: Serialize>(_t: &T) -> Result<String, Self::SerializeError> {
+            Err(BinaryError::NotText { format: "BSON" })
+        }
+
+        fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+            bson::from_slice(s).map_err(|error| FromSliceError::Deserialize(BinaryError::Format(error)))
+        }
+
+        fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+            bson::to_vec(t).map_err(BinaryError::Format)
+        }
+
+        fn from_reader<R: Read, T: DeserializeOwned>(r: R) -> Result<T, FromReaderError<Self::DeserializeError>> {
+            bson::from_reader(r).map_err(|error| FromReaderError::Deserialize(BinaryError::Format(error)))
+        }
+
+        fn to_writer<W: Write, T: Serialize>(mut w: W, t: &T) -> Result<(), ToWriterError<Self::SerializeError>> {
+            Ok(w.write_all(&Self::to_vec(t).map_err(ToWriterError::Serialize)?)?)
+        }
+    }
+}
+
+#[cfg(feature = "bson")]
+pub use self::bson::Bson;
+
+#[cfg(any(feature = "csv", feature = "properties"))]
+mod untyped;
+
+#[cfg(feature = "csv")]
+mod csv;
+
+#[cfg(feature = "csv")]
+pub use self::csv::{Csv, CsvError};
+
+#[cfg(feature = "dhall")]
+mod dhall {
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use crate::formats::Format;
+
+    /// A typed, programmable config language. Configs are fully evaluated while deserializing, so
+    /// they may use `let` bindings, functions and local imports, e.g. to apply defaults with the
+    /// `//` operator, as long as they evaluate to a value of the deserialized type.
+    ///
+    /// This format is read only: serializing always fails with
+    /// [`DhallSerializeError::Unsupported`], as values can't be mapped to Dhall without knowing
+    /// their Dhall type, which serde doesn't provide, e.g. for `None`, empty sequences and enums.
+    pub enum Dhall {}
+
+    /// The error of serializing to [`Dhall`], which isn't supported.
+    #[derive(thiserror::Error, Debug)]
+    #[non_exhaustive]
+    pub enum DhallSerializeError {
+        #[error("serializing to Dhall isn't supported; Dhall configs can only be read")]
+        Unsupported,
+    }
+
+    impl Format for Dhall {
+        type SerializeError = DhallSerializeError;
+        type DeserializeError = serde_dhall::Error;
+
+        fn extension() -> &'static str {
+            "dhall"
+        }
+
+        fn content_type() -> &'static str {
+            "application/x-dhall"
+        }
+
+        fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+            serde_dhall::from_str(s).parse()
+        }
+
+        fn to_string<T: Serialize>(_t: &T) -> Result<String, Self::SerializeError> {
+            Err(DhallSerializeError::Unsupported)
+        }
+    }
+}
+
+#[cfg(feature = "dhall")]
+pub use self::dhall::{Dhall, DhallSerializeError};
+
+#[cfg(feature = "postcard")]
+mod postcard;
+
+#[cfg(feature = "postcard")]
+pub use self::postcard::{Postcard, PostcardError, VersionedPostcard};
+
+#[cfg(feature = "properties")]
+mod properties;
+
+#[cfg(feature = "properties")]
+pub use self::properties::{Properties, PropertiesError};
+
+#[cfg(any(test, feature = "test-util"))]
+mod roundtrip;
+
+#[cfg(any(test, feature = "test-util"))]
+pub use roundtrip::assert_roundtrip;
+
+#[cfg(feature = "kdl")]
+mod kdl;
+
+#[cfg(feature = "kdl")]
+pub use kdl::{Kdl, KdlDeserializeError, KdlSerializeError};
+
+#[cfg(feature = "hcl")]
+mod hcl {
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use crate::formats::Format;
+
+    /// HashiCorp's configuration language, as used by e.g. Terraform.
+    ///
+    /// Blocks are read as maps, nested in maps keyed by their labels if they have any. Repeated
+    /// blocks with the same name and labels are read as a sequence of maps instead, so a field
+    /// holding a block which may be repeated has to accept both. Nested structs are written as
+    /// attributes holding objects, unless serialized as blocks with [`hcl::ser::block`] or
+    /// [`hcl::ser::labeled_block`].
+    ///
+    /// Expressions which can't be evaluated without a context, such as variables and function
+    /// calls, are read as interpolation strings, e.g. `var.region` as `"${var.region}"`. Comments
+    /// and the order of blocks relative to attributes are lost in a round trip.
+    pub enum Hcl {}
+
+    impl Format for Hcl {
+        type SerializeError = hcl::Error;
+        type DeserializeError = hcl::Error;
+
+        fn extension() -> &'static str {
+            "hcl"
+        }
+
+        fn content_type() -> &'static str {
+            "application/x-hcl"
+        }
+
+        fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+            hcl::from_str(s)
+        }
+
+        fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            hcl::to_string(t)
+        }
+    }
+}
+
+#[cfg(feature = "hcl")]
+pub use self::hcl::Hcl;
+
+#[cfg(feature = "gzip")]
+mod gzip;
+
+#[cfg(feature = "gzip")]
+pub use gzip::{Gzipped, GzippedError};
+
+#[cfg(feature = "base64")]
+mod base64;
+
+#[cfg(feature = "base64")]
+pub use self::base64::{Base64, Base64Error};
+
+#[cfg(feature = "encryption")]
+mod encrypted;
+
+#[cfg(feature = "encryption")]
+pub use encrypted::{Encrypted, EncryptedError, EncryptionKey};
+
+/// The error of a binary format, such as [`Cbor`](crate::Cbor) or [`Bincode`](crate::Bincode),
+/// which is either an error of its backend, or [`NotText`](Self::NotText).
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum BinaryError<E> {
+    /// [`to_string`](Format::to_string) or [`from_str`](Format::from_str) was used, which always
+    /// fail for a binary format, as its data isn't text, even if it happens to be valid UTF-8. Use
+    /// [`to_vec`](Format::to_vec) and [`from_slice`](Format::from_slice) instead.
+    #[error("{format} is a binary format, so its data can't be a string")]
+    NotText { format: &'static str },
+
+    #[error(transparent)]
+    Format(E),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FromSliceError<E> {
+    #[error("the data is not valid UTF-8")]
+    Utf8(#[from] Utf8Error),
+
+    #[error(transparent)]
+    Deserialize(E),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FromReaderError<E> {
+    #[error("failed to read the data")]
+    Io(#[from] io::Error),
+
+    #[error("the data is not valid UTF-8")]
+    Utf8(#[from] Utf8Error),
+
+    #[error(transparent)]
+    Deserialize(E),
+}
+
+impl<E> From<FromSliceError<E>> for FromReaderError<E> {
+    fn from(value: FromSliceError<E>) -> Self {
+        match value {
+            FromSliceError::Utf8(error) => Self::Utf8(error),
+            FromSliceError::Deserialize(error) => Self::Deserialize(error),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ToWriterError<E> {
+    #[error("failed to write the data")]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Serialize(E),
+}
+
+/// An error of [`Format::load_path`].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum LoadPathError<E> {
+    #[error("failed to read '{}'", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to deserialize '{}'", path.display())]
+    Deserialize {
+        path: PathBuf,
+        #[source]
+        source: FromSliceError<E>,
+    },
+}
+
+/// An error of [`Format::save_path`].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum SavePathError<E> {
+    #[error("failed to write '{}'", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to serialize '{}'", path.display())]
+    Serialize {
+        path: PathBuf,
+        #[source]
+        source: E,
+    },
+}
+
+/// Reads and deserializes the file at `path` in the format `F`, e.g. for an ad-hoc file outside of
+/// the usual config directories. Like [`Format::load_path`], but the error of the format is a
+/// [`FormatError`], so the error is the same type for every format.
+pub fn read_from_path<F: Format, T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, LoadPathError<FormatError>> {
+    F::load_path(path).map_err(|error| match error {
+        LoadPathError::Read { path, source } => LoadPathError::Read { path, source },
+        LoadPathError::Deserialize { path, source } => LoadPathError::Deserialize {
+            path,
+            source: match source {
+                FromSliceError::Utf8(error) => FromSliceError::Utf8(error),
+                FromSliceError::Deserialize(error) => FromSliceError::Deserialize(FormatError::new(error)),
+            },
+        },
+    })
+}
+
+/// Serializes `t` in the format `F` to the file at `path`. Like [`Format::save_path`], the file is
+/// written atomically and its parent directories are created if needed, but the error of the
+/// format is a [`FormatError`], so the error is the same type for every format.
+pub fn write_to_path<F: Format, T: Serialize>(path: impl AsRef<Path>, t: &T) -> Result<(), SavePathError<FormatError>> {
+    F::save_path(path, t).map_err(|error| match error {
+        SavePathError::Write { path, source } => SavePathError::Write { path, source },
+        SavePathError::Serialize { path, source } => SavePathError::Serialize { path, source: FormatError::new(source) },
+    })
+}
+
+/// A serialization format for configs.
+///
+/// Text formats only implement [`from_str`](Self::from_str) and [`to_string`](Self::to_string);
+/// the bytes-based methods bridge to them through UTF-8. Binary formats also override
+/// [`from_slice`](Self::from_slice) and [`to_vec`](Self::to_vec), as their data can't go through a
+/// string.
+///
+/// Likewise, [`from_reader`](Self::from_reader) and [`to_writer`](Self::to_writer) buffer the whole
+/// data by default, while formats whose library can stream override them. Readers and writers
+/// aren't buffered by these methods, so wrap e.g. files in a [`BufReader`](std::io::BufReader) or
+/// [`BufWriter`](std::io::BufWriter).
+pub trait Format {
+    type SerializeError: Error + Send + Sync + 'static;
+    type DeserializeError: Error + Send + Sync + 'static;
+
+    /// The canonical file extension of the format, without the leading dot.
+    fn extension() -> &'static str;
+
+    /// The MIME type of the format, e.g. for the `Content-Type` header when serving a config over
+    /// HTTP. Formats without a registered type of their own use `application/octet-stream`, i.e.
+    /// arbitrary binary data.
+    fn content_type() -> &'static str;
+
+    fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError>;
+    fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError>;
+
+    /// Like [`to_string`](Self::to_string), but the keys of maps are sorted, so that equal values
+    /// serialize the same regardless of the order of their maps, e.g. to diff or hash configs.
+    ///
+    /// Only [`Json`], [`JsonPretty`], [`Toml`], [`TomlPretty`] and [`Yaml`] sort keys. Every other
+    /// format serializes like `to_string`, keeping the order of the maps.
+    fn to_string_canonical<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+        Self::to_string(t)
+    }
+
+    fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+        Self::from_str(str::from_utf8(s)?).map_err(FromSliceError::Deserialize)
+    }
+
+    fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+        Self::to_string(t).map(String::into_bytes)
+    }
+
+    fn from_reader<R: Read, T: DeserializeOwned>(mut r: R) -> Result<T, FromReaderError<Self::DeserializeError>> {
+        let mut vec = Vec::new();
+        r.read_to_end(&mut vec)?;
+
+        Ok(Self::from_slice(&vec)?)
+    }
+
+    fn to_writer<W: Write, T: Serialize>(mut w: W, t: &T) -> Result<(), ToWriterError<Self::SerializeError>> {
+        w.write_all(&Self::to_vec(t).map_err(ToWriterError::Serialize)?)?;
+
+        Ok(())
+    }
+
+    /// Reads and deserializes the file at `path`.
+    fn load_path<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, LoadPathError<Self::DeserializeError>> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|source| LoadPathError::Read { path: path.to_owned(), source })?;
+
+        Self::from_slice(&bytes).map_err(|source| LoadPathError::Deserialize { path: path.to_owned(), source })
+    }
+
+    /// Serializes `t` to the file at `path` [atomically](crate::atomic_write), creating its parent
+    /// directories if needed.
+    fn save_path<T: Serialize>(path: impl AsRef<Path>, t: &T) -> Result<(), SavePathError<Self::SerializeError>> {
+        let path = path.as_ref();
+        let bytes = Self::to_vec(t).map_err(|source| SavePathError::Serialize { path: path.to_owned(), source })?;
+
+        crate::atomic_write(path, bytes).map_err(|source| SavePathError::Write { path: path.to_owned(), source })
+    }
+
+    /// Like [`from_str`](Self::from_str), but returns a [`FormatError`], which is the same type
+    /// for every format.
+    fn from_str_boxed<T: DeserializeOwned>(s: &str) -> Result<T, FormatError> {
+        Self::from_str(s).map_err(FormatError::new)
+    }
+
+    /// Like [`to_string`](Self::to_string), but returns a [`FormatError`].
+    fn to_string_boxed<T: Serialize>(t: &T) -> Result<String, FormatError> {
+        Self::to_string(t).map_err(FormatError::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn extensions() {
+        #[cfg(any(
+            feature = "toml", feature = "json", feature = "yaml", feature = "ini", feature = "ron", feature = "json5",
+            feature = "xml", feature = "cbor", feature = "messagepack", feature = "bincode", feature = "bencode",
+            feature = "bson", feature = "csv", feature = "dhall", feature = "postcard", feature = "properties",
+            feature = "kdl", feature = "hcl",
+        ))]
+        use crate::Format;
+
+        #[cfg(feature = "toml")]
+        assert_eq!(crate::Toml::extension(), "toml");
+        #[cfg(feature = "json")]
+        assert_eq!(crate::Json::extension(), "json");
+        #[cfg(feature = "yaml")]
+        assert_eq!(crate::Yaml::extension(), "yaml");
+        #[cfg(feature = "ini")]
+        assert_eq!(crate::Ini::extension(), "ini");
+        #[cfg(feature = "ron")]
+        assert_eq!(crate::Ron::extension(), "ron");
+        #[cfg(feature = "json5")]
+        assert_eq!(crate::Json5::extension(), "json5");
+        #[cfg(feature = "xml")]
+        assert_eq!(crate::Xml::extension(), "xml");
+        #[cfg(feature = "cbor")]
+        assert_eq!(crate::Cbor::extension(), "cbor");
+        #[cfg(feature = "messagepack")]
+        assert_eq!(crate::MessagePack::extension(), "msgpack");
+        #[cfg(feature = "bincode")]
+        assert_eq!(crate::Bincode::extension(), "bincode");
+        #[cfg(feature = "bencode")]
+        assert_eq!(crate::Bencode::extension(), "torrent");
+        #[cfg(feature = "bson")]
+        assert_eq!(crate::Bson::extension(), "bson");
+        #[cfg(feature = "csv")]
+        assert_eq!(crate::Csv::extension(), "csv");
+        #[cfg(feature = "dhall")]
+        assert_eq!(crate::Dhall::extension(), "dhall");
+        #[cfg(feature = "postcard")]
+        assert_eq!(crate::Postcard::extension(), "postcard");
+        #[cfg(feature = "properties")]
+        assert_eq!(crate::Properties::extension(), "properties");
+        #[cfg(feature = "kdl")]
+        assert_eq!(crate::Kdl::extension(), "kdl");
+        #[cfg(feature = "hcl")]
+        assert_eq!(crate::Hcl::extension(), "hcl");
+    }
+
+    #[test]
+    fn content_types() {
+        #[cfg(any(
+            feature = "toml", feature = "json", feature = "yaml", feature = "ini", feature = "ron", feature = "json5",
+            feature = "xml", feature = "cbor", feature = "messagepack", feature = "bincode", feature = "bencode",
+            feature = "bson", feature = "csv", feature = "dhall", feature = "postcard", feature = "properties",
+            feature = "kdl", feature = "hcl",
+        ))]
+        use crate::Format;
+
+        #[cfg(feature = "toml")]
+        assert_eq!(crate::Toml::content_type(), "application/toml");
+        #[cfg(feature = "toml")]
+        assert_eq!(crate::TomlPretty::content_type(), "application/toml");
+        #[cfg(feature = "json")]
+        assert_eq!(crate::Json::content_type(), "application/json");
+        #[cfg(feature = "json")]
+        assert_eq!(crate::JsonPretty::content_type(), "application/json");
+        #[cfg(feature = "yaml")]
+        assert_eq!(crate::Yaml::content_type(), "application/yaml");
+        #[cfg(feature = "ini")]
+        assert_eq!(crate::Ini::content_type(), "application/x-ini");
+        #[cfg(feature = "ron")]
+        assert_eq!(crate::Ron::content_type(), "application/x-ron");
+        #[cfg(feature = "json5")]
+        assert_eq!(crate::Json5::content_type(), "application/json5");
+        #[cfg(feature = "xml")]
+        assert_eq!(crate::Xml::content_type(), "application/xml");
+        #[cfg(feature = "cbor")]
+        assert_eq!(crate::Cbor::content_type(), "application/cbor");
+        #[cfg(feature = "messagepack")]
+        assert_eq!(crate::MessagePack::content_type(), "application/msgpack");
+        #[cfg(feature = "bincode")]
+        assert_eq!(crate::Bincode::content_type(), "application/octet-stream");
+        #[cfg(feature = "bencode")]
+        assert_eq!(crate::Bencode::content_type(), "application/x-bittorrent");
+        #[cfg(feature = "bson")]
+        assert_eq!(crate::Bson::content_type(), "application/bson");
+        #[cfg(feature = "csv")]
+        assert_eq!(crate::Csv::content_type(), "text/csv");
+        #[cfg(feature = "dhall")]
+        assert_eq!(crate::Dhall::content_type(), "application/x-dhall");
+        #[cfg(feature = "postcard")]
+        assert_eq!(crate::Postcard::content_type(), "application/octet-stream");
+        #[cfg(feature = "properties")]
+        assert_eq!(crate::Properties::content_type(), "text/x-java-properties");
+        #[cfg(feature = "kdl")]
+        assert_eq!(crate::Kdl::content_type(), "application/kdl");
+        #[cfg(feature = "hcl")]
+        assert_eq!(crate::Hcl::content_type(), "application/x-hcl");
+        #[cfg(all(feature = "gzip", feature = "json"))]
+        assert_eq!(crate::Gzipped::<crate::Json>::content_type(), "application/gzip");
+        #[cfg(all(feature = "base64", feature = "json"))]
+        assert_eq!(crate::Base64::<crate::Json>::content_type(), "text/plain");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn text_formats_bridge_bytes_through_utf8() {
+        use crate::{Format, FromSliceError, Json};
+
+        assert_eq!(Json::to_vec(&[1, 2]).unwrap(), b"[1,2]");
+        assert_eq!(Json::from_slice::<Vec<u8>>(b"[1,2]").unwrap(), [1, 2]);
+        assert!(matches!(Json::from_slice::<String>(b"\"\xff\""), Err(FromSliceError::Utf8(_))));
+        assert!(matches!(Json::from_slice::<String>(b"["), Err(FromSliceError::Deserialize(_))));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_pretty_round_trip() {
+        use serde::{Deserialize, Serialize};
+        use crate::{Format, Json, JsonPretty};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            ports: Vec<u16>,
+        }
+
+        let server = Server { host: "localhost".to_owned(), ports: vec![80, 443] };
+        let pretty = JsonPretty::to_string(&server).unwrap();
+
+        let compact = Json::to_string(&server).unwrap();
+
+        assert_eq!(pretty, "{\n  \"host\": \"localhost\",\n  \"ports\": [\n    80,\n    443\n  ]\n}\n");
+        assert!(!compact.contains('\n'));
+        assert_eq!(JsonPretty::from_str::<Server>(&pretty).unwrap(), server);
+        assert_eq!(JsonPretty::from_str::<Server>(&compact).unwrap(), Json::from_str::<Server>(&pretty).unwrap());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_pretty_round_trip() {
+        use serde::{Deserialize, Serialize};
+        use crate::{Format, Toml, TomlPretty};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            ports: Vec<u16>,
+        }
+
+        let server = Server { host: "localhost".to_owned(), ports: vec![80, 443] };
+        let pretty = TomlPretty::to_string(&server).unwrap();
+
+        assert!(pretty.contains("ports = [\n    80,\n    443,\n]"), "{pretty}");
+        assert!(Toml::to_string(&server).unwrap().contains("ports = [80, 443]"));
+        assert_eq!(TomlPretty::from_str::<Server>(&pretty).unwrap(), server);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_pretty_expands_tables() {
+        use serde::{Deserialize, Serialize};
+        use crate::{Format, TomlPretty};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Settings {
+            theme: String,
+            cluster: Cluster,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Cluster {
+            name: String,
+            servers: Vec<Server>,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            tls: Tls,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Tls {
+            enabled: bool,
+        }
+
+        let server = |host: &str, enabled| Server { host: host.to_owned(), tls: Tls { enabled } };
+        let settings = Settings {
+            theme: "dark".to_owned(),
+            cluster: Cluster { name: "main".to_owned(), servers: vec![server("a.example.com", true), server("b.example.com", false)] },
+        };
+        let pretty = TomlPretty::to_string(&settings).unwrap();
+
+        assert!(pretty.contains("\n[cluster]\n"), "{pretty}");
+        assert_eq!(pretty.matches("\n[[cluster.servers]]\n").count(), 2, "{pretty}");
+        assert_eq!(pretty.matches("\n[cluster.servers.tls]\n").count(), 2, "{pretty}");
+        assert!(!pretty.contains('{'), "{pretty}");
+        assert_eq!(TomlPretty::from_str::<Settings>(&pretty).unwrap(), settings);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn yaml_multi_document_round_trip() {
+        use serde::{Deserialize, Serialize};
+        use crate::Yaml;
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Service {
+            name: String,
+            replicas: u8,
+        }
+
+        let stream = "name: web\nreplicas: 3\n---\nname: worker\nreplicas: 2\n---\nname: cron\nreplicas: 1\n";
+        let services = Yaml::from_str_multi::<Service>(stream).unwrap();
+
+        assert_eq!(services.iter().map(|service| service.name.as_str()).collect::<Vec<_>>(), ["web", "worker", "cron"]);
+        assert_eq!(Yaml::to_string_multi(&services).unwrap(), stream);
+        assert_eq!(Yaml::from_str_multi::<Service>(&Yaml::to_string_multi(&services).unwrap()).unwrap(), services);
+        assert!(Yaml::from_str_multi::<Service>("name: web\nreplicas: 3\n---\nname: worker\n").is_err());
+    }
+
+    #[cfg(all(feature = "json", feature = "toml", feature = "yaml"))]
+    #[test]
+    fn canonical_output_sorts_keys() {
+        use serde::{Serialize, Serializer};
+        use crate::{Format, Json, JsonPretty, Toml, TomlPretty, Yaml};
+
+        /// A map which serializes its entries in the given order.
+        struct Map(Vec<(&'static str, Entry)>);
+
+        enum Entry {
+            Number(u16),
+            Map(Map),
+        }
+
+        impl Serialize for Map {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter().map(|(key, entry)| (key, entry)))
+            }
+        }
+
+        impl Serialize for Entry {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match self {
+                    Entry::Number(number) => number.serialize(serializer),
+                    Entry::Map(map) => map.serialize(serializer),
+                }
+            }
+        }
+
+        let server = |reversed| {
+            let mut entries = vec![("port", Entry::Number(8080)), ("backlog", Entry::Number(128))];
+            let mut limits = vec![("workers", Entry::Number(4)), ("connections", Entry::Number(1024))];
+
+            if reversed {
+                entries.reverse();
+                limits.reverse();
+            }
+
+            entries.insert(1, ("limits", Entry::Map(Map(limits))));
+            Map(vec![("server", Entry::Map(Map(entries)))])
+        };
+
+        fn assert_canonical<F: Format>(a: &Map, b: &Map) {
+            assert_ne!(F::to_string(a).unwrap(), F::to_string(b).unwrap());
+            assert_eq!(F::to_string_canonical(a).unwrap(), F::to_string_canonical(b).unwrap());
+        }
+
+        let (a, b) = (server(false), server(true));
+
+        assert_canonical::<Json>(&a, &b);
+        assert_canonical::<JsonPretty>(&a, &b);
+        assert_canonical::<Toml>(&a, &b);
+        assert_canonical::<TomlPretty>(&a, &b);
+        assert_canonical::<Yaml>(&a, &b);
+        assert_eq!(
+            Json::to_string_canonical(&a).unwrap(),
+            r#"{"server":{"backlog":128,"limits":{"connections":1024,"workers":4},"port":8080}}"#
+        );
+        assert_eq!(Toml::to_string_canonical(&a).unwrap(), "[server]\nbacklog = 128\nport = 8080\n\n[server.limits]\nconnections = 1024\nworkers = 4\n");
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn text_formats_stream_through_a_buffer() {
+        use std::io::Cursor;
+        use crate::{Format, FromReaderError, Toml};
+
+        let mut cursor = Cursor::new(Vec::new());
+        Toml::to_writer(&mut cursor, &[("port", 8080)].into_iter().collect::<std::collections::BTreeMap<_, _>>()).unwrap();
+
+        assert_eq!(cursor.get_ref(), b"port = 8080\n");
+
+        cursor.set_position(0);
+        let value: toml::Table = Toml::from_reader(&mut cursor).unwrap();
+
+        assert_eq!(value["port"].as_integer(), Some(8080));
+        assert!(matches!(Toml::from_reader::<_, toml::Table>(Cursor::new(b"\xff")), Err(FromReaderError::Utf8(_))));
+        assert!(matches!(Toml::from_reader::<_, toml::Table>(Cursor::new(b"port =")), Err(FromReaderError::Deserialize(_))));
+    }
+
+    #[cfg(all(feature = "json", feature = "cbor", feature = "messagepack"))]
+    #[test]
+    fn streaming_formats_round_trip_through_cursors() {
+        use std::io::Cursor;
+        use serde::{Deserialize, Serialize};
+        use crate::{Cbor, Format, Json, JsonPretty, MessagePack};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            ports: Vec<u16>,
+        }
+
+        fn round_trip<F: Format>(server: &Server) {
+            let mut cursor = Cursor::new(Vec::new());
+            F::to_writer(&mut cursor, server).unwrap();
+
+            assert_eq!(cursor.get_ref(), &F::to_vec(server).unwrap());
+
+            cursor.set_position(0);
+
+            assert_eq!(&F::from_reader::<_, Server>(&mut cursor).unwrap(), server);
+        }
+
+        let server = Server { host: "localhost".to_owned(), ports: vec![80, 443] };
+
+        round_trip::<Json>(&server);
+        round_trip::<JsonPretty>(&server);
+        round_trip::<Cbor>(&server);
+        round_trip::<MessagePack>(&server);
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn bincode_round_trip() {
+        use std::io::Cursor;
+        use serde::{Deserialize, Serialize};
+        use crate::{BinaryError, Bincode, Format, FromSliceError};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Payload {
+            name: String,
+            data: Vec<u8>,
+        }
+
+        let payload = Payload { name: "größe — 大きさ".to_owned(), data: vec![0xff, 0x00, 0x80, 0xfe] };
+        let bytes = Bincode::to_vec(&payload).unwrap();
+
+        assert_eq!(Bincode::from_slice::<Payload>(&bytes).unwrap(), payload);
+        assert!(matches!(Bincode::to_string(&payload), Err(BinaryError::NotText { .. })));
+        assert!(matches!(Bincode::to_string(&0u8), Err(BinaryError::NotText { .. })), "the data would be valid UTF-8");
+        assert!(matches!(Bincode::from_slice::<Payload>(&bytes[..5]), Err(FromSliceError::Deserialize(_))));
+
+        let mut cursor = Cursor::new(Vec::new());
+        Bincode::to_writer(&mut cursor, &payload).unwrap();
+
+        assert_eq!(cursor.get_ref(), &bytes);
+
+        cursor.set_position(0);
+
+        assert_eq!(Bincode::from_reader::<_, Payload>(cursor).unwrap(), payload);
+    }
+
+    #[cfg(feature = "bencode")]
+    #[test]
+    fn bencode_round_trip() {
+        use serde::{Deserialize, Serialize};
+        use crate::{Bencode, Format, FromSliceError};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Info {
+            name: String,
+            length: u64,
+            #[serde(rename = "piece length")]
+            piece_length: i64,
+            #[serde(with = "serde_bytes")]
+            pieces: Vec<u8>,
+        }
+
+        let info = Info {
+            name: "image.iso".to_owned(),
+            length: 1 << 32,
+            piece_length: -1,
+            pieces: vec![0xff, 0x00, 0x80, 0xfe],
+        };
+        let bytes = Bencode::to_vec(&info).unwrap();
+
+        assert_eq!(bytes, b"d6:lengthi4294967296e4:name9:image.iso12:piece lengthi-1e6:pieces4:\xff\x00\x80\xfee");
+        assert_eq!(Bencode::from_slice::<Info>(&bytes).unwrap(), info);
+        assert!(Bencode::to_string(&info).is_err());
+        assert!(Bencode::to_vec(&1.5).is_err());
+        assert!(matches!(Bencode::from_slice::<Info>(&bytes[..20]), Err(FromSliceError::Deserialize(_))));
+    }
+
+    #[cfg(feature = "bson")]
+    #[test]
+    fn bson_round_trip() {
+        use serde::{Deserialize, Serialize};
+        use crate::{BinaryError, Bson, Format, FromSliceError};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Deployment {
+            name: String,
+            created_at: bson::DateTime,
+            shards: Vec<Vec<Replica>>,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Replica {
+            host: String,
+            priority: i32,
+        }
+
+        let replica = |host: &str, priority| Replica { host: host.to_owned(), priority };
+        let deployment = Deployment {
+            name: "main".to_owned(),
+            created_at: bson::DateTime::from_millis(1_700_000_000_000),
+            shards: vec![vec![replica("a.example.com", 2), replica("b.example.com", 1)], vec![], vec![replica("c.example.com", 1)]],
+        };
+        let bytes = Bson::to_vec(&deployment).unwrap();
+
+        assert_eq!(Bson::from_slice::<Deployment>(&bytes).unwrap(), deployment);
+        assert!(matches!(Bson::to_string(&deployment), Err(BinaryError::NotText { .. })));
+        assert!(matches!(Bson::to_vec(&[1, 2]), Err(BinaryError::Format(_))), "the root isn't a document");
+        assert!(matches!(Bson::from_slice::<Deployment>(&bytes[..10]), Err(FromSliceError::Deserialize(_))));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trip() {
+        use serde::{Deserialize, Serialize};
+        use crate::{BinaryError, Cbor, Format};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            port: u16,
+            key: Vec<u8>,
+        }
+
+        let server = Server { host: "localhost".to_owned(), port: 8080, key: vec![0xff, 0x00, 0x80] };
+        let cbor = Cbor::to_vec(&server).unwrap();
+
+        assert_eq!(Cbor::from_slice::<Server>(&cbor).unwrap(), server);
+        assert!(matches!(Cbor::to_string(&server), Err(BinaryError::NotText { .. })));
+        assert!(matches!(Cbor::to_string(&0u8), Err(BinaryError::NotText { .. })), "the data would be valid UTF-8");
+        assert!(matches!(Cbor::from_str::<u8>("\0"), Err(BinaryError::NotText { .. })), "the data would be valid CBOR");
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_nested_enums_and_fixture() {
+        use serde::{Deserialize, Serialize};
+        use crate::{Cbor, Format};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum Stage {
+            Boot(Box<Stage>),
+            Verify { digest: [u8; 4], next: Option<Box<Stage>> },
+            Run,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Image {
+            name: String,
+            rev: u8,
+            key: Option<Vec<u8>>,
+        }
+
+        let stage = Stage::Boot(Box::new(Stage::Verify {
+            digest: [0xde, 0xad, 0xbe, 0xef],
+            next: Some(Box::new(Stage::Boot(Box::new(Stage::Verify { digest: [0; 4], next: Some(Box::new(Stage::Run)) })))),
+        }));
+
+        assert_eq!(Cbor::from_slice::<Stage>(&Cbor::to_vec(&stage).unwrap()).unwrap(), stage);
+
+        // {"name": "fw", "rev": 3, "key": null}, written by hand
+        let fixture = "a3 64 6e616d65 62 6677 63 726576 03 63 6b6579 f6"
+            .split_whitespace()
+            .flat_map(|bytes| (0..bytes.len()).step_by(2).map(move |i| u8::from_str_radix(&bytes[i..i + 2], 16).unwrap()))
+            .collect::<Vec<_>>();
+        let image = Image { name: "fw".to_owned(), rev: 3, key: None };
+
+        assert_eq!(Cbor::from_slice::<Image>(&fixture).unwrap(), image);
+        assert_eq!(Cbor::to_vec(&image).unwrap(), fixture);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("image.cbor");
+        let image = Image { key: Some(vec![0xff, 0x00]), ..image };
+        Cbor::save_path(&path, &image).unwrap();
+
+        assert_eq!(Cbor::load_path::<Image>(&path).unwrap(), image);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn xml_round_trip() {
+        use serde::{Deserialize, Serialize};
+        use crate::{Format, Xml};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            port: u16,
+            tags: Vec<String>,
+        }
+
+        let server = Server { host: "localhost".to_owned(), port: 8080, tags: vec!["a".to_owned(), "b".to_owned()] };
+        let xml = Xml::to_string(&server).unwrap();
+
+        assert_eq!(xml, "<Server><host>localhost</host><port>8080</port><tags>a</tags><tags>b</tags></Server>");
+        assert_eq!(Xml::from_str::<Server>(&xml).unwrap(), server);
+    }
+
+    #[cfg(feature = "messagepack")]
+    #[test]
+    fn messagepack_round_trip() {
+        use std::collections::BTreeMap;
+        use serde::{Deserialize, Serialize};
+        use crate::{BinaryError, Format, MessagePack};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Endpoint {
+            socket: String,
+            timeout_ms: Option<u32>,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Ipc {
+            name: String,
+            endpoints: Vec<Endpoint>,
+            limits: BTreeMap<String, u64>,
+        }
+
+        let ipc = Ipc {
+            name: "worker".to_owned(),
+            endpoints: vec![
+                Endpoint { socket: "/run/app/a.sock".to_owned(), timeout_ms: Some(250) },
+                Endpoint { socket: "/run/app/b.sock".to_owned(), timeout_ms: None },
+            ],
+            limits: BTreeMap::from([("memory".to_owned(), 1 << 30), ("files".to_owned(), 64)]),
+        };
+        let bytes = MessagePack::to_vec(&ipc).unwrap();
+
+        assert_eq!(MessagePack::from_slice::<Ipc>(&bytes).unwrap(), ipc);
+        assert!(matches!(MessagePack::to_string(&()), Err(BinaryError::NotText { .. })));
+    }
+
+    #[cfg(feature = "messagepack")]
+    #[test]
+    fn messagepack_integer_keys_and_nested_enums() {
+        use std::collections::BTreeMap;
+        use serde::{Deserialize, Serialize};
+        use crate::{Format, MessagePack};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum Action {
+            Pause,
+            Seek(u64),
+            Open { path: String, mode: Mode },
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum Mode {
+            Read,
+            Write { append: bool },
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct State {
+            // keyed by device id
+            pending: BTreeMap<u32, Vec<Action>>,
+            offsets: BTreeMap<i64, Option<Mode>>,
+        }
+
+        let state = State {
+            pending: BTreeMap::from([
+                (1, vec![Action::Pause, Action::Seek(u64::MAX)]),
+                (42, vec![Action::Open { path: "notes.txt".to_owned(), mode: Mode::Write { append: true } }]),
+            ]),
+            offsets: BTreeMap::from([(-1, None), (7, Some(Mode::Read))]),
+        };
+        let bytes = MessagePack::to_vec(&state).unwrap();
+
+        assert_eq!(MessagePack::from_slice::<State>(&bytes).unwrap(), state);
+    }
+
+    #[cfg(feature = "dhall")]
+    #[test]
+    fn dhall_evaluates_expressions() {
+        use serde::{Deserialize, Serialize};
+        use crate::{Dhall, Format};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Service {
+            name: String,
+            port: u64,
+            replicas: u64,
+            debug: bool,
+        }
+
+        let dhall = r#"
+            let defaults = { port = 8080, replicas = 1, debug = False }
+            let service = \(name : Text) -> defaults // { name = name }
+            in  service "api" // { replicas = 3 }
+        "#;
+        let service = Service { name: "api".to_owned(), port: 8080, replicas: 3, debug: false };
+
+        assert_eq!(Dhall::from_str::<Service>(dhall).unwrap(), service);
+        assert_eq!(Dhall::from_str::<Service>("{ name = \"api\", port = 8080, replicas = 3, debug = False }").unwrap(), service);
+
+        let error = Dhall::from_str::<Service>("{ name = \"api\", port = 8080 }").unwrap_err();
+
+        assert!(error.to_string().contains("replicas"), "{error}");
+    }
+
+    #[cfg(feature = "dhall")]
+    #[test]
+    fn dhall_is_read_only() {
+        use std::collections::BTreeMap;
+        use crate::{Dhall, DhallSerializeError, Format, FormatError, SavePathError};
+
+        let error = Dhall::to_string(&BTreeMap::from([("port", 8080)])).unwrap_err();
+
+        assert!(matches!(error, DhallSerializeError::Unsupported));
+        assert_eq!(error.to_string(), "serializing to Dhall isn't supported; Dhall configs can only be read");
+        assert!(matches!(Dhall::to_vec(&1), Err(DhallSerializeError::Unsupported)));
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("unwritten.dhall");
+
+        assert!(matches!(
+            Dhall::save_path(&path, &1),
+            Err(SavePathError::Serialize { source: DhallSerializeError::Unsupported, .. })
+        ));
+        assert!(!path.exists());
+        assert!(matches!(Dhall::to_string_boxed(&1), Err(FormatError::DhallSerialize(DhallSerializeError::Unsupported))));
+    }
+
+    #[cfg(feature = "hcl")]
+    #[test]
+    fn hcl_round_trip() {
+        use std::collections::BTreeMap;
+        use serde::{Deserialize, Serialize};
+        use crate::{Format, Hcl};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Infra {
+            region: String,
+            #[serde(serialize_with = "hcl::ser::block")]
+            server: Server,
+            #[serde(serialize_with = "hcl::ser::labeled_block")]
+            listener: BTreeMap<String, Listener>,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            #[serde(serialize_with = "hcl::ser::block")]
+            tls: Tls,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Tls {
+            enabled: bool,
+            ciphers: Vec<String>,
+        }
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Listener {
+            port: u16,
+        }
+
+        let infra = Infra {
+            region: "eu-west-1".to_owned(),
+            server: Server {
+                host: "localhost".to_owned(),
+                tls: Tls { enabled: true, ciphers: vec!["aes128".to_owned(), "aes256".to_owned()] },
+            },
+            listener: [("http".to_owned(), Listener { port: 80 }), ("https".to_owned(), Listener { port: 443 })].into(),
+        };
+        let hcl = Hcl::to_string(&infra).unwrap();
+
+        assert!(hcl.contains("server {\n  host = \"localhost\"\n\n  tls {"), "{hcl}");
+        assert!(hcl.contains("listener \"https\" {"), "{hcl}");
+        assert_eq!(Hcl::from_str::<Infra>(&hcl).unwrap(), infra);
+        assert!(Hcl::from_str::<Infra>("server {").is_err());
+    }
+
+    #[cfg(all(feature = "json", feature = "toml"))]
+    #[test]
+    fn load_and_save_paths() {
+        use std::fs;
+        use serde::{Deserialize, Serialize};
+        use crate::{Format, Json, LoadPathError, SavePathError, Toml};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            port: u16,
+        }
+
+        fn round_trip<F: Format>(path: &std::path::Path) {
+            let server = Server { host: "localhost".to_owned(), port: 8080 };
+            F::save_path(path, &server).unwrap();
+
+            assert_eq!(F::load_path::<Server>(path).unwrap(), server);
+        }
+
+        let root = tempfile::tempdir().unwrap();
+        let tmp = root.path();
+        round_trip::<Json>(&tmp.join("nested/server.json"));
+        round_trip::<Toml>(&tmp.join("server.toml"));
+
+        let missing = tmp.join("missing.toml");
+        let error = Toml::load_path::<Server>(&missing).unwrap_err();
+
+        assert!(matches!(&error, LoadPathError::Read { source, .. } if source.kind() == std::io::ErrorKind::NotFound));
+        assert_eq!(error.to_string(), format!("failed to read '{}'", missing.display()));
+
+        // a directory can't be read as a file
+        assert!(matches!(Toml::load_path::<Server>(tmp.join("nested")), Err(LoadPathError::Read { .. })));
+
+        let broken = tmp.join("broken.toml");
+        fs::write(&broken, "port =").unwrap();
+        let error = Toml::load_path::<Server>(&broken).unwrap_err();
+
+        assert!(matches!(error, LoadPathError::Deserialize { .. }));
+        assert!(error.to_string().contains(&broken.display().to_string()), "{error}");
+        assert!(matches!(Toml::save_path(tmp.join("array.toml"), &[1, 2]), Err(SavePathError::Serialize { .. })));
+        assert!(matches!(Json::save_path(tmp.join("nested"), &[1, 2]), Err(SavePathError::Write { .. })));
+    }
+
+    #[cfg(all(feature = "json", feature = "toml"))]
+    #[test]
+    fn read_and_write_paths() {
+        use std::fs;
+        use serde::{Deserialize, Serialize};
+        use crate::{read_from_path, write_to_path, Format, FormatError, FromSliceError, Json, LoadPathError, SavePathError, Toml};
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Server {
+            host: String,
+            port: u16,
+        }
+
+        fn round_trip<F: Format>(path: &std::path::Path) {
+            let server = Server { host: "localhost".to_owned(), port: 8080 };
+            write_to_path::<F, _>(path, &server).unwrap();
+
+            assert_eq!(F::from_str::<Server>(&fs::read_to_string(path).unwrap()).unwrap(), server);
+            assert_eq!(read_from_path::<F, Server>(path).unwrap(), server);
+        }
+
+        let root = tempfile::tempdir().unwrap();
+        let tmp = root.path();
+        round_trip::<Json>(&tmp.join("nested/server.json"));
+        round_trip::<Toml>(&tmp.join("server.toml"));
+
+        let missing = tmp.join("missing.json");
+
+        assert!(matches!(read_from_path::<Json, Server>(&missing), Err(LoadPathError::Read { path, .. }) if path == missing));
+        assert!(matches!(
+            read_from_path::<Json, Server>(tmp.join("server.toml")),
+            Err(LoadPathError::Deserialize { source: FromSliceError::Deserialize(FormatError::Json(_)), .. })
+        ));
+        assert!(matches!(
+            read_from_path::<Toml, Server>(tmp.join("nested/server.json")),
+            Err(LoadPathError::Deserialize { source: FromSliceError::Deserialize(FormatError::TomlDeserialize(_)), .. })
+        ));
+        assert!(matches!(write_to_path::<Toml, _>(tmp.join("array.toml"), &[1, 2]), Err(SavePathError::Serialize { source: FormatError::TomlSerialize(_), .. })));
+        assert!(matches!(write_to_path::<Json, _>(tmp.join("nested"), &[1, 2]), Err(SavePathError::Write { .. })));
+        assert!(!tmp.join("array.toml").exists());
+    }
 }
@@ -0,0 +1,94 @@
+use std::marker::PhantomData;
+use std::str::Utf8Error;
+use base64::engine::general_purpose::STANDARD;
+use base64::{DecodeError, Engine};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::formats::{Format, FromSliceError};
+
+/// Encodes the data of the format `F` as base64, e.g. `Base64<Cbor>`, so that binary formats can
+/// be stored in text, such as an environment variable or a string field of another config.
+///
+/// The standard alphabet with padding is used, and whitespace around the encoded data is ignored
+/// when decoding. The extension is `b64`.
+pub struct Base64<F>(PhantomData<F>);
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Base64Error<E> {
+    #[error("the data is not valid base64")]
+    Decode(#[from] DecodeError),
+
+    #[error("the decoded data is not valid UTF-8")]
+    Utf8(#[from] Utf8Error),
+
+    #[error(transparent)]
+    Format(E),
+}
+
+impl<F: Format> Format for Base64<F> {
+    type SerializeError = Base64Error<F::SerializeError>;
+    type DeserializeError = Base64Error<F::DeserializeError>;
+
+    fn extension() -> &'static str {
+        "b64"
+    }
+
+    fn content_type() -> &'static str {
+        "text/plain"
+    }
+
+    fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+        F::from_slice(&STANDARD.decode(s.trim())?).map_err(|error| match error {
+            FromSliceError::Utf8(error) => Base64Error::Utf8(error),
+            FromSliceError::Deserialize(error) => Base64Error::Format(error),
+        })
+    }
+
+    fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+        Ok(STANDARD.encode(F::to_vec(t).map_err(Base64Error::Format)?))
+    }
+}
+
+#[cfg(all(test, feature = "cbor"))]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use crate::{Base64, Base64Error, Cbor, Format};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Token {
+        subject: String,
+        scopes: Vec<String>,
+        expires: u64,
+        signature: Vec<u8>,
+    }
+
+    fn token() -> Token {
+        Token {
+            subject: "ci".to_owned(),
+            scopes: vec!["read".to_owned(), "deploy".to_owned()],
+            expires: 1_700_000_000,
+            signature: vec![0xff, 0x00, 0x80, 0xfe],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let encoded = Base64::<Cbor>::to_string(&token()).unwrap();
+
+        assert!(encoded.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"+/=".contains(&byte)), "{encoded}");
+        assert_eq!(encoded.len() % 4, 0);
+        assert_eq!(
+            base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &encoded).unwrap(),
+            Cbor::to_vec(&token()).unwrap()
+        );
+        assert_eq!(Base64::<Cbor>::from_str::<Token>(&format!("{encoded}\n")).unwrap(), token());
+        assert_eq!(Base64::<Cbor>::from_slice::<Token>(&Base64::<Cbor>::to_vec(&token()).unwrap()).unwrap(), token());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(Base64::<Cbor>::from_str::<Token>("not base64!"), Err(Base64Error::Decode(_))));
+        assert!(matches!(Base64::<Cbor>::from_str::<Token>("AAAA"), Err(Base64Error::Format(_))));
+    }
+}
@@ -0,0 +1,330 @@
+use std::io::{Read, Write};
+use csv::{StringRecord, StringRecordIter};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, IntoDeserializer, Visitor};
+use serde::ser::{Impossible, SerializeSeq, SerializeTuple};
+use serde::{de, ser, Serialize};
+use crate::formats::{Format, FromReaderError, FromSliceError, ToWriterError};
+use crate::formats::untyped::{self, deserialize_untyped, message_error};
+
+/// Comma separated values, for configs which are lists of uniform records, e.g.
+/// `Vec<Record>`. The first line is a header naming the fields of the records.
+///
+/// The top level must be a sequence of records, such as a [`Vec`] or a slice; scalars and maps
+/// aren't supported at the top level, and neither are nested maps, structs or sequences inside a
+/// record. Empty fields deserialize to `None` for optional fields.
+pub enum Csv {}
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum CsvError {
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    #[error("{0}")]
+    Message(String),
+}
+
+message_error!(CsvError: ser, de);
+
+impl Format for Csv {
+    type SerializeError = CsvError;
+    type DeserializeError = CsvError;
+
+    fn extension() -> &'static str {
+        "csv"
+    }
+
+    fn content_type() -> &'static str {
+        "text/csv"
+    }
+
+    fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+        read(s.as_bytes())
+    }
+
+    fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+        String::from_utf8(Self::to_vec(t)?).map_err(|error| CsvError::Message(error.to_string()))
+    }
+
+    fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+        read(s).map_err(FromSliceError::Deserialize)
+    }
+
+    fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+        let mut vec = Vec::new();
+        write(&mut vec, t)?;
+
+        Ok(vec)
+    }
+
+    fn from_reader<R: Read, T: DeserializeOwned>(r: R) -> Result<T, FromReaderError<Self::DeserializeError>> {
+        read(r).map_err(FromReaderError::Deserialize)
+    }
+
+    fn to_writer<W: Write, T: Serialize>(w: W, t: &T) -> Result<(), ToWriterError<Self::SerializeError>> {
+        write(w, t).map_err(ToWriterError::Serialize)
+    }
+}
+
+fn read<R: Read, T: DeserializeOwned>(r: R) -> Result<T, CsvError> {
+    let mut reader = csv::Reader::from_reader(r);
+    let headers = reader.headers()?.clone();
+    let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+
+    T::deserialize(SeqDeserializer::new(records.iter().map(|record| Record { headers: &headers, record })))
+}
+
+fn write<W: Write, T: Serialize>(w: W, t: &T) -> Result<(), CsvError> {
+    let mut writer = csv::Writer::from_writer(w);
+    t.serialize(Records(&mut writer))?;
+    writer.flush().map_err(csv::Error::from)?;
+
+    Ok(())
+}
+
+/// Deserializes a record as a map from the headers to its fields.
+struct Record<'a> {
+    headers: &'a StringRecord,
+    record: &'a StringRecord,
+}
+
+type Fields<'a> = std::iter::Zip<StringRecordIter<'a>, std::iter::Map<StringRecordIter<'a>, fn(&'a str) -> Field<'a>>>;
+
+impl<'de, 'a> IntoDeserializer<'de, CsvError> for Record<'a> {
+    type Deserializer = MapDeserializer<'de, Fields<'a>, CsvError>;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        MapDeserializer::new(self.headers.iter().zip(self.record.iter().map(Field as fn(_) -> _)))
+    }
+}
+
+/// Deserializes a field, parsing it into the type which is asked for.
+struct Field<'a>(&'a str);
+
+impl Field<'_> {
+    fn parse<T: std::str::FromStr>(&self) -> Result<T, CsvError>
+    where
+        T::Err: std::fmt::Display,
+    {
+        untyped::parse(self.0)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, CsvError> for Field<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Field<'_> {
+    type Error = CsvError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        untyped::deserialize_option(self.0.is_empty(), self, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        untyped::deserialize_enum(self.0, visitor)
+    }
+
+    deserialize_untyped!();
+}
+
+/// Serializes a sequence by writing every element as a record.
+struct Records<'a, W: Write>(&'a mut csv::Writer<W>);
+
+fn not_a_sequence() -> CsvError {
+    CsvError::Message("CSV can only serialize a sequence of records".to_owned())
+}
+
+macro_rules! serialize_unsupported {
+    ($($method:ident($($ty:ty),*),)*) => {
+        $(
+        fn $method(self, $(_: $ty),*) -> Result<Self::Ok, Self::Error> {
+            Err(not_a_sequence())
+        }
+        )*
+    };
+}
+
+impl<W: Write> ser::Serializer for Records<'_, W> {
+    type Ok = ();
+    type Error = CsvError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Impossible<(), CsvError>;
+    type SerializeTupleVariant = Impossible<(), CsvError>;
+    type SerializeMap = Impossible<(), CsvError>;
+    type SerializeStruct = Impossible<(), CsvError>;
+    type SerializeStructVariant = Impossible<(), CsvError>;
+
+    serialize_unsupported! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(not_a_sequence())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(not_a_sequence())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(not_a_sequence())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(not_a_sequence())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(not_a_sequence())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(not_a_sequence())
+    }
+}
+
+impl<W: Write> SerializeSeq for Records<'_, W> {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        Ok(self.0.serialize(value)?)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> SerializeTuple for Records<'_, W> {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use crate::{Csv, CsvError, Format};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Mirror {
+        name: String,
+        url: String,
+        priority: u8,
+        enabled: bool,
+        region: Option<Region>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Region {
+        Europe,
+        Asia,
+    }
+
+    #[test]
+    fn round_trip() {
+        let mirror = |name: &str, priority, enabled, region| Mirror {
+            name: name.to_owned(),
+            url: format!("https://{name}.example.com/repo"),
+            priority,
+            enabled,
+            region,
+        };
+        let mirrors = vec![
+            mirror("eu", 1, true, Some(Region::Europe)),
+            mirror("asia, east", 2, false, Some(Region::Asia)),
+            mirror("fallback", 10, true, None),
+        ];
+        let csv = Csv::to_string(&mirrors).unwrap();
+
+        assert_eq!(csv, "name,url,priority,enabled,region
+eu,https://eu.example.com/repo,1,true,Europe
+\"asia, east\",\"https://asia, east.example.com/repo\",2,false,Asia
+fallback,https://fallback.example.com/repo,10,true,
+");
+        assert_eq!(Csv::from_str::<Vec<Mirror>>(&csv).unwrap(), mirrors);
+        assert_eq!(Csv::from_slice::<Vec<Mirror>>(&Csv::to_vec(&mirrors.as_slice()).unwrap()).unwrap(), mirrors);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(Csv::to_string(&1), Err(CsvError::Message(_))));
+        assert!(matches!(Csv::from_str::<Vec<Mirror>>("name,priority\neu,high\n"), Err(CsvError::Message(_))));
+        assert!(matches!(Csv::from_str::<Vec<Mirror>>("name,priority\neu\n"), Err(CsvError::Csv(_))));
+    }
+}
@@ -0,0 +1,151 @@
+use std::marker::PhantomData;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::formats::{Format, FromSliceError};
+
+/// The length of the nonce prepended to the encrypted data, in bytes.
+const NONCE_LEN: usize = 24;
+
+/// Supplies the key of an [`Encrypted`] format. Formats are stateless, so the key is provided by a
+/// type rather than a value, which may e.g. read it from the keyring of the platform.
+pub trait EncryptionKey {
+    /// The 256-bit key.
+    fn key() -> [u8; 32];
+}
+
+/// Encrypts the data of the format `F` at rest with XChaCha20-Poly1305, using the key supplied by
+/// `K`. A random nonce is generated for every encryption and prepended to the data.
+///
+/// The encryption is authenticated, so data encrypted with a different key or modified in any way
+/// fails to load with [`EncryptedError::Decrypt`] instead of being parsed. Like any binary format,
+/// [`to_string`](Format::to_string) always fails, so use [`to_vec`](Format::to_vec) and
+/// [`from_slice`](Format::from_slice) instead.
+pub struct Encrypted<F, K>(PhantomData<(F, K)>);
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum EncryptedError<E> {
+    #[error("failed to encrypt the data")]
+    Encrypt,
+
+    #[error("failed to decrypt the data; it was encrypted with another key or has been tampered with")]
+    Decrypt,
+
+    #[error("encrypted data is binary and can't be a string")]
+    Binary,
+
+    #[error(transparent)]
+    Format(E),
+}
+
+fn cipher<K: EncryptionKey>() -> XChaCha20Poly1305 {
+    XChaCha20Poly1305::new(&K::key().into())
+}
+
+impl<F: Format, K: EncryptionKey> Format for Encrypted<F, K> {
+    type SerializeError = EncryptedError<F::SerializeError>;
+    type DeserializeError = EncryptedError<F::DeserializeError>;
+
+    fn extension() -> &'static str {
+        "enc"
+    }
+
+    fn content_type() -> &'static str {
+        "application/octet-stream"
+    }
+
+    fn from_str<T: DeserializeOwned>(_s: &str) -> Result<T, Self::DeserializeError> {
+        Err(EncryptedError::Binary)
+    }
+
+    fn to_string<T: Serialize>(_t: &T) -> Result<String, Self::SerializeError> {
+        Err(EncryptedError::Binary)
+    }
+
+    fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+        if s.len() < NONCE_LEN {
+            return Err(FromSliceError::Deserialize(EncryptedError::Decrypt))
+        }
+
+        let (nonce, ciphertext) = s.split_at(NONCE_LEN);
+        let plaintext = cipher::<K>()
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| FromSliceError::Deserialize(EncryptedError::Decrypt))?;
+
+        F::from_slice(&plaintext).map_err(|error| match error {
+            FromSliceError::Utf8(error) => FromSliceError::Utf8(error),
+            FromSliceError::Deserialize(error) => FromSliceError::Deserialize(EncryptedError::Format(error)),
+        })
+    }
+
+    fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+        let plaintext = F::to_vec(t).map_err(EncryptedError::Format)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher::<K>().encrypt(&nonce, plaintext.as_slice()).map_err(|_| EncryptedError::Encrypt)?;
+
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use crate::{Encrypted, EncryptedError, EncryptionKey, Format, FromSliceError, Json};
+
+    struct TestKey;
+
+    impl EncryptionKey for TestKey {
+        fn key() -> [u8; 32] {
+            [7; 32]
+        }
+    }
+
+    struct OtherKey;
+
+    impl EncryptionKey for OtherKey {
+        fn key() -> [u8; 32] {
+            [8; 32]
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Credentials {
+        api_token: String,
+    }
+
+    type Secret = Encrypted<Json, TestKey>;
+
+    #[test]
+    fn round_trip() {
+        let credentials = Credentials { api_token: "secret-token".to_owned() };
+        let encrypted = Secret::to_vec(&credentials).unwrap();
+
+        assert!(!encrypted.windows(12).any(|window| window == b"secret-token"));
+        assert_ne!(encrypted, Secret::to_vec(&credentials).unwrap(), "the nonce was reused");
+        assert_eq!(Secret::from_slice::<Credentials>(&encrypted).unwrap(), credentials);
+        assert_eq!(Secret::content_type(), "application/octet-stream");
+    }
+
+    #[test]
+    fn tampering_is_rejected() {
+        let mut encrypted = Secret::to_vec(&Credentials { api_token: "secret-token".to_owned() }).unwrap();
+
+        assert!(matches!(
+            Encrypted::<Json, OtherKey>::from_slice::<Credentials>(&encrypted),
+            Err(FromSliceError::Deserialize(EncryptedError::Decrypt))
+        ));
+
+        *encrypted.last_mut().unwrap() ^= 1;
+
+        assert!(matches!(
+            Secret::from_slice::<Credentials>(&encrypted),
+            Err(FromSliceError::Deserialize(EncryptedError::Decrypt))
+        ));
+        assert!(matches!(
+            Secret::from_slice::<Credentials>(&encrypted[..10]),
+            Err(FromSliceError::Deserialize(EncryptedError::Decrypt))
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::formats::{Format, FromReaderError, FromSliceError, ToWriterError};
+
+/// Compresses the data of the format `F` with gzip, e.g. `Gzipped<Json>` for large cached state.
+///
+/// Like any binary format, [`to_string`](Format::to_string) always fails, so use
+/// [`to_vec`](Format::to_vec) and [`from_slice`](Format::from_slice) instead. The extension is
+/// `gz`, which usually follows the extension of `F` in file names, e.g. `state.json.gz`.
+pub struct Gzipped<F>(PhantomData<F>);
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum GzippedError<E> {
+    #[error("failed to compress or decompress the data")]
+    Io(#[from] io::Error),
+
+    #[error("gzipped data is binary and can't be a string")]
+    Binary,
+
+    #[error(transparent)]
+    Format(E),
+}
+
+impl<F: Format> Format for Gzipped<F> {
+    type SerializeError = GzippedError<F::SerializeError>;
+    type DeserializeError = GzippedError<F::DeserializeError>;
+
+    fn extension() -> &'static str {
+        "gz"
+    }
+
+    fn content_type() -> &'static str {
+        "application/gzip"
+    }
+
+    fn from_str<T: DeserializeOwned>(_s: &str) -> Result<T, Self::DeserializeError> {
+        Err(GzippedError::Binary)
+    }
+
+    fn to_string<T: Serialize>(_t: &T) -> Result<String, Self::SerializeError> {
+        Err(GzippedError::Binary)
+    }
+
+    fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+        let mut vec = Vec::new();
+        GzDecoder::new(s).read_to_end(&mut vec).map_err(|error| FromSliceError::Deserialize(error.into()))?;
+
+        F::from_slice(&vec).map_err(|error| match error {
+            FromSliceError::Utf8(error) => FromSliceError::Utf8(error),
+            FromSliceError::Deserialize(error) => FromSliceError::Deserialize(GzippedError::Format(error)),
+        })
+    }
+
+    fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&F::to_vec(t).map_err(GzippedError::Format)?)?;
+
+        Ok(encoder.finish()?)
+    }
+
+    fn from_reader<R: Read, T: DeserializeOwned>(r: R) -> Result<T, FromReaderError<Self::DeserializeError>> {
+        F::from_reader(GzDecoder::new(r)).map_err(|error| match error {
+            FromReaderError::Io(error) => FromReaderError::Io(error),
+            FromReaderError::Utf8(error) => FromReaderError::Utf8(error),
+            FromReaderError::Deserialize(error) => FromReaderError::Deserialize(GzippedError::Format(error)),
+        })
+    }
+
+    fn to_writer<W: Write, T: Serialize>(w: W, t: &T) -> Result<(), ToWriterError<Self::SerializeError>> {
+        let mut encoder = GzEncoder::new(w, Compression::default());
+
+        F::to_writer(&mut encoder, t).map_err(|error| match error {
+            ToWriterError::Io(error) => ToWriterError::Io(error),
+            ToWriterError::Serialize(error) => ToWriterError::Serialize(GzippedError::Format(error)),
+        })?;
+        encoder.finish()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use std::io::Cursor;
+    use serde::{Deserialize, Serialize};
+    use crate::{Format, FromSliceError, Gzipped, GzippedError, Json};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct State {
+        entries: Vec<Entry>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Entry {
+        path: String,
+        hits: u32,
+    }
+
+    #[test]
+    fn round_trip() {
+        let state = State {
+            entries: (0..1000).map(|i| Entry { path: format!("/var/cache/app/{}", i % 10), hits: i % 3 }).collect(),
+        };
+        let json = Json::to_vec(&state).unwrap();
+        let gzipped = Gzipped::<Json>::to_vec(&state).unwrap();
+
+        assert!(gzipped.len() * 10 < json.len(), "{} bytes gzipped, {} bytes plain", gzipped.len(), json.len());
+        assert_eq!(Gzipped::<Json>::from_slice::<State>(&gzipped).unwrap(), state);
+
+        let mut cursor = Cursor::new(Vec::new());
+        Gzipped::<Json>::to_writer(&mut cursor, &state).unwrap();
+        cursor.set_position(0);
+
+        assert_eq!(Gzipped::<Json>::from_reader::<_, State>(cursor).unwrap(), state);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            Gzipped::<Json>::from_slice::<State>(b"{}"),
+            Err(FromSliceError::Deserialize(GzippedError::Io(_)))
+        ));
+        assert!(matches!(
+            Gzipped::<Json>::from_slice::<State>(&Gzipped::<Json>::to_vec(&[1]).unwrap()),
+            Err(FromSliceError::Deserialize(GzippedError::Format(_)))
+        ));
+        assert!(matches!(Gzipped::<Json>::to_string(&[1]), Err(GzippedError::Binary)));
+    }
+}
@@ -0,0 +1,333 @@
+use kdl::{KdlDocument, KdlEntry, KdlError, KdlNode, KdlValue};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+use crate::formats::Format;
+
+/// KDL, mapped to the serde data model like the JSON-in-KDL (JiK) microsyntax does:
+///
+/// * The document is a map with a key for every node.
+/// * A node with a single argument is that value, and one with several arguments is a sequence of
+///   them. A node without any entries or children is null.
+/// * A node with properties or children is a map of both.
+/// * A node whose children are all named `-` is a sequence of them, which is how sequences of
+///   maps or sequences are written.
+/// * The type annotations `(array)` and `(object)` force a node to be a sequence or a map, which
+///   is needed for empty ones and sequences with a single argument.
+///
+/// ```kdl
+/// theme "dark"
+/// plugins "git" "lsp"
+/// (array)fonts "Fira Code"
+/// server host="localhost" {
+///     ports 80 443
+/// }
+/// users {
+///     - name="alice"
+///     - name="bob"
+/// }
+/// ```
+///
+/// Consequently, only data which fits this mapping is supported: the top level must be a map,
+/// nodes can't mix arguments with properties or children, node names must be unique within their
+/// parent, and maps with a single key named `-` are read back as sequences. Integers must fit in
+/// 64 bits, and floats must be finite.
+pub enum Kdl {}
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum KdlDeserializeError {
+    #[error("{}", describe(.0))]
+    Parse(#[source] KdlError),
+
+    #[error("the node '{node}' {reason}")]
+    Unsupported { node: String, reason: &'static str },
+
+    #[error(transparent)]
+    Data(serde_json::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum KdlSerializeError {
+    #[error("the value {reason}")]
+    Unsupported { reason: &'static str },
+
+    #[error(transparent)]
+    Data(serde_json::Error),
+}
+
+fn describe(error: &KdlError) -> String {
+    let messages: Vec<_> = error
+        .diagnostics
+        .iter()
+        .filter_map(|diagnostic| diagnostic.message.as_deref())
+        .collect();
+
+    if messages.is_empty() {
+        return "failed to parse the KDL document".to_owned()
+    }
+
+    format!("failed to parse the KDL document: {}", messages.join("; "))
+}
+
+impl Format for Kdl {
+    type SerializeError = KdlSerializeError;
+    type DeserializeError = KdlDeserializeError;
+
+    fn extension() -> &'static str {
+        "kdl"
+    }
+
+    fn content_type() -> &'static str {
+        "application/kdl"
+    }
+
+    fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+        let document = s.parse::<KdlDocument>().map_err(KdlDeserializeError::Parse)?;
+
+        serde_json::from_value(document_to_value(&document)?).map_err(KdlDeserializeError::Data)
+    }
+
+    fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+        let Value::Object(map) = serde_json::to_value(t).map_err(KdlSerializeError::Data)? else {
+            return Err(KdlSerializeError::Unsupported { reason: "must be a map at the top level" })
+        };
+        let mut document = KdlDocument::new();
+
+        for (key, value) in map {
+            document.nodes_mut().push(value_to_node(key, value)?);
+        }
+
+        document.autoformat();
+
+        Ok(document.to_string())
+    }
+}
+
+fn document_to_value(document: &KdlDocument) -> Result<Value, KdlDeserializeError> {
+    let mut map = Map::new();
+
+    for node in document.nodes() {
+        insert(&mut map, node, node.name().value(), node_to_value(node)?)?;
+    }
+
+    Ok(Value::Object(map))
+}
+
+fn insert(map: &mut Map<String, Value>, node: &KdlNode, key: &str, value: Value) -> Result<(), KdlDeserializeError> {
+    match map.insert(key.to_owned(), value) {
+        Some(_) => Err(unsupported(node, "has a duplicate key")),
+        None => Ok(()),
+    }
+}
+
+fn unsupported(node: &KdlNode, reason: &'static str) -> KdlDeserializeError {
+    KdlDeserializeError::Unsupported { node: node.name().value().to_owned(), reason }
+}
+
+fn node_to_value(node: &KdlNode) -> Result<Value, KdlDeserializeError> {
+    let ty = node.ty().map(|ty| ty.value());
+    let children = node.children().map_or(&[][..], KdlDocument::nodes);
+    let (props, args): (Vec<_>, Vec<_>) = node.entries().iter().partition(|entry| entry.name().is_some());
+
+    let is_array = ty == Some("array") || (!children.is_empty() && children.iter().all(|child| child.name().value() == "-"));
+    let is_object = ty == Some("object") || !props.is_empty() || !children.is_empty();
+
+    if is_array {
+        if !props.is_empty() {
+            return Err(unsupported(node, "is a sequence, which can't have properties"))
+        }
+
+        let args = args.iter().map(|entry| scalar_to_value(node, entry.value()));
+        let children = children.iter().map(node_to_value);
+
+        return args.chain(children).collect::<Result<_, _>>().map(Value::Array)
+    }
+
+    if is_object {
+        if !args.is_empty() {
+            return Err(unsupported(node, "is a map, which can't have arguments"))
+        }
+
+        let mut map = Map::new();
+
+        for prop in props {
+            let key = prop.name().map_or("", |name| name.value());
+            insert(&mut map, node, key, scalar_to_value(node, prop.value())?)?;
+        }
+
+        for child in children {
+            insert(&mut map, child, child.name().value(), node_to_value(child)?)?;
+        }
+
+        return Ok(Value::Object(map))
+    }
+
+    match args.as_slice() {
+        [] => Ok(Value::Null),
+        [arg] => scalar_to_value(node, arg.value()),
+        args => args.iter().map(|entry| scalar_to_value(node, entry.value())).collect::<Result<_, _>>().map(Value::Array),
+    }
+}
+
+fn scalar_to_value(node: &KdlNode, value: &KdlValue) -> Result<Value, KdlDeserializeError> {
+    Ok(match value {
+        KdlValue::String(s) => Value::String(s.clone()),
+        KdlValue::Integer(i) => match (i64::try_from(*i), u64::try_from(*i)) {
+            (Ok(i), _) => Value::from(i),
+            (_, Ok(i)) => Value::from(i),
+            _ => return Err(unsupported(node, "has an integer which doesn't fit in 64 bits")),
+        },
+        KdlValue::Float(f) => Number::from_f64(*f)
+            .map(Value::Number)
+            .ok_or_else(|| unsupported(node, "has a float which isn't finite"))?,
+        KdlValue::Bool(b) => Value::Bool(*b),
+        KdlValue::Null => Value::Null,
+    })
+}
+
+fn value_to_node(name: String, value: Value) -> Result<KdlNode, KdlSerializeError> {
+    let mut node = KdlNode::new(name);
+
+    match value {
+        Value::Array(values) if values.iter().all(is_scalar) => {
+            if values.len() < 2 {
+                node.set_ty("array");
+            }
+
+            for value in values {
+                node.push(KdlEntry::new(value_to_scalar(value)?));
+            }
+        }
+        Value::Array(values) => {
+            let children = node.ensure_children();
+
+            for value in values {
+                children.nodes_mut().push(value_to_node("-".to_owned(), value)?);
+            }
+        }
+        Value::Object(map) if map.is_empty() => node.set_ty("object"),
+        Value::Object(map) => {
+            if map.len() == 1 && map.contains_key("-") {
+                return Err(KdlSerializeError::Unsupported { reason: "is a map with the single key '-'" })
+            }
+
+            let children = node.ensure_children();
+
+            for (key, value) in map {
+                children.nodes_mut().push(value_to_node(key, value)?);
+            }
+        }
+        scalar => node.push(KdlEntry::new(value_to_scalar(scalar)?)),
+    }
+
+    Ok(node)
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+fn value_to_scalar(value: Value) -> Result<KdlValue, KdlSerializeError> {
+    Ok(match value {
+        Value::Null => KdlValue::Null,
+        Value::Bool(b) => KdlValue::Bool(b),
+        Value::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(i), _, _) => KdlValue::Integer(i.into()),
+            (_, Some(i), _) => KdlValue::Integer(i.into()),
+            (_, _, Some(f)) => KdlValue::Float(f),
+            _ => return Err(KdlSerializeError::Unsupported { reason: "is a number which can't be represented" }),
+        },
+        Value::String(s) => KdlValue::String(s),
+        Value::Array(_) | Value::Object(_) => unreachable!("only called with scalars"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use serde::{Deserialize, Serialize};
+    use crate::{Format, Kdl, KdlDeserializeError};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Settings {
+        theme: String,
+        plugins: Vec<String>,
+        fonts: Vec<String>,
+        scale: f64,
+        accent: Option<String>,
+        server: Server,
+        users: Vec<User>,
+        env: BTreeMap<String, String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Server {
+        host: String,
+        ports: Vec<u16>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct User {
+        name: String,
+        admin: bool,
+    }
+
+    #[test]
+    fn round_trip() {
+        let settings = Settings {
+            theme: "dark".to_owned(),
+            plugins: vec!["git".to_owned(), "lsp".to_owned()],
+            fonts: vec!["Fira Code".to_owned()],
+            scale: 1.5,
+            accent: None,
+            server: Server { host: "localhost".to_owned(), ports: vec![80, 443] },
+            users: vec![User { name: "alice".to_owned(), admin: true }, User { name: "bob".to_owned(), admin: false }],
+            env: BTreeMap::new(),
+        };
+        let kdl = Kdl::to_string(&settings).unwrap();
+
+        assert!(kdl.contains("plugins git lsp"), "{kdl}");
+        assert!(kdl.contains("(array)fonts"), "{kdl}");
+        assert_eq!(Kdl::from_str::<Settings>(&kdl).unwrap(), settings);
+    }
+
+    #[test]
+    fn reads_handwritten_documents() {
+        let kdl = r#"
+            theme dark
+            plugins git lsp
+            (array)fonts "Fira Code"
+            scale 1.5
+            accent #null
+            server host=localhost {
+                ports 80 443
+            }
+            users {
+                - name=alice admin=#true
+                - name=bob admin=#false
+            }
+            (object)env
+        "#;
+        let settings = Kdl::from_str::<Settings>(kdl).unwrap();
+
+        assert_eq!(settings.server, Server { host: "localhost".to_owned(), ports: vec![80, 443] });
+        assert_eq!(settings.users[1], User { name: "bob".to_owned(), admin: false });
+        assert_eq!(settings.fonts, ["Fira Code"]);
+    }
+
+    #[test]
+    fn unsupported_documents() {
+        assert!(matches!(Kdl::from_str::<()>("theme {"), Err(KdlDeserializeError::Parse(_))));
+        assert!(matches!(
+            Kdl::from_str::<BTreeMap<String, u8>>("a 1\na 2"),
+            Err(KdlDeserializeError::Unsupported { reason: "has a duplicate key", .. })
+        ));
+        assert!(matches!(
+            Kdl::from_str::<BTreeMap<String, u8>>("server 1 port=2"),
+            Err(KdlDeserializeError::Unsupported { .. })
+        ));
+        assert!(Kdl::to_string(&[1, 2]).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::formats::{BinaryError, Format, FromSliceError};
+
+/// A compact binary format which is usable without the standard library, e.g. to share state
+/// with embedded devices. Like any binary format, [`to_string`](Format::to_string) and
+/// [`from_str`](Format::from_str) always fail with [`BinaryError::NotText`], so use
+/// [`to_vec`](Format::to_vec) and [`from_slice`](Format::from_slice) instead.
+///
+/// Postcard isn't self-describing, so data deserialized into a type other than the one it was
+/// serialized from is silently misinterpreted rather than rejected. See [`VersionedPostcard`] to
+/// reject data written by an incompatible version.
+pub enum Postcard {}
+
+impl Format for Postcard {
+    type SerializeError = BinaryError<postcard::Error>;
+    type DeserializeError = BinaryError<postcard::Error>;
+
+    fn extension() -> &'static str {
+        "postcard"
+    }
+
+    fn content_type() -> &'static str {
+        "application/octet-stream"
+    }
+
+    fn from_str<T: DeserializeOwned>(_s: &str) -> Result<T, Self::DeserializeError> {
+        Err(BinaryError::NotText { format: "postcard" })
+    }
+
+    fn to_string<T: Serialize>(_t: &T) -> Result<String, Self::SerializeError> {
+        Err(BinaryError::NotText { format: "postcard" })
+    }
+
+    fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+        postcard::from_bytes(s).map_err(|error| FromSliceError::Deserialize(BinaryError::Format(error)))
+    }
+
+    fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+        postcard::to_stdvec(t).map_err(BinaryError::Format)
+    }
+}
+
+/// Like [`Postcard`], but the data starts with the byte `VERSION`, which is checked before
+/// deserializing, so that data written by an incompatible version of the type fails with
+/// [`PostcardError::VersionMismatch`] instead of being misinterpreted. Bump `VERSION` whenever the
+/// layout of the type changes.
+pub enum VersionedPostcard<const VERSION: u8> {}
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum PostcardError {
+    #[error("the data has version {found}, but version {expected} was expected")]
+    VersionMismatch { expected: u8, found: u8 },
+
+    #[error("the data is empty, so it has no version")]
+    Empty,
+
+    #[error(transparent)]
+    Postcard(#[from] postcard::Error),
+}
+
+impl<const VERSION: u8> Format for VersionedPostcard<VERSION> {
+    type SerializeError = BinaryError<PostcardError>;
+    type DeserializeError = BinaryError<PostcardError>;
+
+    fn extension() -> &'static str {
+        Postcard::extension()
+    }
+
+    fn content_type() -> &'static str {
+        Postcard::content_type()
+    }
+
+    fn from_str<T: DeserializeOwned>(_s: &str) -> Result<T, Self::DeserializeError> {
+        Err(BinaryError::NotText { format: "postcard" })
+    }
+
+    fn to_string<T: Serialize>(_t: &T) -> Result<String, Self::SerializeError> {
+        Err(BinaryError::NotText { format: "postcard" })
+    }
+
+    fn from_slice<T: DeserializeOwned>(s: &[u8]) -> Result<T, FromSliceError<Self::DeserializeError>> {
+        let error = |error| FromSliceError::Deserialize(BinaryError::Format(error));
+        let (&found, data) = s.split_first().ok_or(error(PostcardError::Empty))?;
+
+        if found != VERSION {
+            return Err(error(PostcardError::VersionMismatch { expected: VERSION, found }))
+        }
+
+        postcard::from_bytes(data).map_err(|source| error(source.into()))
+    }
+
+    fn to_vec<T: Serialize>(t: &T) -> Result<Vec<u8>, Self::SerializeError> {
+        let mut vec = vec![VERSION];
+        vec.extend(postcard::to_stdvec(t).map_err(|error| BinaryError::Format(error.into()))?);
+
+        Ok(vec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use crate::{BinaryError, Format, FromSliceError, Postcard, PostcardError, VersionedPostcard};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Telemetry {
+        device: String,
+        interval_ms: u32,
+        channels: Vec<Channel>,
+        calibration: Option<[i16; 3]>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Channel {
+        Temperature,
+        Pressure { hpa_offset: i8 },
+    }
+
+    fn telemetry() -> Telemetry {
+        Telemetry {
+            device: "sensor-7".to_owned(),
+            interval_ms: 500,
+            channels: vec![Channel::Temperature, Channel::Pressure { hpa_offset: -3 }],
+            calibration: Some([1, -2, 3]),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = Postcard::to_vec(&telemetry()).unwrap();
+
+        assert_eq!(Postcard::from_slice::<Telemetry>(&bytes).unwrap(), telemetry());
+
+        let versioned = VersionedPostcard::<2>::to_vec(&telemetry()).unwrap();
+
+        assert_eq!(versioned[0], 2);
+        assert_eq!(versioned[1..], bytes);
+        assert_eq!(VersionedPostcard::<2>::from_slice::<Telemetry>(&versioned).unwrap(), telemetry());
+    }
+
+    #[test]
+    fn corrupted_input_is_rejected() {
+        let bytes = VersionedPostcard::<2>::to_vec(&telemetry()).unwrap();
+
+        assert!(matches!(
+            VersionedPostcard::<3>::from_slice::<Telemetry>(&bytes),
+            Err(FromSliceError::Deserialize(BinaryError::Format(PostcardError::VersionMismatch { expected: 3, found: 2 })))
+        ));
+        assert!(matches!(
+            VersionedPostcard::<2>::from_slice::<Telemetry>(&[]),
+            Err(FromSliceError::Deserialize(BinaryError::Format(PostcardError::Empty)))
+        ));
+        assert!(matches!(
+            VersionedPostcard::<2>::from_slice::<Telemetry>(&bytes[..bytes.len() / 2]),
+            Err(FromSliceError::Deserialize(BinaryError::Format(PostcardError::Postcard(postcard::Error::DeserializeUnexpectedEnd))))
+        ));
+        assert!(Postcard::from_slice::<Telemetry>(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).is_err());
+    }
+
+    #[test]
+    fn strings_are_rejected() {
+        // a unit struct serializes to no bytes at all, which is valid UTF-8
+        assert!(Postcard::to_vec(&()).unwrap().is_empty());
+        assert!(matches!(Postcard::to_string(&()), Err(BinaryError::NotText { format: "postcard" })));
+        assert!(matches!(Postcard::from_str::<()>(""), Err(BinaryError::NotText { .. })));
+        assert!(matches!(VersionedPostcard::<2>::to_string(&()), Err(BinaryError::NotText { .. })));
+        assert!(matches!(VersionedPostcard::<2>::from_str::<()>("\u{2}"), Err(BinaryError::NotText { .. })));
+    }
+}
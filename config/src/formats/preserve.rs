@@ -0,0 +1,514 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use serde::ser::{self, Serialize};
+use toml_edit::{DocumentMut, Item, Table, TableLike};
+use crate::Toml;
+
+/// Tables appended to an existing document are moved after its tables, but keep their order.
+const APPENDED_POSITION: usize = usize::MAX / 2;
+
+/// An error of [`Toml::save_preserving`].
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum SavePreservingError {
+    #[error("failed to read '{}'", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to parse '{}'", path.display())]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: toml_edit::TomlError,
+    },
+
+    #[error("failed to serialize '{}'", path.display())]
+    Serialize {
+        path: PathBuf,
+        #[source]
+        source: toml::ser::Error,
+    },
+
+    #[error("failed to write '{}'", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl Toml {
+    /// Like [`save_path`](crate::Format::save_path), but if the file exists, serializes `t` into
+    /// it key by key rather than replacing it, so that comments, whitespace and the order of keys
+    /// survive, e.g. when an app saves back a config file which a user has edited by hand. The
+    /// merged document is still written [atomically](crate::atomic_write).
+    ///
+    /// The values of existing keys are replaced in place and keep the comments around them, keys
+    /// which are new are appended to their table, and keys of fields which `t` skips, e.g. a field
+    /// set to `None`, are removed along with their comments. Keys which `t` doesn't know about,
+    /// e.g. those of another tool sharing the file, are kept as they are.
+    pub fn save_preserving<T: Serialize>(path: impl AsRef<Path>, t: &T) -> Result<(), SavePreservingError> {
+        let path = path.as_ref();
+        let existing = match fs::read_to_string(path) {
+            Ok(existing) => existing,
+            Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+            Err(source) => return Err(SavePreservingError::Read { path: path.to_owned(), source }),
+        };
+        let mut document = existing
+            .parse::<DocumentMut>()
+            .map_err(|source| SavePreservingError::Parse { path: path.to_owned(), source })?;
+        let new = toml::to_string(t)
+            .map_err(|source| SavePreservingError::Serialize { path: path.to_owned(), source })?
+            .parse::<DocumentMut>()
+            .expect("the toml serializer produced an invalid document");
+        let mut skipped = Skipped::default();
+        t.serialize(SkipRecorder(&mut skipped))
+            .map_err(|source| SavePreservingError::Serialize { path: path.to_owned(), source })?;
+
+        merge(document.as_table_mut(), new.as_table().clone(), &skipped);
+
+        crate::atomic_write(path, document.to_string())
+            .map_err(|source| SavePreservingError::Write { path: path.to_owned(), source })
+    }
+}
+
+fn merge(existing: &mut dyn TableLike, new: Table, skipped: &Skipped) {
+    let removed: Vec<_> = existing
+        .iter()
+        .map(|(key, _)| key.to_owned())
+        .filter(|key| !new.contains_key(key) && skipped.keys.contains(key))
+        .collect();
+
+    for key in removed {
+        existing.remove(&key);
+    }
+
+    for (key, new) in new {
+        let Some(item) = existing.get_mut(&key) else {
+            existing.insert(&key, appended(new));
+            continue
+        };
+
+        match (item, new) {
+            (item @ (Item::Table(_) | Item::Value(toml_edit::Value::InlineTable(_))), new @ Item::Table(_)) => {
+                let new = new.into_table().expect("the item is a table");
+                merge(item.as_table_like_mut().expect("the item is a table"), new, skipped.table(&key));
+            }
+            (Item::Value(value), Item::Value(mut new)) => {
+                *new.decor_mut() = value.decor().clone();
+                *value = new;
+            }
+            (item, new) => *item = appended(new),
+        }
+    }
+}
+
+/// Moves the tables in `item` after the tables of the document it is appended to.
+fn appended(mut item: Item) -> Item {
+    fn shift(table: &mut Table) {
+        if let Some(position) = table.position() {
+            table.set_position(APPENDED_POSITION + position);
+        }
+
+        for (_, item) in table.iter_mut() {
+            match item {
+                Item::Table(table) => shift(table),
+                Item::ArrayOfTables(array) => array.iter_mut().for_each(shift),
+                _ => {}
+            }
+        }
+    }
+
+    match &mut item {
+        Item::Table(table) => shift(table),
+        Item::ArrayOfTables(array) => array.iter_mut().for_each(shift),
+        _ => {}
+    }
+
+    item
+}
+
+/// The keys which a value skipped when it was serialized, e.g. its fields set to `None`, by table,
+/// as opposed to keys which it doesn't know about.
+#[derive(Default)]
+struct Skipped {
+    keys: BTreeSet<String>,
+    tables: BTreeMap<String, Skipped>,
+}
+
+impl Skipped {
+    fn table(&self, key: &str) -> &Skipped {
+        static EMPTY: Skipped = Skipped { keys: BTreeSet::new(), tables: BTreeMap::new() };
+
+        self.tables.get(key).unwrap_or(&EMPTY)
+    }
+
+    /// Serializes `value` at `key`, which is skipped if the value is `None`.
+    fn serialize<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), toml::ser::Error> {
+        if value.serialize(SkipRecorder(self.tables.entry(key.to_owned()).or_default()))? {
+            self.keys.insert(key.to_owned());
+        }
+
+        Ok(())
+    }
+}
+
+/// Records the [`Skipped`] keys of a value, mirroring how the toml serializer lays it out in
+/// tables. Serializing returns whether the value is `None`, which TOML leaves out. Arrays are
+/// replaced as a whole when merging, so their elements aren't looked at.
+struct SkipRecorder<'a>(&'a mut Skipped);
+
+/// Records the [`Skipped`] keys of a map, whose entries are only looked at if their keys are
+/// strings, like every key in TOML.
+struct MapRecorder<'a> {
+    skipped: &'a mut Skipped,
+    key: Option<String>,
+}
+
+/// Ignores the elements of a sequence.
+struct Ignored;
+
+impl<'a> ser::Serializer for SkipRecorder<'a> {
+    type Ok = bool;
+    type Error = toml::ser::Error;
+    type SerializeSeq = Ignored;
+    type SerializeTuple = Ignored;
+    type SerializeTupleStruct = Ignored;
+    type SerializeTupleVariant = Ignored;
+    type SerializeMap = MapRecorder<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, _v: bool) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_char(self, _v: char) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_none(self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<bool, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, _variant: &'static str) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<bool, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<bool, Self::Error> {
+        self.0.serialize(variant, value)?;
+
+        Ok(false)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Ignored, Self::Error> {
+        Ok(Ignored)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Ignored, Self::Error> {
+        Ok(Ignored)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Ignored, Self::Error> {
+        Ok(Ignored)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Ignored, Self::Error> {
+        Ok(Ignored)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapRecorder<'a>, Self::Error> {
+        Ok(MapRecorder { skipped: self.0, key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self(self.0.tables.entry(variant.to_owned()).or_default()))
+    }
+}
+
+impl ser::SerializeStruct for SkipRecorder<'_> {
+    type Ok = bool;
+    type Error = toml::ser::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error> {
+        self.0.serialize(key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.0.keys.insert(key.to_owned());
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
+impl ser::SerializeStructVariant for SkipRecorder<'_> {
+    type Ok = bool;
+    type Error = toml::ser::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        ser::SerializeStruct::skip_field(self, key)
+    }
+
+    fn end(self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
+impl ser::SerializeMap for MapRecorder<'_> {
+    type Ok = bool;
+    type Error = toml::ser::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        self.key = match toml::Value::try_from(key) {
+            Ok(toml::Value::String(key)) => Some(key),
+            _ => None,
+        };
+
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        match self.key.take() {
+            Some(key) => self.skipped.serialize(&key, value),
+            None => Ok(()),
+        }
+    }
+
+    fn end(self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
+macro_rules! ignored {
+    ($($trait:ident::$method:ident,)*) => {
+        $(
+        impl ser::$trait for Ignored {
+            type Ok = bool;
+            type Error = toml::ser::Error;
+
+            fn $method<T: Serialize + ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error> {
+                Ok(())
+            }
+
+            fn end(self) -> Result<bool, Self::Error> {
+                Ok(false)
+            }
+        }
+        )*
+    };
+}
+
+ignored! {
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use serde::{Deserialize, Serialize};
+    use crate::{Format, SavePreservingError, Toml};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Settings {
+        theme: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        font: Option<String>,
+        server: Server,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        aliases: BTreeMap<String, String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    const EXISTING: &str = r#"# the colour scheme
+theme = "light" # or "dark"
+# the editor font
+font = "Fira Code"
+
+# where to connect to
+[server]
+host = "localhost" # for development
+port = 80
+"#;
+
+    #[test]
+    fn comments_survive_an_edit() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("settings.toml");
+        std::fs::write(&path, EXISTING).unwrap();
+
+        let mut settings = Toml::load_path::<Settings>(&path).unwrap();
+        settings.server.port = 8080;
+        Toml::save_preserving(&path, &settings).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), EXISTING.replace("port = 80", "port = 8080"));
+
+        settings.theme = "dark".to_owned();
+        settings.font = None;
+        settings.aliases.insert("ll".to_owned(), "ls -l".to_owned());
+        Toml::save_preserving(&path, &settings).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+
+        assert_eq!(saved, r#"# the colour scheme
+theme = "dark" # or "dark"
+
+# where to connect to
+[server]
+host = "localhost" # for development
+port = 8080
+
+[aliases]
+ll = "ls -l"
+"#);
+        assert_eq!(Toml::from_str::<Settings>(&saved).unwrap(), settings);
+    }
+
+    #[test]
+    fn missing_files_are_created() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("nested/settings.toml");
+        let settings = Settings {
+            theme: "light".to_owned(),
+            font: None,
+            server: Server { host: "localhost".to_owned(), port: 80 },
+            aliases: BTreeMap::new(),
+        };
+        Toml::save_preserving(&path, &settings).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), Toml::to_string(&settings).unwrap());
+
+        std::fs::write(&path, "theme =").unwrap();
+
+        assert!(matches!(Toml::save_preserving(&path, &settings), Err(SavePreservingError::Parse { .. })));
+    }
+
+    #[test]
+    fn unknown_keys_are_kept() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("settings.toml");
+        std::fs::write(&path, "theme = \"light\"\nfont = \"Fira Code\"\neditor = \"vim\"\n\n[server]\nhost = \"localhost\"\nport = 80\ntimeout = 30\n\n[other_tool]\nenabled = true\n").unwrap();
+
+        let mut settings = Toml::load_path::<Settings>(&path).unwrap();
+        settings.font = None;
+        settings.server.port = 8080;
+        Toml::save_preserving(&path, &settings).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "theme = \"light\"\neditor = \"vim\"\n\n[server]\nhost = \"localhost\"\nport = 8080\ntimeout = 30\n\n[other_tool]\nenabled = true\n",
+        );
+
+        // keys of maps set to `None` are skipped too
+        let aliases = BTreeMap::from([("ll", Some("ls -l")), ("la", None)]);
+        std::fs::write(&path, "la = \"ls -a\"\nl = \"ls\"\n").unwrap();
+        Toml::save_preserving(&path, &aliases).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "l = \"ls\"\nll = \"ls -l\"\n");
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use serde::de::value::MapDeserializer;
+use serde::de::{DeserializeOwned, IntoDeserializer, Visitor};
+use serde::{de, Serialize};
+use serde_json::Value;
+use crate::formats::Format;
+use crate::formats::untyped::{self, deserialize_untyped, message_error};
+
+/// Java `.properties` files, which are flat lists of `key=value` pairs, e.g. for deployments
+/// which mandate them.
+///
+/// Nested structs and maps are flattened into dotted keys, so `server.port=8080` is the field
+/// `port` of the struct in the field `server`. Every dot in a key separates a level of nesting, so
+/// map keys containing dots don't survive a round trip, and a key can't be both a value and a
+/// table, as in `server=local` next to `server.port=8080`.
+///
+/// ```properties
+/// # the colour scheme
+/// theme=dark
+/// server.host=localhost
+/// server.port=8080
+/// ```
+///
+/// Reading follows `java.util.Properties`: keys and values are separated by `=`, `:` or
+/// whitespace, lines starting with `#` or `!` are comments, a line ending in a backslash continues
+/// on the next one, and `\t`, `\n`, `\r`, `\f` and `\uXXXX` are escapes. When a key is repeated,
+/// the last value wins. Values are strings which are parsed into the type which is asked for, and
+/// an empty value is `None` for optional fields.
+///
+/// Writing sorts the keys, escapes everything which needs it and writes characters outside of
+/// ASCII as `\uXXXX`, so that the file is also read correctly by Java versions which expect
+/// ISO-8859-1. `None` values are left out, and sequences aren't supported.
+pub enum Properties {}
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum PropertiesError {
+    #[error("line {line}: {reason}")]
+    Parse { line: usize, reason: &'static str },
+
+    #[error("the key '{key}' {reason}")]
+    Unsupported { key: String, reason: &'static str },
+
+    #[error(transparent)]
+    Data(serde_json::Error),
+
+    #[error("{0}")]
+    Message(String),
+}
+
+message_error!(PropertiesError: de);
+
+impl Format for Properties {
+    type SerializeError = PropertiesError;
+    type DeserializeError = PropertiesError;
+
+    fn extension() -> &'static str {
+        "properties"
+    }
+
+    fn content_type() -> &'static str {
+        "text/x-java-properties"
+    }
+
+    fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+        let mut root = BTreeMap::new();
+
+        for (key, value) in parse(s)? {
+            insert(&mut root, key, value)?;
+        }
+
+        T::deserialize(Node::Table(root))
+    }
+
+    fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+        let Value::Object(map) = serde_json::to_value(t).map_err(PropertiesError::Data)? else {
+            return Err(PropertiesError::Unsupported { key: String::new(), reason: "must be a map or a struct at the top level" })
+        };
+        let mut entries = BTreeMap::new();
+
+        for (key, value) in map {
+            flatten(&mut entries, key, value)?;
+        }
+
+        let mut string = String::new();
+
+        for (key, value) in entries {
+            escape(&mut string, &key, true);
+            string.push('=');
+            escape(&mut string, &value, false);
+            string.push('\n');
+        }
+
+        Ok(string)
+    }
+}
+
+/// Splits `s` into its key value pairs.
+fn parse(s: &str) -> Result<Vec<(String, String)>, PropertiesError> {
+    let mut pairs = Vec::new();
+    let mut lines = s.lines().enumerate();
+
+    while let Some((number, line)) = lines.next() {
+        let mut logical = line.trim_start_matches(is_whitespace).to_owned();
+
+        if logical.is_empty() || logical.starts_with(['#', '!']) {
+            continue
+        }
+
+        while ends_with_continuation(&logical) {
+            logical.pop();
+
+            match lines.next() {
+                Some((_, next)) => logical.push_str(next.trim_start_matches(is_whitespace)),
+                None => break,
+            }
+        }
+
+        let (key, value) = split(&logical);
+        pairs.push((unescape(key, number + 1)?, unescape(value, number + 1)?));
+    }
+
+    Ok(pairs)
+}
+
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\x0c')
+}
+
+/// Whether `line` ends in an odd number of backslashes, the last of which isn't escaped.
+fn ends_with_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1
+}
+
+/// Splits a logical line into its raw key and value.
+fn split(line: &str) -> (&str, &str) {
+    let mut chars = line.char_indices();
+    let mut end = line.len();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '=' | ':' => return (&line[..index], line[index + 1..].trim_start_matches(is_whitespace)),
+            c if is_whitespace(c) => {
+                end = index;
+                break
+            }
+            _ => {}
+        }
+    }
+
+    let rest = line[end..].trim_start_matches(is_whitespace);
+    let rest = rest.strip_prefix(['=', ':']).unwrap_or(rest);
+
+    (&line[..end], rest.trim_start_matches(is_whitespace))
+}
+
+fn unescape(s: &str, line: usize) -> Result<String, PropertiesError> {
+    let invalid = |reason| PropertiesError::Parse { line, reason };
+    let mut string = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            string.push(c);
+            continue
+        }
+
+        match chars.next() {
+            Some('t') => string.push('\t'),
+            Some('n') => string.push('\n'),
+            Some('r') => string.push('\r'),
+            Some('f') => string.push('\x0c'),
+            Some('u') => {
+                let unit = code_unit(&mut chars).ok_or(invalid("a \\u escape needs 4 hex digits"))?;
+                let c = if (0xd800..0xdc00).contains(&unit) {
+                    let low = match (chars.next(), chars.next()) {
+                        (Some('\\'), Some('u')) => code_unit(&mut chars),
+                        _ => None,
+                    };
+
+                    low.and_then(|low| char::decode_utf16([unit, low]).next()?.ok())
+                } else {
+                    char::from_u32(unit.into())
+                };
+
+                string.push(c.ok_or(invalid("a \\u escape is an unpaired surrogate"))?);
+            }
+            Some(c) => string.push(c),
+            None => {}
+        }
+    }
+
+    Ok(string)
+}
+
+fn code_unit(chars: &mut std::str::Chars) -> Option<u16> {
+    let digits: String = chars.take(4).collect();
+
+    if digits.len() != 4 {
+        return None
+    }
+
+    u16::from_str_radix(&digits, 16).ok()
+}
+
+fn escape(string: &mut String, s: &str, key: bool) {
+    for (index, c) in s.chars().enumerate() {
+        match c {
+            '\\' => string.push_str("\\\\"),
+            '\t' => string.push_str("\\t"),
+            '\n' => string.push_str("\\n"),
+            '\r' => string.push_str("\\r"),
+            '\x0c' => string.push_str("\\f"),
+            ' ' if key || index == 0 => string.push_str("\\ "),
+            '=' | ':' | '#' | '!' if key || index == 0 => {
+                string.push('\\');
+                string.push(c);
+            }
+            ' '..='~' => string.push(c),
+            c => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    write!(string, "\\u{unit:04X}").expect("writing to a string can't fail");
+                }
+            }
+        }
+    }
+}
+
+fn flatten(entries: &mut BTreeMap<String, String>, key: String, value: Value) -> Result<(), PropertiesError> {
+    let value = match value {
+        Value::Null => return Ok(()),
+        Value::Bool(bool) => bool.to_string(),
+        Value::Number(number) => number.to_string(),
+        Value::String(string) => string,
+        Value::Array(_) => return Err(PropertiesError::Unsupported { key, reason: "is a sequence, which can't be written" }),
+        Value::Object(map) => {
+            for (child, value) in map {
+                flatten(entries, format!("{key}.{child}"), value)?;
+            }
+
+            return Ok(())
+        }
+    };
+
+    entries.insert(key, value);
+
+    Ok(())
+}
+
+/// A dotted key split into its levels of nesting.
+enum Node {
+    Value(String),
+    Table(BTreeMap<String, Node>),
+}
+
+fn insert(root: &mut BTreeMap<String, Node>, key: String, value: String) -> Result<(), PropertiesError> {
+    let conflict = || PropertiesError::Unsupported {
+        key: key.clone(),
+        reason: "is both a value and a table",
+    };
+    let mut segments: Vec<_> = key.split('.').collect();
+    let last = segments.pop().expect("split always yields a segment");
+    let mut table = root;
+
+    for segment in segments {
+        let node = table.entry(segment.to_owned()).or_insert_with(|| Node::Table(BTreeMap::new()));
+
+        table = match node {
+            Node::Table(table) => table,
+            Node::Value(_) => return Err(conflict()),
+        };
+    }
+
+    match table.get(last) {
+        Some(Node::Table(_)) => Err(conflict()),
+        _ => {
+            table.insert(last.to_owned(), Node::Value(value));
+
+            Ok(())
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, PropertiesError> for Node {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl Node {
+    fn parse<T: std::str::FromStr>(self) -> Result<T, PropertiesError>
+    where
+        T::Err: std::fmt::Display,
+    {
+        match self {
+            Self::Value(value) => untyped::parse(&value),
+            Self::Table(_) => Err(PropertiesError::Message("expected a value, found a table".to_owned())),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Node {
+    type Error = PropertiesError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            Self::Value(value) => visitor.visit_string(value),
+            Self::Table(table) => visitor.visit_map(MapDeserializer::new(table.into_iter())),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let empty = matches!(&self, Self::Value(value) if value.is_empty());
+
+        untyped::deserialize_option(empty, self, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self {
+            Self::Value(value) => untyped::deserialize_enum(&value, visitor),
+            Self::Table(_) => Err(PropertiesError::Message("only unit variants are supported".to_owned())),
+        }
+    }
+
+    deserialize_untyped!();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use serde::{Deserialize, Serialize};
+    use crate::{Format, Properties, PropertiesError};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Settings {
+        theme: String,
+        font_size: u8,
+        scale: f64,
+        telemetry: bool,
+        proxy: Option<String>,
+        server: Server,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    fn read(s: &str) -> BTreeMap<String, String> {
+        Properties::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let settings = Settings {
+            theme: "dark mode: high contrast".to_owned(),
+            font_size: 13,
+            scale: 1.25,
+            telemetry: false,
+            proxy: None,
+            server: Server { host: " localhost".to_owned(), port: 8080 },
+        };
+        let properties = Properties::to_string(&settings).unwrap();
+
+        assert_eq!(properties, "font_size=13
+scale=1.25
+server.host=\\ localhost
+server.port=8080
+telemetry=false
+theme=dark mode: high contrast
+");
+        assert_eq!(Properties::from_str::<Settings>(&properties).unwrap(), settings);
+    }
+
+    #[test]
+    fn continuation_lines() {
+        let properties = read(r"
+fruits = apple, banana, \
+         cherry, \
+         date
+path=C:\\dir\\
+next=line
+");
+
+        assert_eq!(properties["fruits"], "apple, banana, cherry, date");
+        assert_eq!(properties["path"], "C:\\dir\\");
+        assert_eq!(properties["next"], "line");
+    }
+
+    #[test]
+    fn separators_and_comments() {
+        let properties = read("# a comment\n  ! another one\n\ncolon: value\nspace   value\nempty\nequals =  = value\n");
+
+        assert_eq!(properties.len(), 4);
+        assert_eq!(properties["colon"], "value");
+        assert_eq!(properties["space"], "value");
+        assert_eq!(properties["empty"], "");
+        assert_eq!(properties["equals"], "= value");
+    }
+
+    #[test]
+    fn escaped_characters() {
+        let properties = read(r"key\ with\=special\:chars = tab\there\nnewline
+greeting = gr\u00FC\u00DFe \uD83D\uDE00
+");
+
+        assert_eq!(properties["key with=special:chars"], "tab\there\nnewline");
+        assert_eq!(properties["greeting"], "grüße 😀");
+
+        let written = Properties::to_string(&properties).unwrap();
+
+        assert_eq!(written, "greeting=gr\\u00FC\\u00DFe \\uD83D\\uDE00
+key\\ with\\=special\\:chars=tab\\there\\nnewline
+");
+        assert_eq!(read(&written), properties);
+        assert!(matches!(Properties::from_str::<BTreeMap<String, String>>("bad=\\u12"), Err(PropertiesError::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let properties = read("theme=light\ntheme=dark\n");
+
+        assert_eq!(properties["theme"], "dark");
+        assert!(matches!(
+            Properties::from_str::<Settings>("server=local\nserver.port=80"),
+            Err(PropertiesError::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn unsupported_values() {
+        assert!(matches!(Properties::to_string(&1), Err(PropertiesError::Unsupported { .. })));
+        assert!(matches!(
+            Properties::to_string(&BTreeMap::from([("plugins", ["git"])])),
+            Err(PropertiesError::Unsupported { .. })
+        ));
+        assert!(matches!(
+            Properties::from_str::<Server>("host=localhost\nport=high"),
+            Err(PropertiesError::Message(_))
+        ));
+    }
+}
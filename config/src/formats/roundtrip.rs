@@ -0,0 +1,108 @@
+use std::fmt::Debug;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::formats::Format;
+
+/// Asserts that `value` survives serializing to and deserializing from the format `F`, e.g. to
+/// check a config type against every format an app supports. Only available with the
+/// `test-util` feature.
+///
+/// # Panics
+///
+/// If serializing or deserializing fails, or the deserialized value differs from `value`. The
+/// message includes the serialized data, as a string if it is valid UTF-8.
+#[track_caller]
+pub fn assert_roundtrip<T, F>(value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+    F: Format,
+{
+    let format = F::extension();
+    let bytes = match F::to_vec(value) {
+        Ok(bytes) => bytes,
+        Err(error) => panic!("failed to serialize {value:?} to {format}: {error}"),
+    };
+    let data = match std::str::from_utf8(&bytes) {
+        Ok(string) => string.to_owned(),
+        Err(_) => format!("{bytes:02x?}"),
+    };
+    let deserialized = match F::from_slice::<T>(&bytes) {
+        Ok(deserialized) => deserialized,
+        Err(error) => panic!("failed to deserialize {value:?} from {format}: {error}\nserialized:\n{data}"),
+    };
+
+    assert!(
+        deserialized == *value,
+        "{value:?} changed in a {format} round trip\nserialized:\n{data}\ndeserialized: {deserialized:?}",
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(feature = "toml", feature = "json", feature = "yaml"))]
+    use serde::{Deserialize, Serialize};
+    #[cfg(any(feature = "toml", feature = "json"))]
+    use crate::assert_roundtrip;
+
+    #[cfg(all(feature = "toml", feature = "json", feature = "yaml"))]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Settings {
+        theme: String,
+        font_size: u8,
+        plugins: Vec<String>,
+        server: Server,
+    }
+
+    #[cfg(all(feature = "toml", feature = "json", feature = "yaml"))]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[cfg(all(feature = "toml", feature = "json", feature = "yaml"))]
+    fn settings() -> Settings {
+        Settings {
+            theme: "dark".to_owned(),
+            font_size: 13,
+            plugins: vec!["git".to_owned(), "lsp".to_owned()],
+            server: Server { host: "localhost".to_owned(), port: 8080 },
+        }
+    }
+
+    #[cfg(all(feature = "toml", feature = "json", feature = "yaml"))]
+    #[test]
+    fn common_formats() {
+        use crate::{Json, JsonPretty, Toml, TomlPretty, Yaml};
+
+        assert_roundtrip::<_, Toml>(&settings());
+        assert_roundtrip::<_, TomlPretty>(&settings());
+        assert_roundtrip::<_, Json>(&settings());
+        assert_roundtrip::<_, JsonPretty>(&settings());
+        assert_roundtrip::<_, Yaml>(&settings());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    #[should_panic(expected = "changed in a json round trip\nserialized:\n{\"name\":\"lost\"}")]
+    fn mismatch_shows_the_data() {
+        use serde::{Deserialize, Serialize};
+        use crate::Json;
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Lossy {
+            name: String,
+            #[serde(skip)]
+            cache: Vec<u8>,
+        }
+
+        assert_roundtrip::<_, Json>(&Lossy { name: "lost".to_owned(), cache: vec![1] });
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    #[should_panic(expected = "failed to serialize")]
+    fn unrepresentable_values_panic() {
+        assert_roundtrip::<_, crate::Toml>(&Some(1));
+    }
+}
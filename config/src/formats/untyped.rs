@@ -0,0 +1,86 @@
+//! Deserializing formats whose values are untyped strings, such as the fields of
+//! [`Csv`](crate::Csv) and the values of [`Properties`](crate::Properties), which are parsed into
+//! the type which is asked for.
+
+use std::fmt;
+use std::str::FromStr;
+use serde::de::{self, IntoDeserializer, Visitor};
+
+/// Implements `de::Error` and optionally `ser::Error` for an error with a `Message(String)`
+/// variant, e.g. `message_error!(CsvError: de, ser);`.
+macro_rules! message_error {
+    ($error:ty: $($kind:ident),+) => {
+        $(
+        impl ::serde::$kind::Error for $error {
+            fn custom<T: ::std::fmt::Display>(msg: T) -> Self {
+                Self::Message(msg.to_string())
+            }
+        }
+        )+
+    };
+}
+
+/// Implements the methods of a deserializer for booleans, numbers and characters by parsing the
+/// value, with its method `fn parse<T: FromStr>(self) -> Result<T, Self::Error>`, and forwards the
+/// rest, except for `deserialize_any`, `deserialize_option` and `deserialize_enum`, to
+/// `deserialize_any`.
+macro_rules! deserialize_untyped {
+    () => {
+        $crate::formats::untyped::deserialize_untyped! {
+            deserialize_bool => visit_bool,
+            deserialize_i8 => visit_i8,
+            deserialize_i16 => visit_i16,
+            deserialize_i32 => visit_i32,
+            deserialize_i64 => visit_i64,
+            deserialize_i128 => visit_i128,
+            deserialize_u8 => visit_u8,
+            deserialize_u16 => visit_u16,
+            deserialize_u32 => visit_u32,
+            deserialize_u64 => visit_u64,
+            deserialize_u128 => visit_u128,
+            deserialize_f32 => visit_f32,
+            deserialize_f64 => visit_f64,
+            deserialize_char => visit_char,
+        }
+
+        ::serde::forward_to_deserialize_any! {
+            str string bytes byte_buf unit unit_struct newtype_struct seq tuple tuple_struct map struct
+            identifier ignored_any
+        }
+    };
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+        fn $method<V: ::serde::de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+            visitor.$visit(self.parse()?)
+        }
+        )*
+    };
+}
+
+pub(crate) use {deserialize_untyped, message_error};
+
+/// Parses `value`, ignoring surrounding whitespace.
+pub(crate) fn parse<T: FromStr, E: de::Error>(value: &str) -> Result<T, E>
+where
+    T::Err: fmt::Display,
+{
+    value.trim().parse().map_err(|error| E::custom(format!("invalid value '{value}': {error}")))
+}
+
+/// Deserializes an option, which is `None` if the value of `deserializer` is `empty`.
+pub(crate) fn deserialize_option<'de, D: de::Deserializer<'de>, V: Visitor<'de>>(
+    empty: bool,
+    deserializer: D,
+    visitor: V,
+) -> Result<V::Value, D::Error> {
+    if empty {
+        visitor.visit_none()
+    } else {
+        visitor.visit_some(deserializer)
+    }
+}
+
+/// Deserializes `value` as the name of a unit variant, the only kind of variant a string can be.
+pub(crate) fn deserialize_enum<'de, V: Visitor<'de>, E: de::Error>(value: &str, visitor: V) -> Result<V::Value, E> {
+    visitor.visit_enum(value.into_deserializer())
+}
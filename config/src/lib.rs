@@ -1,3 +1,19 @@
+mod any;
+mod atomic;
+mod backup;
+#[cfg(feature = "convert")]
+mod convert;
+mod error;
 mod formats;
+mod load;
+mod lock;
 
-pub use formats::*;
\ No newline at end of file
+pub use any::*;
+pub use atomic::*;
+pub use backup::*;
+#[cfg(feature = "convert")]
+pub use convert::*;
+pub use error::*;
+pub use formats::*;
+pub use load::*;
+pub use lock::*;
\ No newline at end of file
@@ -0,0 +1,228 @@
+use std::fs::File;
+use std::io::{self, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use crate::formats::{Format, FromSliceError};
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum LoadError<E> {
+    #[error("failed to read '{}'", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to deserialize '{}'", path.display())]
+    Deserialize {
+        path: PathBuf,
+        #[source]
+        source: FromSliceError<E>,
+    },
+}
+
+/// Returns whether an IO error of the given kind is considered transient, and thus worth retrying.
+///
+/// These are:
+///
+/// * [`ErrorKind::Interrupted`]: the read was interrupted by a signal (`EINTR`).
+/// * [`ErrorKind::WouldBlock`]: the resource is temporarily unavailable (`EAGAIN`).
+/// * [`ErrorKind::TimedOut`]: the underlying (network) filesystem timed out.
+/// * [`ErrorKind::ResourceBusy`]: the file is temporarily busy (`EBUSY`).
+/// * [`ErrorKind::StaleNetworkFileHandle`]: the NFS handle went stale (`ESTALE`); reopening the
+///   file usually fixes it.
+/// * [`ErrorKind::ConnectionReset`] and [`ErrorKind::ConnectionAborted`]: the connection to the
+///   remote filesystem dropped.
+///
+/// Everything else, notably [`ErrorKind::NotFound`] and [`ErrorKind::PermissionDenied`], is
+/// permanent.
+pub fn is_transient(kind: ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::Interrupted
+            | ErrorKind::WouldBlock
+            | ErrorKind::TimedOut
+            | ErrorKind::ResourceBusy
+            | ErrorKind::StaleNetworkFileHandle
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+    )
+}
+
+/// Reads and deserializes the config at `path`, retrying up to `attempts` times in total on
+/// [transient](is_transient) IO errors. The delay between attempts starts at `backoff` and doubles
+/// after every failed attempt.
+///
+/// Deserialization errors and permanent IO errors are returned immediately.
+pub fn load_retrying<F: Format, T: DeserializeOwned>(
+    path: impl AsRef<Path>,
+    attempts: u32,
+    backoff: Duration,
+) -> Result<T, LoadError<F::DeserializeError>> {
+    load_retrying_with::<F, T, _>(path, |path| File::open(path), attempts, backoff)
+}
+
+/// Like [`load_retrying`], but reads from whatever `open` returns for `path`. `open` is called
+/// once per attempt.
+pub fn load_retrying_with<F: Format, T: DeserializeOwned, R: Read>(
+    path: impl AsRef<Path>,
+    mut open: impl FnMut(&Path) -> io::Result<R>,
+    attempts: u32,
+    backoff: Duration,
+) -> Result<T, LoadError<F::DeserializeError>> {
+    let path = path.as_ref();
+    let bytes = retry(
+        || {
+            let mut bytes = Vec::new();
+            open(path)?.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        },
+        attempts,
+        backoff,
+    ).map_err(|source| LoadError::Read { path: path.to_owned(), source })?;
+
+    F::from_slice(&bytes).map_err(|source| LoadError::Deserialize { path: path.to_owned(), source })
+}
+
+fn retry<T>(mut op: impl FnMut() -> io::Result<T>, attempts: u32, mut backoff: Duration) -> io::Result<T> {
+    let mut remaining = attempts.max(1);
+
+    loop {
+        remaining -= 1;
+
+        match op() {
+            Err(error) if remaining > 0 && is_transient(error.kind()) => {
+                thread::sleep(backoff);
+                backoff = backoff.saturating_mul(2);
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::io::{self, ErrorKind, Read};
+    use std::path::Path;
+    use std::time::Duration;
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use crate::formats::Format;
+    use crate::load::{load_retrying_with, LoadError};
+
+    enum TestJson {}
+
+    impl Format for TestJson {
+        type SerializeError = serde_json::Error;
+        type DeserializeError = serde_json::Error;
+
+        fn extension() -> &'static str {
+            "json"
+        }
+
+        fn content_type() -> &'static str {
+            "application/json"
+        }
+
+        fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Self::DeserializeError> {
+            serde_json::from_str(s)
+        }
+
+        fn to_string<T: Serialize>(t: &T) -> Result<String, Self::SerializeError> {
+            serde_json::to_string(t)
+        }
+    }
+
+    struct Flaky<'a> {
+        failures: &'a Cell<u32>,
+        kind: ErrorKind,
+        data: &'a [u8],
+    }
+
+    impl Read for Flaky<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(io::Error::from(self.kind))
+            }
+
+            self.data.read(buf)
+        }
+    }
+
+    #[test]
+    fn retries_transient_errors() {
+        let failures = Cell::new(2);
+        let opened = Cell::new(0);
+        let value: Vec<u32> = load_retrying_with::<TestJson, _, _>(
+            "config.json",
+            |_| {
+                opened.set(opened.get() + 1);
+                Ok(Flaky { failures: &failures, kind: ErrorKind::TimedOut, data: b"[1, 2, 3]" })
+            },
+            3,
+            Duration::from_millis(1),
+        ).unwrap();
+
+        assert_eq!(value, [1, 2, 3]);
+        assert_eq!(opened.get(), 3);
+    }
+
+    #[test]
+    fn gives_up_after_attempts() {
+        let failures = Cell::new(5);
+        let result = load_retrying_with::<TestJson, Vec<u32>, _>(
+            "config.json",
+            |_| Ok(Flaky { failures: &failures, kind: ErrorKind::TimedOut, data: b"[]" }),
+            3,
+            Duration::from_millis(1),
+        );
+
+        assert!(matches!(result, Err(LoadError::Read { source, .. }) if source.kind() == ErrorKind::TimedOut));
+        assert_eq!(failures.get(), 2);
+    }
+
+    #[test]
+    fn does_not_retry_permanent_errors() {
+        let opened = Cell::new(0);
+        let result = load_retrying_with::<TestJson, Vec<u32>, io::Empty>(
+            "config.json",
+            |_| {
+                opened.set(opened.get() + 1);
+                Err(io::Error::from(ErrorKind::NotFound))
+            },
+            3,
+            Duration::from_millis(1),
+        );
+
+        assert!(matches!(result, Err(LoadError::Read { source, .. }) if source.kind() == ErrorKind::NotFound));
+        assert_eq!(opened.get(), 1);
+
+        let result = load_retrying_with::<TestJson, Vec<u32>, _>("config.json", |_| Ok(&b"not json"[..]), 3, Duration::from_millis(1));
+
+        assert!(matches!(result, Err(LoadError::Deserialize { path, .. }) if path == Path::new("config.json")));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn retries_binary_formats() {
+        use crate::Cbor;
+
+        let data = Cbor::to_vec(&vec![1u32, 2, 3]).unwrap();
+        let failures = Cell::new(1);
+        let value: Vec<u32> = load_retrying_with::<Cbor, _, _>(
+            "config.cbor",
+            |_| Ok(Flaky { failures: &failures, kind: ErrorKind::TimedOut, data: &data }),
+            2,
+            Duration::from_millis(1),
+        ).unwrap();
+
+        assert_eq!(value, [1, 2, 3]);
+        assert_eq!(failures.get(), 0);
+    }
+}
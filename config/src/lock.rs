@@ -0,0 +1,124 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// How often a lock which is held by someone else is tried again.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The path of the lock file of `path`, which is `<name>.lock` next to it.
+pub fn lock_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".lock");
+
+    path.with_file_name(name)
+}
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum LockError {
+    #[error("timed out after {timeout:?} waiting for the lock '{}'", path.display())]
+    Timeout { path: PathBuf, timeout: Duration },
+
+    #[error("failed to lock '{}'", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// An advisory lock on a file, which is released when dropped, e.g. so that two instances of an
+/// app don't interleave their writes to a config.
+///
+/// The lock is taken on the [lock file](lock_path) of the file rather than the file itself, so
+/// that it survives the file being replaced by [`atomic_write`](crate::atomic_write). The lock
+/// file is created if needed and left behind afterwards, as deleting it would race with others
+/// locking it. Being advisory, the lock only excludes others which lock the file as well.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Takes a shared lock on `path`, e.g. to read it, waiting at most `timeout` for an exclusive
+    /// lock to be released. A `timeout` of zero fails right away if the lock is held.
+    pub fn shared(path: impl AsRef<Path>, timeout: Duration) -> Result<Self, LockError> {
+        Self::acquire(path.as_ref(), timeout, File::try_lock_shared)
+    }
+
+    /// Takes an exclusive lock on `path`, e.g. to write it, waiting at most `timeout` for every
+    /// other lock to be released.
+    pub fn exclusive(path: impl AsRef<Path>, timeout: Duration) -> Result<Self, LockError> {
+        Self::acquire(path.as_ref(), timeout, File::try_lock)
+    }
+
+    fn acquire(path: &Path, timeout: Duration, try_lock: fn(&File) -> Result<(), TryLockError>) -> Result<Self, LockError> {
+        let path = lock_path(path);
+        let io = |source| LockError::Io { path: path.clone(), source };
+
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(io)?;
+        }
+
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path).map_err(io)?;
+        let start = Instant::now();
+
+        loop {
+            match try_lock(&file) {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(TryLockError::WouldBlock) => {
+                    let Some(remaining) = timeout.checked_sub(start.elapsed()).filter(|remaining| !remaining.is_zero()) else {
+                        return Err(LockError::Timeout { path, timeout })
+                    };
+
+                    thread::sleep(remaining.min(POLL_INTERVAL));
+                }
+                Err(TryLockError::Error(source)) => return Err(io(source)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::{lock_path, FileLock, LockError};
+
+    #[test]
+    fn exclusive_locks_block_others() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path();
+        let path = dir.join("settings.toml");
+        let timeout = Duration::from_millis(50);
+
+        let first = FileLock::shared(&path, timeout).unwrap();
+        let second = FileLock::shared(&path, timeout).unwrap();
+        let start = Instant::now();
+
+        assert!(matches!(FileLock::exclusive(&path, timeout), Err(LockError::Timeout { .. })));
+        assert!(start.elapsed() >= timeout);
+        assert!(matches!(FileLock::exclusive(&path, Duration::ZERO), Err(LockError::Timeout { .. })));
+
+        drop((first, second));
+        let exclusive = FileLock::exclusive(&path, timeout).unwrap();
+
+        assert!(matches!(FileLock::shared(&path, timeout), Err(LockError::Timeout { .. })));
+
+        thread::scope(|scope| {
+            let waiter = scope.spawn(|| FileLock::shared(&path, Duration::from_secs(5)).map(drop));
+            thread::sleep(Duration::from_millis(50));
+            drop(exclusive);
+
+            waiter.join().unwrap().unwrap();
+        });
+
+        assert!(lock_path(&path).exists());
+        assert!(!path.exists());
+        assert_eq!(lock_path("config/app.toml"), std::path::Path::new("config/app.toml.lock"));
+    }
+}
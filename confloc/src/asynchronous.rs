@@ -0,0 +1,82 @@
+use std::io;
+use std::panic;
+use alptk_config::Format;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::task;
+use crate::{Config, ConfigError, FileError, FormatConfigError};
+
+impl<T: DeserializeOwned, F: Format> Config<T, F> {
+    /// Like [`load`](Self::load), but reads the file on tokio's blocking thread pool rather than
+    /// blocking the runtime. Deserializing still happens on the current task, as configs are
+    /// usually small.
+    pub async fn load_async(&self) -> Result<T, FormatConfigError<F>> {
+        let (path, options) = (self.path.clone(), self.options);
+        let bytes = blocking(move || crate::read(&path, options), FileError::Read)
+            .await
+            .map_err(|error| error.into_config_error(&self.path))?;
+
+        F::from_slice(&bytes).map_err(ConfigError::Deserialize)
+    }
+}
+
+impl<T: Serialize, F: Format> Config<T, F> {
+    /// Like [`save`](Self::save), but writes the file on tokio's blocking thread pool rather than
+    /// blocking the runtime. Serializing still happens on the current task, as configs are
+    /// usually small.
+    pub async fn save_async(&self, config: &T) -> Result<(), FormatConfigError<F>> {
+        let bytes = F::to_vec(config).map_err(ConfigError::Serialize)?;
+        let (path, options) = (self.path.clone(), self.options);
+
+        blocking(move || crate::write(&path, options, &bytes), FileError::Write)
+            .await
+            .map_err(|error| error.into_config_error(&self.path))
+    }
+}
+
+/// Runs `f` on tokio's blocking thread pool. If `f` panics, the panic is resumed on the current
+/// task, while if the task is cancelled, e.g. as the runtime shuts down, it fails with `cancelled`.
+async fn blocking<R: Send + 'static>(
+    f: impl FnOnce() -> Result<R, FileError> + Send + 'static,
+    cancelled: fn(io::Error) -> FileError,
+) -> Result<R, FileError> {
+    match task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(error) if error.is_panic() => panic::resume_unwind(error.into_panic()),
+        Err(error) => Err(cancelled(io::Error::other(error))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alptk_config::Toml;
+    use alptk_location::ProjectDirsOrEnv;
+    use serde::{Deserialize, Serialize};
+    use crate::{Config, ConfigError, FileError};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Settings {
+        theme: String,
+        plugins: Vec<String>,
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::new(dirs.config_dir().join("nested/settings.toml"));
+        let settings = Settings { theme: "dark".to_owned(), plugins: vec!["git".to_owned()] };
+
+        assert!(matches!(config.load_async().await, Err(ConfigError::Read { .. })));
+
+        config.save_async(&settings).await.unwrap();
+
+        assert_eq!(config.load_async().await.unwrap(), settings);
+        assert_eq!(config.load().unwrap(), settings);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "failed while blocking")]
+    async fn panics_are_resumed() {
+        let _ = super::blocking(|| -> Result<(), FileError> { panic!("failed while blocking") }, FileError::Read).await;
+    }
+}
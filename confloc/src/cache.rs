@@ -0,0 +1,138 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+use alptk_config::Format;
+use serde::de::DeserializeOwned;
+use crate::{Config, FormatConfigError};
+
+/// A [`Config`] which keeps the last loaded value in memory and only reloads the file once it
+/// changed, e.g. for a config read on every request.
+///
+/// The file counts as changed when its modification time or its size differ from when it was
+/// last loaded. Clones share the cache, so a `CachedConfig` can be cloned into every handler
+/// rather than wrapped in an [`Arc`].
+pub struct CachedConfig<T, F> {
+    inner: Arc<Inner<T, F>>,
+}
+
+struct Inner<T, F> {
+    config: Config<T, F>,
+    cached: Mutex<Option<Cached<T>>>,
+}
+
+struct Cached<T> {
+    stamp: Stamp,
+    value: Arc<T>,
+}
+
+/// What identifies a version of the file.
+#[derive(PartialEq, Eq)]
+struct Stamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+
+        Some(Self { modified: metadata.modified().ok()?, len: metadata.len() })
+    }
+}
+
+impl<T, F> Clone for CachedConfig<T, F> {
+    fn clone(&self) -> Self {
+        Self { inner: Arc::clone(&self.inner) }
+    }
+}
+
+impl<T, F: Format> CachedConfig<T, F> {
+    pub fn new(config: Config<T, F>) -> Self {
+        Self { inner: Arc::new(Inner { config, cached: Mutex::new(None) }) }
+    }
+
+    pub fn config(&self) -> &Config<T, F> {
+        &self.inner.config
+    }
+
+    /// Drops the cached value, so that the next [`get`](Self::get) reloads the file.
+    pub fn invalidate(&self) {
+        *self.inner.cached.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+impl<T: DeserializeOwned, F: Format> CachedConfig<T, F> {
+    /// Returns the cached config, or [loads](Config::load) it if the file changed since it was
+    /// last loaded. Errors aren't cached, so the next call tries again.
+    ///
+    /// Concurrent calls wait for each other, so the file is loaded at most once per change.
+    pub fn get(&self) -> Result<Arc<T>, FormatConfigError<F>> {
+        let mut cached = self.inner.cached.lock().unwrap_or_else(PoisonError::into_inner);
+        let stamp = Stamp::of(self.inner.config.path());
+
+        if let (Some(cached), Some(stamp)) = (&*cached, &stamp) {
+            if cached.stamp == *stamp {
+                return Ok(Arc::clone(&cached.value))
+            }
+        }
+
+        let value = Arc::new(self.inner.config.load()?);
+        *cached = stamp.map(|stamp| Cached { stamp, value: Arc::clone(&value) });
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+    use alptk_config::Toml;
+    use alptk_location::{DirKind, ProjectDirsOrEnv};
+    use serde::{Deserialize, Serialize};
+    use crate::{CachedConfig, Config, ConfigError};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Settings {
+        theme: String,
+    }
+
+    fn touch(path: &std::path::Path, modified: SystemTime) {
+        File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn reloads_only_after_a_change() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap();
+        let path = config.path().to_owned();
+        let start = SystemTime::now() - Duration::from_secs(60);
+        config.save(&Settings { theme: "light".to_owned() }).unwrap();
+        touch(&path, start);
+
+        let cached = CachedConfig::new(config);
+        let first = cached.get().unwrap();
+
+        assert!(Arc::ptr_eq(&first, &cached.get().unwrap()), "the unchanged file was parsed again");
+        assert!(Arc::ptr_eq(&first, &cached.clone().get().unwrap()), "clones don't share the cache");
+
+        fs::write(&path, "theme = \"dark\"").unwrap();
+        touch(&path, start + Duration::from_secs(1));
+        let second = cached.get().unwrap();
+
+        assert_eq!(second.theme, "dark");
+        assert!(!Arc::ptr_eq(&first, &second));
+
+        // touching alone counts as a change as well
+        touch(&path, start + Duration::from_secs(2));
+
+        assert!(!Arc::ptr_eq(&second, &cached.get().unwrap()));
+
+        cached.invalidate();
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(cached.get(), Err(ConfigError::Read { .. })));
+    }
+}
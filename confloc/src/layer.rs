@@ -0,0 +1,270 @@
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use alptk_config::Format;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use crate::{Config, ConfigError, FormatConfigError};
+
+/// A layer of a config loaded with [`Config::load_layered`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Source<'a> {
+    /// A value built in code.
+    Value(Value),
+
+    /// Data in the format of the config, e.g. defaults baked into the binary with
+    /// [`include_bytes!`].
+    Bytes(&'a [u8]),
+
+    /// The file at the path, which must exist.
+    File(&'a Path),
+
+    /// The file at the path, which is skipped if it doesn't exist.
+    OptionalFile(&'a Path),
+
+    /// The environment variables starting with the prefix followed by an underscore, e.g.
+    /// `APP_THEME` for the prefix `APP`. The rest of the name is lowercased and split at double
+    /// underscores into nested keys, so `APP_SERVER__PORT` sets `port` in the `server` map. Values
+    /// are parsed as JSON if possible, and taken as strings otherwise. Variables whose name or
+    /// value isn't valid unicode are ignored.
+    Env(&'a str),
+}
+
+/// Deep merges `overlay` into `base`: maps are merged recursively, key by key, while everything
+/// else, including arrays, is replaced by the value in `overlay`.
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base) => merge(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// The environment variables starting with `prefix` followed by an underscore, as the path of
+/// nested keys they set and their value. See [`Source::Env`].
+fn env_vars(prefix: &str) -> impl Iterator<Item = (Vec<String>, String)> {
+    let prefix = format!("{prefix}_");
+
+    env::vars_os().filter_map(move |(name, value)| {
+        let key = name.to_str()?.strip_prefix(&prefix)?;
+        let path = key.split("__").map(str::to_lowercase).collect();
+
+        Some((path, value.into_string().ok()?))
+    })
+}
+
+/// Parses the value of an environment variable as JSON if possible, and takes it as a string
+/// otherwise.
+fn parse_env(value: String) -> Value {
+    serde_json::from_str(&value).unwrap_or(Value::String(value))
+}
+
+fn env_value(prefix: &str) -> Value {
+    let mut root = Value::Object(Map::new());
+
+    for (path, value) in env_vars(prefix) {
+        let value = path.into_iter().rev().fold(parse_env(value), |value, key| {
+            Value::Object(Map::from_iter([(key, value)]))
+        });
+
+        merge(&mut root, value);
+    }
+
+    root
+}
+
+/// Sets the keys of `value` named by the environment variables with `prefix`, like
+/// [`Source::Env`], except that the values of keys which are already strings are kept as strings
+/// rather than parsed, so that e.g. a version string of `1.0` isn't turned into a number.
+fn splice_env(value: &mut Value, prefix: &str) {
+    for (path, raw) in env_vars(prefix) {
+        let mut target = &mut *value;
+
+        for key in path {
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+
+            target = target.as_object_mut().unwrap().entry(key).or_insert(Value::Null);
+        }
+
+        *target = match target {
+            Value::String(_) => Value::String(raw),
+            _ => parse_env(raw),
+        };
+    }
+}
+
+impl<T: DeserializeOwned, F: Format> Config<T, F> {
+    /// Loads a config from several sources, where later sources override the keys of earlier ones
+    /// as described in [`merge`], e.g. defaults baked into the binary, then the system config and
+    /// then the user config:
+    ///
+    /// ```no_run
+    /// # use alptk_config::Toml;
+    /// # use alptk_config_location::{Config, Source};
+    /// # use alptk_location::ProjectDirsOrEnv;
+    /// # #[derive(serde::Deserialize)]
+    /// # struct Settings {}
+    /// let dirs = ProjectDirsOrEnv::new("app", "APP")?;
+    /// let system = dirs.site_config_dirs().first().map(|dir| dir.join("settings.toml"));
+    /// let user = dirs.config_dir().join("settings.toml");
+    ///
+    /// let mut sources = vec![Source::Bytes(b"theme = \"light\"")];
+    /// sources.extend(system.as_deref().map(Source::OptionalFile));
+    /// sources.extend([Source::OptionalFile(&user), Source::Env("APP_CONFIG")]);
+    ///
+    /// let settings = Config::<Settings, Toml>::load_layered(&sources)?;
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// Every source is deserialized into a [`Value`] first, so the config must be representable
+    /// as JSON.
+    pub fn load_layered(sources: &[Source]) -> Result<T, FormatConfigError<F>> {
+        let mut value = Value::Object(Map::new());
+
+        for source in sources {
+            let layer = match source {
+                Source::Value(layer) => layer.clone(),
+                Source::Bytes(bytes) => F::from_slice(bytes).map_err(ConfigError::Deserialize)?,
+                Source::File(path) | Source::OptionalFile(path) => match fs::read(path) {
+                    Ok(bytes) => F::from_slice(&bytes).map_err(ConfigError::Deserialize)?,
+                    Err(error) if error.kind() == ErrorKind::NotFound && matches!(source, Source::OptionalFile(_)) => continue,
+                    Err(error) => return Err(ConfigError::Read { path: path.to_path_buf(), source: error }),
+                },
+                Source::Env(prefix) => env_value(prefix),
+            };
+
+            merge(&mut value, layer);
+        }
+
+        serde_json::from_value(value).map_err(ConfigError::FromValue)
+    }
+
+    /// Like [`load`](Self::load), but lets environment variables starting with `prefix` followed
+    /// by an underscore override the keys of the file, e.g. `APP_SERVER__PORT=8080` for the
+    /// prefix `APP` sets `port` in the `server` map. The names are mapped to keys as described in
+    /// [`Source::Env`].
+    ///
+    /// Values are parsed as JSON, so that numbers and booleans are coerced by serde as usual,
+    /// unless the file already has a string at that key, in which case they are kept as strings.
+    /// Like every other layer, the file is deserialized into a [`Value`] first, so the config must
+    /// be representable as JSON.
+    pub fn with_env_overrides(&self, prefix: &str) -> Result<T, FormatConfigError<F>> {
+        let mut value: Value = F::from_slice(&self.read()?).map_err(ConfigError::Deserialize)?;
+        splice_env(&mut value, prefix);
+
+        serde_json::from_value(value).map_err(ConfigError::FromValue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use alptk_config::Toml;
+    use alptk_location::ProjectDirsOrEnv;
+    use serde::Deserialize;
+    use serde_json::json;
+    use crate::{merge, Config, ConfigError, Source};
+
+    #[test]
+    fn maps_merge_recursively() {
+        let mut base = json!({ "server": { "host": "localhost", "tls": { "enabled": false, "cert": "a.pem" } }, "theme": "light" });
+        merge(&mut base, json!({ "server": { "tls": { "enabled": true } }, "debug": true }));
+
+        assert_eq!(base, json!({
+            "server": { "host": "localhost", "tls": { "enabled": true, "cert": "a.pem" } },
+            "theme": "light",
+            "debug": true,
+        }));
+    }
+
+    #[test]
+    fn arrays_and_scalars_replace() {
+        let mut base = json!({ "plugins": ["git", "lsp"], "server": { "port": 80 }, "theme": "light" });
+        merge(&mut base, json!({ "plugins": ["fmt"], "server": 8080, "theme": null }));
+
+        assert_eq!(base, json!({ "plugins": ["fmt"], "server": 8080, "theme": null }));
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Settings {
+        theme: String,
+        plugins: Vec<String>,
+        server: Server,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[test]
+    fn later_sources_override() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let system = dirs.site_config_dirs()[0].join("settings.toml");
+        let user = dirs.config_dir().join("settings.toml");
+        let missing = dirs.data_dir().join("missing.toml");
+        fs::write(&system, "plugins = [\"git\", \"lsp\"]\n[server]\nport = 8080").unwrap();
+        fs::write(&user, "plugins = [\"fmt\"]").unwrap();
+        env::set_var("ALPTK_CONFLOC_LAYER_TEST_SERVER__HOST", "example.com");
+
+        let sources = [
+            Source::Bytes(b"theme = \"light\"\nplugins = []\n[server]\nhost = \"localhost\"\nport = 80"),
+            Source::File(&system),
+            Source::OptionalFile(&missing),
+            Source::OptionalFile(&user),
+            Source::Env("ALPTK_CONFLOC_LAYER_TEST"),
+        ];
+        let settings = Config::<Settings, Toml>::load_layered(&sources).unwrap();
+
+        assert_eq!(settings, Settings {
+            theme: "light".to_owned(),
+            plugins: vec!["fmt".to_owned()],
+            server: Server { host: "example.com".to_owned(), port: 8080 },
+        });
+        assert!(matches!(
+            Config::<Settings, Toml>::load_layered(&[Source::File(&missing)]),
+            Err(ConfigError::Read { .. })
+        ));
+        assert!(matches!(
+            Config::<Settings, Toml>::load_layered(&[Source::Bytes(b"theme = \"light\"")]),
+            Err(ConfigError::FromValue(_))
+        ));
+    }
+
+    #[test]
+    fn env_overrides_the_file() {
+        const PREFIX: &str = "ALPTK_CONFLOC_ENV_OVERRIDE_TEST";
+
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::new(dirs.config_dir().join("settings.toml"));
+        fs::write(config.path(), "theme = \"light\"\nplugins = []\n[server]\nhost = \"localhost\"\nport = 80").unwrap();
+        env::set_var(format!("{PREFIX}_SERVER__HOST"), "example.com");
+        env::set_var(format!("{PREFIX}_SERVER__PORT"), "8080");
+        env::set_var(format!("{PREFIX}_THEME"), "42");
+        env::set_var(format!("{PREFIX}_PLUGINS"), "[\"git\"]");
+
+        assert_eq!(config.with_env_overrides(PREFIX).unwrap(), Settings {
+            theme: "42".to_owned(),
+            plugins: vec!["git".to_owned()],
+            server: Server { host: "example.com".to_owned(), port: 8080 },
+        });
+
+        env::set_var(format!("{PREFIX}_SERVER__PORT"), "http");
+
+        assert!(matches!(config.with_env_overrides(PREFIX), Err(ConfigError::FromValue(_))));
+    }
+}
@@ -1,14 +1,518 @@
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use alptk_config::{atomic_write, back_up, FileLock, Format, FromSliceError, LockError};
+use alptk_location::{DirKind, ProjectDirsOrEnv};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod cache;
+mod layer;
+mod managed;
+mod migrate;
+#[cfg(feature = "validate")]
+mod validate;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use cache::*;
+pub use layer::*;
+pub use managed::*;
+pub use migrate::*;
+#[cfg(feature = "validate")]
+pub use validate::*;
+#[cfg(feature = "watch")]
+pub use watch::*;
+#[cfg(feature = "watch")]
+pub use notify;
+pub use serde_json;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ConfigError<D, S> {
+    #[error("failed to read the config at '{}'", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to write the config at '{}'", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// Backing up the previous config failed, so it wasn't overwritten. See
+    /// [`Config::with_backups`].
+    #[error("failed to back up the config at '{}'", path.display())]
+    Backup {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to deserialize the config")]
+    Deserialize(#[source] FromSliceError<D>),
+
+    #[error("failed to serialize the config")]
+    Serialize(#[source] S),
+
+    /// Converting the [intermediate value](serde_json::Value) of [`Config::load_layered`] and
+    /// [`Config::load_strict`] to the config failed.
+    #[error("failed to deserialize the config from its intermediate value")]
+    FromValue(#[source] serde_json::Error),
+
+    #[error("unknown keys in the config: {}", .0.join(", "))]
+    UnknownKeys(Vec<String>),
+
+    #[error("failed to migrate the config")]
+    Migration(#[from] MigrationError),
+
+    /// The config doesn't match its schema. See [`Config::load_validated`].
+    #[cfg(feature = "validate")]
+    #[error("failed to validate the config")]
+    Validation(#[from] ValidationError),
+
+    /// The config couldn't be locked. See [`Config::with_lock`].
+    #[error("failed to lock the config")]
+    Lock(#[from] LockError),
+}
+
+/// The [`ConfigError`] of the format `F`.
+pub type FormatConfigError<F> = ConfigError<<F as Format>::DeserializeError, <F as Format>::SerializeError>;
+
+/// A config of type `T`, stored in the format `F` at a fixed path, typically inside one of the
+/// directories of a [`ProjectDirsOrEnv`].
+///
+/// ```no_run
+/// # use alptk_config::Toml;
+/// # use alptk_config_location::Config;
+/// # use alptk_location::{DirKind, ProjectDirsOrEnv};
+/// #[derive(serde::Serialize, serde::Deserialize, Default)]
+/// struct Settings {
+///     theme: String,
+/// }
+///
+/// let dirs = ProjectDirsOrEnv::new("app", "APP")?;
+/// let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap();
+///
+/// let mut settings = config.load_or_default()?;
+/// settings.theme = "dark".to_owned();
+/// config.save(&settings)?;
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub struct Config<T, F> {
+    path: PathBuf,
+    options: Options,
+    _marker: PhantomData<fn() -> (T, F)>,
+}
+
+/// How a [`Config`] accesses its file, which is shared with copies of the config made
+/// internally, e.g. by [`Config::watch`].
+#[derive(Clone, Copy, Default)]
+struct Options {
+    backups: usize,
+    lock_timeout: Option<Duration>,
+}
+
+impl<T, F: Format> Config<T, F> {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), options: Options::default(), _marker: PhantomData }
+    }
+
+    /// A config named `file_name` in the directory of the given kind, or `None` if that directory
+    /// isn't set. Note that [`DirKind::ProjectPath`] is a relative path rather than a directory.
+    pub fn in_dir(dirs: &ProjectDirsOrEnv, kind: DirKind, file_name: impl AsRef<Path>) -> Option<Self> {
+        dirs.get(kind).map(|dir| Self::new(dir.join(file_name)))
+    }
+
+    /// Keeps the last `keep` versions of the file as [backups](alptk_config::backup_path) when
+    /// saving, e.g. so that users can recover yesterday's config after it was mangled. Saving
+    /// content which is the same as the file's doesn't create a backup. Off by default.
+    ///
+    /// Backups are made by [`save`](Self::save), [`patch`](Self::patch) and the functions based
+    /// on them. Use [`restore_backup`](alptk_config::restore_backup) to bring one back.
+    pub fn with_backups(mut self, keep: usize) -> Self {
+        self.options.backups = keep;
+        self
+    }
+
+    /// Takes an advisory [lock](FileLock) on the file while reading and writing it, waiting at
+    /// most `timeout` for others to release it, e.g. so that two instances of an app don't
+    /// interleave their saves. Reads take a shared lock, and writes an exclusive one. If the lock
+    /// isn't released in time, [`ConfigError::Lock`] is returned. Off by default.
+    ///
+    /// The lock is held on a `.lock` file next to the file, which is left behind. Only others
+    /// which lock the file, such as other instances of the app, are excluded.
+    pub fn with_lock(mut self, timeout: Duration) -> Self {
+        self.options.lock_timeout = Some(timeout);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn write(&self, bytes: &[u8]) -> Result<(), FormatConfigError<F>> {
+        write(&self.path, self.options, bytes).map_err(|error| error.into_config_error(&self.path))
+    }
+}
+
+impl<T: DeserializeOwned, F: Format> Config<T, F> {
+    pub fn load(&self) -> Result<T, FormatConfigError<F>> {
+        F::from_slice(&self.read()?).map_err(ConfigError::Deserialize)
+    }
+
+    /// Like [`load`](Self::load), but fails with [`ConfigError::UnknownKeys`] if the file contains
+    /// keys which `T` doesn't know, e.g. because of a typo, rather than ignoring them. The keys are
+    /// listed as paths such as `server.port`.
+    ///
+    /// The file is deserialized into a [`Value`](serde_json::Value) first, which the config is then
+    /// deserialized from, so the config must be representable as JSON.
+    pub fn load_strict(&self) -> Result<T, FormatConfigError<F>> {
+        let value: serde_json::Value = F::from_slice(&self.read()?).map_err(ConfigError::Deserialize)?;
+        let mut unknown = Vec::new();
+        let config = serde_ignored::deserialize(value, |path| unknown.push(path.to_string()))
+            .map_err(ConfigError::FromValue)?;
+
+        if !unknown.is_empty() {
+            return Err(ConfigError::UnknownKeys(unknown))
+        }
+
+        Ok(config)
+    }
+
+    fn read(&self) -> Result<Vec<u8>, FormatConfigError<F>> {
+        read(&self.path, self.options).map_err(|error| error.into_config_error(&self.path))
+    }
+
+    /// Like [`load`](Self::load), but returns the default config if the file doesn't exist. Every
+    /// other error is still returned.
+    pub fn load_or_default(&self) -> Result<T, FormatConfigError<F>>
+    where
+        T: Default,
+    {
+        match self.load() {
+            Err(ConfigError::Read { source, .. }) if source.kind() == ErrorKind::NotFound => Ok(T::default()),
+            result => result,
+        }
+    }
+}
+
+impl<T: Serialize, F: Format> Config<T, F> {
+    /// Serializes `config` to the file, creating its parent directories if needed.
+    ///
+    /// The file is written [atomically](alptk_config::atomic_write), so a crash while saving
+    /// leaves the previous config behind rather than a partially written one.
+    pub fn save(&self, config: &T) -> Result<(), FormatConfigError<F>> {
+        let bytes = F::to_vec(config).map_err(ConfigError::Serialize)?;
+
+        self.write(&bytes)
+    }
+
+    /// Like [`load_or_default`](Self::load_or_default), but also saves the default config if the
+    /// file doesn't exist, so that users find a config to edit after the first run. An existing
+    /// file is never overwritten, even if it fails to deserialize.
+    pub fn load_or_create_default(&self) -> Result<T, FormatConfigError<F>>
+    where
+        T: Default + DeserializeOwned,
+    {
+        match self.load() {
+            Err(ConfigError::Read { source, .. }) if source.kind() == ErrorKind::NotFound => {
+                let config = T::default();
+                self.save(&config)?;
+
+                Ok(config)
+            }
+            result => result,
+        }
+    }
+
+    /// An alias of [`save`](Self::save), which always writes atomically, for call sites which want
+    /// to spell that out.
+    pub fn save_atomic(&self, config: &T) -> Result<(), FormatConfigError<F>> {
+        self.save(config)
+    }
+}
+
+impl<T, F: Format> Config<T, F> {
+    /// Updates the file in place by letting `patch` modify its [`Value`](serde_json::Value),
+    /// which is then saved back [atomically](alptk_config::atomic_write), e.g. to change a single key.
+    ///
+    /// Unlike loading, modifying and saving a `T`, keys which `T` doesn't know about are kept,
+    /// so the file can be shared with other tools. The value isn't checked against `T`. The file
+    /// must exist and be representable as JSON.
+    pub fn patch(&self, patch: impl FnOnce(&mut serde_json::Value)) -> Result<(), FormatConfigError<F>> {
+        // the lock is held from reading until writing, so that no other save is lost in between
+        let _lock = lock(&self.path, self.options, true)?;
+        let bytes = fs::read(&self.path).map_err(|source| ConfigError::Read { path: self.path.clone(), source })?;
+        let mut value: serde_json::Value = F::from_slice(&bytes).map_err(ConfigError::Deserialize)?;
+        patch(&mut value);
+        let bytes = F::to_vec(&value).map_err(ConfigError::Serialize)?;
+
+        write_locked(&self.path, self.options, &bytes).map_err(|error| error.into_config_error(&self.path))
+    }
+}
+
+/// Loads the config at `path`, or if the file doesn't exist, saves the default config there,
+/// creating its parent directories, and returns it. A shorthand for
+/// [`Config::load_or_create_default`] for configs at a known path.
+///
+/// A file which exists but fails to load is an error, and is left as it is rather than being
+/// replaced by the default.
+pub fn load_or_default<T, F>(path: impl Into<PathBuf>) -> Result<T, FormatConfigError<F>>
+where
+    T: Serialize + DeserializeOwned + Default,
+    F: Format,
+{
+    Config::<T, F>::new(path).load_or_create_default()
+}
+
+/// Takes a shared or `exclusive` lock on `path` if [enabled](Config::with_lock).
+fn lock(path: &Path, options: Options, exclusive: bool) -> Result<Option<FileLock>, LockError> {
+    let Some(timeout) = options.lock_timeout else { return Ok(None) };
+
+    if exclusive {
+        FileLock::exclusive(path, timeout).map(Some)
+    } else {
+        FileLock::shared(path, timeout).map(Some)
+    }
+}
+
+fn read(path: &Path, options: Options) -> Result<Vec<u8>, FileError> {
+    let _lock = lock(path, options, false).map_err(FileError::Lock)?;
+
+    fs::read(path).map_err(FileError::Read)
+}
+
+fn write(path: &Path, options: Options, bytes: &[u8]) -> Result<(), FileError> {
+    let _lock = lock(path, options, true).map_err(FileError::Lock)?;
+
+    write_locked(path, options, bytes)
+}
+
+/// Backs up the file at `path` if [enabled](Config::with_backups) and `bytes` differ from it,
+/// then writes `bytes` to it atomically. The caller must hold the exclusive lock, if enabled.
+fn write_locked(path: &Path, options: Options, bytes: &[u8]) -> Result<(), FileError> {
+    if options.backups > 0 && fs::read(path).is_ok_and(|existing| existing != bytes) {
+        back_up(path, options.backups).map_err(FileError::Backup)?;
+    }
+
+    atomic_write(path, bytes).map_err(FileError::Write)
+}
+
+/// An error of accessing the file of a config, independent of its type and format.
+enum FileError {
+    Lock(LockError),
+    Read(io::Error),
+    Backup(io::Error),
+    Write(io::Error),
+}
+
+impl FileError {
+    fn into_config_error<D, S>(self, path: &Path) -> ConfigError<D, S> {
+        match self {
+            Self::Lock(error) => ConfigError::Lock(error),
+            Self::Read(source) => ConfigError::Read { path: path.to_owned(), source },
+            Self::Backup(source) => ConfigError::Backup { path: path.to_owned(), source },
+            Self::Write(source) => ConfigError::Write { path: path.to_owned(), source },
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+    use alptk_config::{backup_path, restore_backup, FileLock, Format, LockError, Toml};
+    use alptk_location::{DirKind, ProjectDirsOrEnv};
+    use serde::{Deserialize, Serialize};
+    use crate::{load_or_default, Config, ConfigError};
+
+    #[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+    struct Settings {
+        theme: String,
+        font_size: u32,
+        plugins: Vec<String>,
+    }
+
+    #[test]
+    fn round_trip_in_config_dir() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "ui/settings.toml").unwrap();
+        let settings = Settings { theme: "dark".to_owned(), font_size: 13, plugins: vec!["git".to_owned()] };
+
+        assert_eq!(config.path(), dirs.config_dir().join("ui/settings.toml"));
+        assert_eq!(config.load_or_default().unwrap(), Settings::default());
+
+        config.save(&settings).unwrap();
+
+        assert!(fs::read_to_string(config.path()).unwrap().contains("theme = \"dark\""));
+        assert_eq!(config.load().unwrap(), settings);
+        assert_eq!(config.load_or_default().unwrap(), settings);
+    }
+
+    #[test]
+    fn save_atomic_replaces_existing() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "ui/settings.toml").unwrap();
+        let settings = Settings { theme: "dark".to_owned(), ..Settings::default() };
+
+        config.save(&Settings::default()).unwrap();
+        config.save_atomic(&settings).unwrap();
+
+        assert_eq!(config.load().unwrap(), settings);
+        assert_eq!(fs::read_dir(dirs.config_dir().join("ui")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn saves_rotate_backups() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap().with_backups(2);
+        let settings = |theme: &str| Settings { theme: theme.to_owned(), ..Settings::default() };
+        let backup = |index| Toml::load_path::<Settings>(backup_path(config.path(), index)).ok().map(|settings| settings.theme);
+
+        for theme in ["light", "dark", "dark", "solarized", "nord"] {
+            config.save(&settings(theme)).unwrap();
+        }
+
+        assert_eq!(config.load().unwrap(), settings("nord"));
+        assert_eq!([backup(1), backup(2), backup(3)], [Some("solarized".to_owned()), Some("dark".to_owned()), None]);
+
+        config.patch(|value| value["theme"] = "gruvbox".into()).unwrap();
+
+        assert_eq!([backup(1), backup(2)], [Some("nord".to_owned()), Some("solarized".to_owned())]);
+
+        restore_backup(config.path(), 2).unwrap();
+
+        assert_eq!(config.load().unwrap(), settings("solarized"));
+        assert_eq!(fs::read_dir(dirs.config_dir()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn locking_serializes_access() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let timeout = Duration::from_millis(50);
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap().with_lock(timeout);
+        config.save(&Settings::default()).unwrap();
+
+        let held = FileLock::exclusive(config.path(), timeout).unwrap();
+
+        assert!(matches!(config.load(), Err(ConfigError::Lock(LockError::Timeout { .. }))));
+        assert!(matches!(config.save(&Settings::default()), Err(ConfigError::Lock(LockError::Timeout { .. }))));
+
+        drop(held);
+        let config = config.with_lock(Duration::from_secs(5));
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    config
+                        .patch(|value| value["font_size"] = (value["font_size"].as_u64().unwrap() + 1).into())
+                        .unwrap();
+                });
+            }
+        });
+
+        assert_eq!(config.load().unwrap().font_size, 8);
+    }
 
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn patch_keeps_unknown_keys() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap();
+        fs::write(config.path(), "theme = \"light\"\nfont_size = 12\nplugins = []\n\n[other_tool]\nenabled = true\n").unwrap();
+
+        config.patch(|value| value["theme"] = "dark".into()).unwrap();
+
+        assert_eq!(config.load().unwrap().theme, "dark");
+        assert!(fs::read_to_string(config.path()).unwrap().contains("[other_tool]\nenabled = true"));
+
+        let missing = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "missing.toml").unwrap();
+
+        assert!(matches!(missing.patch(|_| {}), Err(ConfigError::Read { .. })));
+    }
+
+    #[test]
+    fn load_or_create_default_creates_missing() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "ui/settings.toml").unwrap();
+
+        assert_eq!(config.load_or_create_default().unwrap(), Settings::default());
+        assert_eq!(config.load().unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn load_or_create_default_loads_present() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap();
+        fs::write(config.path(), "theme = \"dark\"\nfont_size = 13\nplugins = []").unwrap();
+
+        assert_eq!(config.load_or_create_default().unwrap().theme, "dark");
+
+        fs::write(config.path(), "theme = ").unwrap();
+
+        assert!(matches!(config.load_or_create_default(), Err(ConfigError::Deserialize(_))));
+        assert_eq!(fs::read_to_string(config.path()).unwrap(), "theme = ");
+    }
+
+    #[test]
+    fn load_or_default_at_a_path() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let path = dirs.get(DirKind::Config).unwrap().join("app/settings.toml");
+
+        assert_eq!(load_or_default::<Settings, Toml>(&path).unwrap(), Settings::default());
+        assert_eq!(Toml::from_str::<Settings>(&fs::read_to_string(&path).unwrap()).unwrap(), Settings::default());
+
+        fs::write(&path, "theme = \"dark\"\nfont_size = 13\nplugins = [\"git\"]").unwrap();
+
+        assert_eq!(
+            load_or_default::<Settings, Toml>(&path).unwrap(),
+            Settings { theme: "dark".to_owned(), font_size: 13, plugins: vec!["git".to_owned()] }
+        );
+
+        fs::write(&path, "font_size = \"13\"").unwrap();
+
+        assert!(matches!(load_or_default::<Settings, Toml>(&path), Err(ConfigError::Deserialize(_))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "font_size = \"13\"");
+    }
+
+    #[test]
+    fn load_strict_reports_unknown_keys() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap();
+        fs::write(config.path(), "theme = \"dark\"\nfont_size = 13\nplugins = []").unwrap();
+
+        assert_eq!(config.load_strict().unwrap().theme, "dark");
+
+        fs::write(config.path(), "theme = \"dark\"\nfont_sise = 13\nfont_size = 13\nplugins = []\n[server]\nport = 1").unwrap();
+
+        assert_eq!(config.load().unwrap().theme, "dark");
+
+        match config.load_strict() {
+            Err(ConfigError::UnknownKeys(keys)) => assert_eq!(keys, ["font_sise", "server"]),
+            _ => panic!("expected unknown keys"),
+        }
+    }
+
+    #[test]
+    fn load_errors() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Data, "settings.toml").unwrap();
+
+        assert!(matches!(config.load(), Err(ConfigError::Read { .. })));
+
+        fs::write(config.path(), "theme = ").unwrap();
+
+        assert!(matches!(config.load(), Err(ConfigError::Deserialize(_))));
+        assert!(matches!(config.load_or_default(), Err(ConfigError::Deserialize(_))));
     }
 }
@@ -0,0 +1,215 @@
+use std::io::ErrorKind;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
+use alptk_config::Format;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::{Config, ConfigError, FormatConfigError};
+
+/// A [`Config`] together with its current value, e.g. to share the settings of an app between
+/// its subsystems, which read them with [`get`](Self::get) and change them with
+/// [`modify`](Self::modify).
+///
+/// Clones share the value, so a `ManagedConfig` can be cloned into every subsystem rather than
+/// wrapped in an [`Arc`]. Unlike [`CachedConfig`](crate::CachedConfig), the file is only read
+/// when asked to, so changes made to it by others are only seen after [`load`](Self::load).
+pub struct ManagedConfig<T, F> {
+    inner: Arc<Inner<T, F>>,
+}
+
+struct Inner<T, F> {
+    config: Config<T, F>,
+    state: RwLock<State<T>>,
+}
+
+struct State<T> {
+    value: Arc<T>,
+    existed: bool,
+}
+
+impl<T, F> Clone for ManagedConfig<T, F> {
+    fn clone(&self) -> Self {
+        Self { inner: Arc::clone(&self.inner) }
+    }
+}
+
+impl<T, F: Format> ManagedConfig<T, F> {
+    pub fn config(&self) -> &Config<T, F> {
+        &self.inner.config
+    }
+
+    /// The current value, which stays the same even if the config is modified afterwards.
+    pub fn get(&self) -> Arc<T> {
+        Arc::clone(&self.state().value)
+    }
+
+    /// Whether the file existed when the config was last loaded or saved. If it didn't, the
+    /// value is the default one.
+    pub fn existed(&self) -> bool {
+        self.state().existed
+    }
+
+    fn state(&self) -> RwLockReadGuard<'_, State<T>> {
+        self.inner.state.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: DeserializeOwned + Default, F: Format> ManagedConfig<T, F> {
+    /// Loads the config, or uses the default value if the file doesn't exist, without creating
+    /// it. The file is created by the first [`save`](Self::save) or [`modify`](Self::modify).
+    ///
+    /// A file which exists but fails to load is an error rather than being replaced by the
+    /// default, so a [`ConfigError::Deserialize`] tells a broken file apart from a
+    /// [`ConfigError::Read`] of an unreadable one.
+    pub fn open(config: Config<T, F>) -> Result<Self, FormatConfigError<F>> {
+        let state = RwLock::new(load(&config)?);
+
+        Ok(Self { inner: Arc::new(Inner { config, state }) })
+    }
+
+    /// Reloads the value from the file, e.g. after it was edited by hand. If this fails, the
+    /// current value is kept.
+    pub fn load(&self) -> Result<(), FormatConfigError<F>> {
+        let state = load(&self.inner.config)?;
+        *self.inner.state.write().unwrap_or_else(PoisonError::into_inner) = state;
+
+        Ok(())
+    }
+}
+
+fn load<T: DeserializeOwned + Default, F: Format>(config: &Config<T, F>) -> Result<State<T>, FormatConfigError<F>> {
+    match config.load() {
+        Ok(value) => Ok(State { value: Arc::new(value), existed: true }),
+        Err(ConfigError::Read { source, .. }) if source.kind() == ErrorKind::NotFound => {
+            Ok(State { value: Arc::new(T::default()), existed: false })
+        }
+        Err(error) => Err(error),
+    }
+}
+
+impl<T: Serialize, F: Format> ManagedConfig<T, F> {
+    /// Saves the current value to the file.
+    pub fn save(&self) -> Result<(), FormatConfigError<F>> {
+        let mut state = self.inner.state.write().unwrap_or_else(PoisonError::into_inner);
+        self.inner.config.save(&state.value)?;
+        state.existed = true;
+
+        Ok(())
+    }
+
+    /// Applies `modify` to a copy of the current value and saves it, and only if that succeeds,
+    /// makes it the current value. If saving fails, neither the value nor the file change.
+    ///
+    /// Calls are serialized, so concurrent modifications don't overwrite each other, and
+    /// [`get`](Self::get) waits for a modification to finish.
+    pub fn modify<R>(&self, modify: impl FnOnce(&mut T) -> R) -> Result<R, FormatConfigError<F>>
+    where
+        T: Clone,
+    {
+        let mut state = self.inner.state.write().unwrap_or_else(PoisonError::into_inner);
+        let mut value = T::clone(&state.value);
+        let result = modify(&mut value);
+        self.inner.config.save(&value)?;
+        *state = State { value: Arc::new(value), existed: true };
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::Arc;
+    use std::thread;
+    use alptk_config::Toml;
+    use alptk_location::{DirKind, ProjectDirsOrEnv};
+    use serde::{Deserialize, Serialize, Serializer};
+    use crate::{Config, ConfigError, ManagedConfig};
+
+    #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+    struct Settings {
+        #[serde(serialize_with = "reject_invalid")]
+        theme: String,
+        launches: u32,
+    }
+
+    fn reject_invalid<S: Serializer>(theme: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        if theme == "invalid" {
+            return Err(serde::ser::Error::custom("invalid theme"))
+        }
+
+        serializer.serialize_str(theme)
+    }
+
+    fn config(dirs: &ProjectDirsOrEnv) -> Config<Settings, Toml> {
+        Config::in_dir(dirs, DirKind::Config, "settings.toml").unwrap()
+    }
+
+    #[test]
+    fn missing_files_are_created_on_save() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let managed = ManagedConfig::open(config(&dirs)).unwrap();
+
+        assert!(!managed.existed());
+        assert_eq!(*managed.get(), Settings::default());
+        assert!(!managed.config().path().exists(), "opening created the file");
+
+        let before = managed.get();
+        managed.modify(|settings| settings.theme = "dark".to_owned()).unwrap();
+
+        assert!(managed.existed());
+        assert_eq!(*before, Settings::default());
+        assert_eq!(managed.get().theme, "dark");
+        assert_eq!(config(&dirs).load().unwrap().theme, "dark");
+
+        fs::write(managed.config().path(), "theme = \"light\"\nlaunches = 3").unwrap();
+        managed.load().unwrap();
+
+        assert_eq!(*managed.get(), Settings { theme: "light".to_owned(), launches: 3 });
+
+        fs::write(managed.config().path(), "theme = ").unwrap();
+
+        assert!(matches!(managed.load(), Err(ConfigError::Deserialize(_))));
+        assert_eq!(managed.get().theme, "light");
+        assert!(matches!(ManagedConfig::open(config(&dirs)), Err(ConfigError::Deserialize(_))));
+    }
+
+    #[test]
+    fn concurrent_modifications() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let managed = ManagedConfig::open(config(&dirs)).unwrap();
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                let managed = managed.clone();
+
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        managed.modify(|settings| settings.launches += 1).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(managed.get().launches, 80);
+        assert_eq!(config(&dirs).load().unwrap().launches, 80);
+    }
+
+    #[test]
+    fn failed_saves_change_nothing() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let managed = ManagedConfig::open(config(&dirs)).unwrap();
+        managed.modify(|settings| settings.theme = "dark".to_owned()).unwrap();
+        let saved = fs::read_to_string(managed.config().path()).unwrap();
+        let before = managed.get();
+
+        let result = managed.modify(|settings| {
+            settings.theme = "invalid".to_owned();
+            settings.launches = 1;
+        });
+
+        assert!(matches!(result, Err(ConfigError::Serialize(_))));
+        assert!(Arc::ptr_eq(&before, &managed.get()));
+        assert_eq!(fs::read_to_string(managed.config().path()).unwrap(), saved);
+        assert_eq!(managed.modify(|settings| settings.launches).unwrap(), 0);
+    }
+}
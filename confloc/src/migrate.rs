@@ -0,0 +1,237 @@
+use std::error::Error;
+use alptk_config::Format;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use thiserror::Error;
+use crate::{Config, ConfigError, FormatConfigError};
+
+/// Upgrades the [intermediate value](Value) of a config from one version of its schema to a later
+/// one. See [`Config::load_migrated`].
+pub trait Migrate {
+    #[allow(clippy::wrong_self_convention)]
+    fn from_version(&self) -> u32;
+    fn to_version(&self) -> u32;
+
+    /// Rewrites `value` from [`from_version`](Self::from_version) to
+    /// [`to_version`](Self::to_version). The `version` field is updated afterwards, so it doesn't
+    /// need to be touched.
+    fn migrate(&self, value: &mut Value) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum MigrationError {
+    #[error("the config has no version field")]
+    MissingVersion,
+
+    #[error("the config has version {found}, which is newer than the supported version {supported}")]
+    NewerVersion { found: u32, supported: u32 },
+
+    #[error("there is no migration from version {0} of the config")]
+    NoMigration(u32),
+
+    #[error("failed to migrate the config from version {from}")]
+    Migrate {
+        from: u32,
+        #[source]
+        source: Box<dyn Error + Send + Sync>,
+    },
+}
+
+/// The migrations of a config up to its current version. See [`Config::load_migrated`].
+pub struct Migrations {
+    current: u32,
+    migrations: Vec<Box<dyn Migrate + Send + Sync>>,
+}
+
+impl Migrations {
+    /// Migrations up to the `current` version of the config.
+    pub fn new(current: u32) -> Self {
+        Self { current, migrations: Vec::new() }
+    }
+
+    /// Adds a migration.
+    ///
+    /// # Panics
+    ///
+    /// If the migration doesn't go to a later version, goes past the current version, or another
+    /// migration from the same version was already added.
+    pub fn with(mut self, migration: impl Migrate + Send + Sync + 'static) -> Self {
+        let from = migration.from_version();
+
+        assert!(migration.to_version() > from, "migrations must go to a later version");
+        assert!(
+            migration.to_version() <= self.current,
+            "a migration to version {} goes past the current version {}",
+            migration.to_version(),
+            self.current,
+        );
+        assert!(
+            self.migrations.iter().all(|migration| migration.from_version() != from),
+            "a migration from version {from} was already added",
+        );
+
+        self.migrations.push(Box::new(migration));
+        self
+    }
+
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    /// Migrates `value` to the current version by applying the chain of migrations starting at the
+    /// version in its `version` field, and updates the field.
+    pub fn apply(&self, value: &mut Value) -> Result<(), MigrationError> {
+        let mut version = value
+            .get("version")
+            .and_then(Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or(MigrationError::MissingVersion)?;
+
+        if version > self.current {
+            return Err(MigrationError::NewerVersion { found: version, supported: self.current })
+        }
+
+        while version < self.current {
+            let migration = self
+                .migrations
+                .iter()
+                .find(|migration| migration.from_version() == version)
+                .ok_or(MigrationError::NoMigration(version))?;
+
+            migration.migrate(value).map_err(|source| MigrationError::Migrate { from: version, source })?;
+            version = migration.to_version();
+            value["version"] = version.into();
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned, F: Format> Config<T, F> {
+    /// Like [`load`](Self::load), but first brings the file up to the current version of the
+    /// config with `migrations`, according to its `version` field. The file itself isn't rewritten;
+    /// [save](Self::save) the config to persist the migration.
+    ///
+    /// The file is deserialized into a [`Value`] first, which the migrations operate on and the
+    /// config is then deserialized from, so the config must be representable as JSON.
+    pub fn load_migrated(&self, migrations: &Migrations) -> Result<T, FormatConfigError<F>> {
+        let mut value: Value = F::from_slice(&self.read()?).map_err(ConfigError::Deserialize)?;
+        migrations.apply(&mut value)?;
+
+        serde_json::from_value(value).map_err(ConfigError::FromValue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::fs;
+    use alptk_config::Toml;
+    use alptk_location::{DirKind, ProjectDirsOrEnv};
+    use serde::Deserialize;
+    use serde_json::Value;
+    use crate::{Config, ConfigError, Migrate, MigrationError, Migrations};
+
+    /// Renames `colour` to `theme`.
+    struct V1ToV2;
+
+    impl Migrate for V1ToV2 {
+        fn from_version(&self) -> u32 {
+            1
+        }
+
+        fn to_version(&self) -> u32 {
+            2
+        }
+
+        fn migrate(&self, value: &mut Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+            let map = value.as_object_mut().ok_or("the config isn't a map")?;
+            let colour = map.remove("colour").ok_or("the config has no colour")?;
+            map.insert("theme".to_owned(), colour);
+
+            Ok(())
+        }
+    }
+
+    /// Moves `port` into the `server` table.
+    struct V2ToV3;
+
+    impl Migrate for V2ToV3 {
+        fn from_version(&self) -> u32 {
+            2
+        }
+
+        fn to_version(&self) -> u32 {
+            3
+        }
+
+        fn migrate(&self, value: &mut Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+            let map = value.as_object_mut().ok_or("the config isn't a map")?;
+            let port = map.remove("port").unwrap_or(8080.into());
+            map.insert("server".to_owned(), serde_json::json!({ "port": port }));
+
+            Ok(())
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Settings {
+        version: u32,
+        theme: String,
+        server: Server,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Server {
+        port: u16,
+    }
+
+    fn migrations() -> Migrations {
+        Migrations::new(3).with(V2ToV3).with(V1ToV2)
+    }
+
+    #[test]
+    fn migrates_through_every_version() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap();
+        let expected = Settings { version: 3, theme: "dark".to_owned(), server: Server { port: 9000 } };
+
+        fs::write(config.path(), "version = 1\ncolour = \"dark\"\nport = 9000").unwrap();
+
+        assert_eq!(config.load_migrated(&migrations()).unwrap(), expected);
+
+        fs::write(config.path(), "version = 3\ntheme = \"dark\"\n[server]\nport = 9000").unwrap();
+
+        assert_eq!(config.load_migrated(&migrations()).unwrap(), expected);
+    }
+
+    #[test]
+    fn unsupported_versions() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap();
+        let error = |contents: &str, migrations: &Migrations| {
+            fs::write(config.path(), contents).unwrap();
+
+            match config.load_migrated(migrations) {
+                Err(ConfigError::Migration(error)) => error,
+                result => panic!("unexpected result: {result:?}"),
+            }
+        };
+
+        assert!(matches!(error("theme = \"dark\"", &migrations()), MigrationError::MissingVersion));
+        assert!(matches!(
+            error("version = 4", &migrations()),
+            MigrationError::NewerVersion { found: 4, supported: 3 }
+        ));
+        assert!(matches!(error("version = 0", &migrations()), MigrationError::NoMigration(0)));
+        assert!(matches!(error("version = 1\ncolour = \"dark\"", &Migrations::new(3).with(V1ToV2)), MigrationError::NoMigration(2)));
+        assert!(matches!(error("version = 1\ntheme = \"dark\"", &migrations()), MigrationError::Migrate { from: 1, .. }));
+    }
+
+    #[test]
+    #[should_panic(expected = "goes past the current version")]
+    fn migrations_past_the_current_version_panic() {
+        let _ = Migrations::new(2).with(V1ToV2).with(V2ToV3);
+    }
+}
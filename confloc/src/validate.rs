@@ -0,0 +1,153 @@
+use std::fmt;
+use alptk_config::Format;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use thiserror::Error;
+use crate::{Config, ConfigError, FormatConfigError};
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ValidationError {
+    #[error("the schema is invalid: {0}")]
+    InvalidSchema(String),
+
+    #[error("the config doesn't match its schema: {}", list(.0))]
+    Violations(Vec<Violation>),
+}
+
+/// A part of a config which doesn't match its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Violation {
+    /// The path of the offending value, such as `server.port` or `plugins.0`, which is empty for
+    /// the config itself.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+fn list(violations: &[Violation]) -> String {
+    violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+}
+
+/// Converts a JSON pointer such as `/server/port` to a path such as `server.port`.
+fn path(pointer: &str) -> String {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Validates `value` against the JSON Schema `schema`.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), ValidationError> {
+    let validator = jsonschema::validator_for(schema).map_err(|error| ValidationError::InvalidSchema(error.to_string()))?;
+    let violations: Vec<_> = validator
+        .iter_errors(value)
+        .map(|error| Violation { path: path(error.instance_path().as_str()), message: error.to_string() })
+        .collect();
+
+    if !violations.is_empty() {
+        return Err(ValidationError::Violations(violations))
+    }
+
+    Ok(())
+}
+
+impl<T: DeserializeOwned, F: Format> Config<T, F> {
+    /// Like [`load`](Self::load), but first [validates](validate) the file against the JSON Schema
+    /// `schema`, so that invalid configs fail with [`ValidationError::Violations`], listing every
+    /// problem along with its path, rather than with the first error of deserializing `T`.
+    ///
+    /// The file is deserialized into a [`Value`] first, which is validated and the config is then
+    /// deserialized from, so the config must be representable as JSON.
+    pub fn load_validated(&self, schema: &Value) -> Result<T, FormatConfigError<F>> {
+        let value: Value = F::from_slice(&self.read()?).map_err(ConfigError::Deserialize)?;
+        validate(schema, &value)?;
+
+        serde_json::from_value(value).map_err(ConfigError::FromValue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use alptk_config::Toml;
+    use alptk_location::{DirKind, ProjectDirsOrEnv};
+    use serde::Deserialize;
+    use serde_json::json;
+    use crate::{Config, ConfigError, ValidationError, Violation};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Settings {
+        theme: String,
+        server: Server,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Server {
+        port: u16,
+    }
+
+    fn schema() -> serde_json::Value {
+        json!({
+            "type": "object",
+            "required": ["theme", "server"],
+            "properties": {
+                "theme": { "enum": ["light", "dark"] },
+                "server": {
+                    "type": "object",
+                    "required": ["port"],
+                    "properties": { "port": { "type": "integer", "minimum": 1024 } },
+                },
+            },
+        })
+    }
+
+    #[test]
+    fn valid_configs_load() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap();
+        fs::write(config.path(), "theme = \"dark\"\n[server]\nport = 8080").unwrap();
+
+        assert_eq!(
+            config.load_validated(&schema()).unwrap(),
+            Settings { theme: "dark".to_owned(), server: Server { port: 8080 } }
+        );
+    }
+
+    #[test]
+    fn violations_are_listed() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap();
+        fs::write(config.path(), "[server]\nport = \"80\"").unwrap();
+
+        let Err(ConfigError::Validation(ValidationError::Violations(violations))) = config.load_validated(&schema()) else {
+            panic!("the config was accepted");
+        };
+        let paths: Vec<_> = violations.iter().map(|violation| violation.path.as_str()).collect();
+
+        assert_eq!(paths, ["", "server.port"]);
+        assert!(violations[0].message.contains("\"theme\" is a required property"), "{}", violations[0]);
+        assert!(violations[1].to_string().starts_with("server.port: \"80\" is not of type \"integer\""), "{}", violations[1]);
+
+        assert!(matches!(
+            config.load_validated(&json!({ "type": 1 })),
+            Err(ConfigError::Validation(ValidationError::InvalidSchema(_)))
+        ));
+        assert_eq!(
+            Violation { path: "a/b.~c".to_owned(), message: "m".to_owned() }.to_string(),
+            "a/b.~c: m",
+        );
+    }
+}
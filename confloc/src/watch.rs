@@ -0,0 +1,107 @@
+use std::fs;
+use std::marker::PhantomData;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use alptk_config::Format;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::de::DeserializeOwned;
+use crate::{Config, FormatConfigError};
+
+/// How long a config has to stay untouched after a change before it is reloaded, so that e.g. an
+/// editor writing it in several steps triggers only one reload.
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watches a config for changes until dropped. Created by [`Config::watch`].
+#[must_use = "the config is only watched until the watcher is dropped"]
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl<T: DeserializeOwned + 'static, F: Format + 'static> Config<T, F> {
+    /// Calls `callback` with the reloaded config whenever the file changes, until the returned
+    /// watcher is dropped. Changes in quick succession are [debounced](DEBOUNCE). Errors while
+    /// reloading, including the file being removed, are passed to `callback` as well, and the
+    /// config keeps being watched.
+    ///
+    /// The directory of the config is watched rather than the file itself, so that replacing the
+    /// file, as editors and [`save`](Self::save) do, is noticed. It is created if it
+    /// doesn't exist yet. `callback` runs on a separate thread.
+    pub fn watch(
+        &self,
+        mut callback: impl FnMut(Result<T, FormatConfigError<F>>) + Send + 'static,
+    ) -> notify::Result<ConfigWatcher> {
+        let dir = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(".".as_ref());
+        fs::create_dir_all(dir).map_err(notify::Error::io)?;
+
+        let config = Self { path: self.path.clone(), options: self.options, _marker: PhantomData };
+        let file_name = config.path.file_name().map(ToOwned::to_owned);
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else { return };
+
+            // reloading opens the file, which must not trigger another reload
+            if !matches!(event.kind, EventKind::Access(_))
+                && event.paths.iter().any(|path| path.file_name() == file_name.as_deref())
+            {
+                let _ = sender.send(());
+            }
+        })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        thread::spawn(move || {
+            // the sender is dropped along with the watcher
+            while receiver.recv().is_ok() {
+                loop {
+                    match receiver.recv_timeout(DEBOUNCE) {
+                        Ok(()) => continue,
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+
+                callback(config.load());
+            }
+        });
+
+        Ok(ConfigWatcher { _watcher: watcher })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use alptk_config::Toml;
+    use alptk_location::{DirKind, ProjectDirsOrEnv};
+    use serde::{Deserialize, Serialize};
+    use crate::{Config, ConfigError};
+
+    #[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+    struct Settings {
+        theme: String,
+    }
+
+    #[test]
+    fn reloads_on_change() {
+        let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let config = Config::<Settings, Toml>::in_dir(&dirs, DirKind::Config, "settings.toml").unwrap();
+        config.save(&Settings { theme: "light".to_owned() }).unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let _watcher = config.watch(move |result| { let _ = sender.send(result); }).unwrap();
+
+        config.save(&Settings { theme: "dark".to_owned() }).unwrap();
+
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap().unwrap(), Settings { theme: "dark".to_owned() });
+
+        fs::write(config.path(), "theme = ").unwrap();
+
+        assert!(matches!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), Err(ConfigError::Deserialize(_))));
+
+        config.save(&Settings { theme: "solarized".to_owned() }).unwrap();
+
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap().unwrap().theme, "solarized");
+    }
+}
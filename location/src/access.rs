@@ -0,0 +1,208 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::validate::probe_writable;
+use crate::{DirKind, ProjectDirsOrEnv};
+
+/// The access to every directory, as checked by [`ProjectDirsOrEnv::check_access`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct AccessReport {
+    /// The directories in the order of [`DirKind::ALL`], without [`DirKind::ProjectPath`].
+    pub dirs: Vec<DirAccess>,
+}
+
+/// The access to a single directory. See [`AccessReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct DirAccess {
+    pub kind: DirKind,
+
+    /// The path of the directory, or `None` if the optional directory is unset, in which case
+    /// every check is `false`.
+    pub path: Option<PathBuf>,
+
+    pub exists: bool,
+    pub is_dir: bool,
+
+    /// Whether the entries of the directory can be listed.
+    pub readable: bool,
+
+    /// Whether a file can be created in the directory.
+    pub writable: bool,
+}
+
+impl DirAccess {
+    fn check(kind: DirKind, path: Option<&Path>) -> Self {
+        let mut this = Self {
+            kind,
+            path: path.map(ToOwned::to_owned),
+            exists: false,
+            is_dir: false,
+            readable: false,
+            writable: false,
+        };
+        let Some(path) = path else { return this };
+
+        match fs::metadata(path) {
+            Ok(metadata) => {
+                this.exists = true;
+                this.is_dir = metadata.is_dir();
+            }
+            // the metadata may be inaccessible even though the path exists
+            Err(_) => this.exists = fs::symlink_metadata(path).is_ok(),
+        }
+
+        if this.is_dir {
+            this.readable = fs::read_dir(path).is_ok();
+            this.writable = probe_writable(path).is_ok();
+        }
+
+        this
+    }
+
+    /// Whether the directory exists and is readable and writable.
+    pub fn is_ok(&self) -> bool {
+        self.is_dir && self.readable && self.writable
+    }
+}
+
+impl AccessReport {
+    pub fn get(&self, kind: DirKind) -> Option<&DirAccess> {
+        self.dirs.iter().find(|dir| dir.kind == kind)
+    }
+
+    /// Iterates over the directories which are set, but aren't [ok](DirAccess::is_ok).
+    pub fn problems(&self) -> impl Iterator<Item = &DirAccess> + '_ {
+        self.dirs.iter().filter(|dir| dir.path.is_some() && !dir.is_ok())
+    }
+}
+
+impl fmt::Display for DirAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(path) = &self.path else {
+            return write!(f, "{}: (unset)", self.kind)
+        };
+
+        write!(f, "{}: {} ", self.kind, path.display())?;
+
+        match (self.exists, self.is_dir) {
+            (false, _) => f.write_str("(missing)"),
+            (true, false) => f.write_str("(not a directory)"),
+            (true, true) => write!(
+                f,
+                "({}, {})",
+                if self.readable { "readable" } else { "not readable" },
+                if self.writable { "writable" } else { "not writable" },
+            ),
+        }
+    }
+}
+
+impl fmt::Display for AccessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for dir in &self.dirs {
+            writeln!(f, "{dir}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProjectDirsOrEnv {
+    /// Checks whether every directory exists, is a directory, is readable and is writable, for
+    /// diagnosing permission problems. The project path is skipped, as it is relative.
+    ///
+    /// Writability is probed by creating and removing a temporary file in the directory rather
+    /// than by inspecting permission bits, which don't account for ACLs or read-only mounts.
+    /// Failing checks, including ones failing due to insufficient permissions, are recorded as
+    /// `false` in the report.
+    pub fn check_access(&self) -> AccessReport {
+        let dirs = DirKind::ALL
+            .into_iter()
+            .filter(|kind| *kind != DirKind::ProjectPath)
+            .map(|kind| DirAccess::check(kind, self.get(kind)))
+            .collect();
+
+        AccessReport { dirs }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use crate::{DirKind, ProjectDirsOrEnv};
+
+    fn dirs(data_dir: &Path, cache_dir: &Path) -> ProjectDirsOrEnv {
+        ProjectDirsOrEnv::builder("app")
+            .override_dir(DirKind::Data, data_dir)
+            .override_dir(DirKind::Cache, cache_dir)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn reports_every_directory() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("file");
+        fs::write(&file, "").unwrap();
+
+        let report = dirs(tmp.path(), &file).check_access();
+        let data = report.get(DirKind::Data).unwrap();
+
+        assert!(data.exists && data.is_dir && data.readable && data.writable && data.is_ok());
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 1, "the probe wasn't removed");
+
+        let cache = report.get(DirKind::Cache).unwrap();
+
+        assert!(cache.exists && !cache.is_dir && !cache.writable);
+        assert_eq!(cache.to_string(), format!("Cache: {} (not a directory)", file.display()));
+        assert!(report.problems().any(|dir| dir.kind == DirKind::Cache));
+        assert!(report.get(DirKind::ProjectPath).is_none());
+        assert_eq!(report.to_string().lines().count(), DirKind::ALL.len() - 1);
+
+        let missing = tmp.path().join("missing");
+        let report = dirs(&missing, &missing).check_access();
+
+        assert!(!report.get(DirKind::Data).unwrap().exists);
+        assert_eq!(report.get(DirKind::Data).unwrap().to_string(), format!("Data: {} (missing)", missing.display()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn read_only_dir_is_not_writable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        fs::set_permissions(tmp.path(), fs::Permissions::from_mode(0o555)).unwrap();
+
+        let data = dirs(tmp.path(), tmp.path()).check_access().get(DirKind::Data).unwrap().clone();
+        let enforced = fs::write(tmp.path().join("file"), "").is_err();
+        fs::set_permissions(tmp.path(), fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(data.exists && data.is_dir && data.readable);
+
+        // root bypasses permission bits, so only check what is actually enforced
+        if enforced {
+            assert!(!data.writable);
+            assert_eq!(data.to_string(), format!("Data: {} (readable, not writable)", tmp.path().display()));
+        }
+
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().filter(|entry| {
+            entry.as_ref().unwrap().file_name().to_string_lossy().starts_with(".alptk")
+        }).count(), 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serializes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let report = serde_json::to_value(dirs(tmp.path(), tmp.path()).check_access()).unwrap();
+
+        assert_eq!(report["dirs"][0]["kind"], "Cache");
+        assert_eq!(report["dirs"][0]["writable"], true);
+    }
+}
@@ -0,0 +1,109 @@
+use std::ops::Deref;
+use std::path::PathBuf;
+use crate::ProjectDirsOrEnv;
+
+/// A mutable view of a resolved [`ProjectDirsOrEnv`], handed to the closure of
+/// [`ProjectDirsOrEnv::adjusted`] (and `initialize_with` of the [`location!`](crate::location)
+/// macro) to adjust directories before they are frozen.
+///
+/// Every accessor of [`ProjectDirsOrEnv`] is available through [`Deref`].
+pub struct ProjectDirsOrEnvMut<'a> {
+    inner: &'a mut ProjectDirsOrEnv,
+}
+
+macro_rules! mut_fns {
+    ($($fn_name:ident => $field:ident: $ty:ty;)*) => {
+        $(
+        pub fn $fn_name(&mut self) -> &mut $ty {
+            &mut self.inner.$field
+        }
+        )*
+    };
+}
+
+impl ProjectDirsOrEnvMut<'_> {
+    mut_fns! {
+        cache_dir_mut => cache_dir: PathBuf;
+        config_dir_mut => config_dir: PathBuf;
+        config_local_dir_mut => config_local_dir: PathBuf;
+        data_dir_mut => data_dir: PathBuf;
+        data_local_dir_mut => data_local_dir: PathBuf;
+        preference_dir_mut => preference_dir: PathBuf;
+        project_path_mut => project_path: PathBuf;
+        runtime_dir_mut => runtime_dir: Option<PathBuf>;
+        state_dir_mut => state_dir: Option<PathBuf>;
+        log_dir_mut => log_dir: PathBuf;
+        executable_dir_mut => executable_dir: Option<PathBuf>;
+        site_config_dirs_mut => site_config_dirs: Vec<PathBuf>;
+    }
+
+    /// Replaces every directory at once, e.g. with an `ephemeral` layout from the `test-util`
+    /// feature, returning the previous ones.
+    pub fn replace(&mut self, with: ProjectDirsOrEnv) -> ProjectDirsOrEnv {
+        std::mem::replace(self.inner, with)
+    }
+}
+
+impl Deref for ProjectDirsOrEnvMut<'_> {
+    type Target = ProjectDirsOrEnv;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner
+    }
+}
+
+impl ProjectDirsOrEnv {
+    /// Lets `adjust` edit the resolved directories, e.g. to append a per-machine component to the
+    /// data directory when home directories are shared between machines.
+    ///
+    /// ```
+    /// # use alptk_location::ProjectDirsOrEnv;
+    /// let dirs = ProjectDirsOrEnv::builder("app")
+    ///     .override_data_dir("/srv/app")
+    ///     .build()?
+    ///     .adjusted(|dirs| {
+    ///         let data_dir = dirs.data_dir_mut();
+    ///         *data_dir = data_dir.join("host-a");
+    ///     });
+    ///
+    /// assert_eq!(dirs.data_dir(), std::path::Path::new("/srv/app/host-a"));
+    /// # Ok::<_, alptk_location::InitializeError>(())
+    /// ```
+    pub fn adjusted(mut self, adjust: impl FnOnce(&mut ProjectDirsOrEnvMut<'_>)) -> Self {
+        adjust(&mut ProjectDirsOrEnvMut { inner: &mut self });
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use crate::tests::layout;
+
+    #[test]
+    fn adjusted_edits_fields_in_place() {
+        let dirs = layout(false).adjusted(|dirs| {
+            let data_dir = dirs.data_dir().join("host-a");
+            *dirs.data_dir_mut() = data_dir;
+            *dirs.state_dir_mut() = Some(PathBuf::from("/var/lib/app"));
+            dirs.site_config_dirs_mut().clear();
+        });
+
+        assert_eq!(dirs.data_dir(), Path::new("/home/x/.local/share/app/host-a"));
+        assert_eq!(dirs.state_dir(), Some(Path::new("/var/lib/app")));
+        assert!(dirs.site_config_dirs().is_empty());
+        assert_eq!(dirs.cache_dir(), layout(false).cache_dir());
+    }
+
+    #[test]
+    fn replace_swaps_everything() {
+        let dirs = layout(true).adjusted(|dirs| {
+            let previous = dirs.replace(layout(false));
+
+            assert!(previous.state_dir().is_some());
+        });
+
+        assert_eq!(dirs.state_dir(), None);
+        assert_eq!(dirs.data_dir(), layout(false).data_dir());
+    }
+}
@@ -0,0 +1,134 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use crate::Env;
+
+/// Where the directories which weren't set or overridden are rooted. See
+/// [`ProjectDirsOrEnvBuilder::relocation`](crate::ProjectDirsOrEnvBuilder::relocation).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Relocation {
+    /// The platform defaults are used.
+    #[default]
+    None,
+
+    /// When running as an [AppImage](AppImage), the directories are rooted in the portable home
+    /// directory `<appimage>.home` next to the AppImage file, like many AppImage tools do, so that
+    /// the app is fully self-contained. Below it, they are laid out like the XDG defaults, e.g.
+    /// `.config/<project>` and `.local/share/<project>`. The runtime and executable directories are
+    /// only set if their environment variables are. Otherwise, the platform defaults are used.
+    BesideAppImage,
+}
+
+/// The AppImage the app runs as, detected from the `APPIMAGE` and `APPDIR` environment variables
+/// set by the AppImage runtime. See [`ProjectDirsOrEnv::app_image`](crate::ProjectDirsOrEnv::app_image).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AppImage {
+    /// The path of the AppImage file, from `APPIMAGE`.
+    pub path: Option<PathBuf>,
+
+    /// The directory the image is mounted at, from `APPDIR`.
+    pub app_dir: Option<PathBuf>,
+}
+
+impl AppImage {
+    /// Returns the AppImage if either of its variables is set.
+    pub(crate) fn detect(var: impl Fn(&str) -> Option<OsString>) -> Option<Self> {
+        let path = var("APPIMAGE").filter(|path| !path.is_empty()).map(PathBuf::from);
+        let app_dir = var("APPDIR").filter(|path| !path.is_empty()).map(PathBuf::from);
+
+        (path.is_some() || app_dir.is_some()).then_some(Self { path, app_dir })
+    }
+
+    /// The portable home directory `<appimage>.home` next to the AppImage file, or `None` if its
+    /// path is unknown.
+    pub fn home_dir(&self) -> Option<PathBuf> {
+        let mut home = self.path.clone()?.into_os_string();
+        home.push(".home");
+
+        Some(PathBuf::from(home))
+    }
+}
+
+/// Roots the required directories and the state directory of `env` which aren't set in `home`.
+pub(crate) fn relocate(env: &mut Env, app_name: &str, home: PathBuf) {
+    // the project path of `directories` on linux
+    let project: String = app_name.trim().to_lowercase().split_whitespace().collect();
+    let dir = |base: &str| Some(home.join(base).join(&project));
+
+    env.cache_dir = env.cache_dir.take().or_else(|| dir(".cache"));
+    env.config_dir = env.config_dir.take().or_else(|| dir(".config"));
+    env.config_local_dir = env.config_local_dir.take().or_else(|| dir(".config"));
+    env.data_dir = env.data_dir.take().or_else(|| dir(".local/share"));
+    env.data_local_dir = env.data_local_dir.take().or_else(|| dir(".local/share"));
+    env.preference_dir = env.preference_dir.take().or_else(|| dir(".config"));
+    env.state_dir = env.state_dir.take().or_else(|| dir(".local/state"));
+    env.project_path = env.project_path.take().or_else(|| Some(PathBuf::from(&project)));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::path::{Path, PathBuf};
+    use crate::{AppImage, ProjectDirsOrEnv, Relocation};
+
+    const PREFIX: &str = "ALPTK_LOCATION_APPIMAGE_TEST";
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+        pairs.iter().map(|(name, value)| (OsString::from(name), OsString::from(value))).collect()
+    }
+
+    #[test]
+    fn detects_app_image() {
+        let this = ProjectDirsOrEnv::from_vars("app", PREFIX, vars(&[])).unwrap();
+
+        assert_eq!(this.app_image(), None);
+
+        let this = ProjectDirsOrEnv::from_vars("app", PREFIX, vars(&[
+            ("APPIMAGE", "/opt/My App.AppImage"),
+            ("APPDIR", "/tmp/.mount_MyApp"),
+        ])).unwrap();
+        let app_image = this.app_image().unwrap();
+
+        assert_eq!(app_image.path.as_deref(), Some(Path::new("/opt/My App.AppImage")));
+        assert_eq!(app_image.app_dir.as_deref(), Some(Path::new("/tmp/.mount_MyApp")));
+        assert_eq!(app_image.home_dir(), Some(PathBuf::from("/opt/My App.AppImage.home")));
+
+        // detection alone doesn't relocate anything
+        assert!(!this.data_dir().starts_with("/opt"));
+        assert_eq!(AppImage { path: None, app_dir: Some("/tmp".into()) }.home_dir(), None);
+    }
+
+    #[test]
+    fn relocates_beside_app_image() {
+        let builder = |pairs: &[(&str, &str)]| {
+            ProjectDirsOrEnv::builder("My App")
+                .env_prefix(PREFIX)
+                .relocation(Relocation::BesideAppImage)
+                .vars(vars(pairs))
+        };
+        let this = builder(&[
+            ("APPIMAGE", "/opt/MyApp.AppImage"),
+            (&format!("{PREFIX}_CACHE_DIR"), "/env/cache"),
+        ]).build().unwrap();
+        let home = Path::new("/opt/MyApp.AppImage.home");
+
+        assert_eq!(this.cache_dir(), Path::new("/env/cache"));
+        assert_eq!(this.config_dir(), home.join(".config/myapp"));
+        assert_eq!(this.data_dir(), home.join(".local/share/myapp"));
+        assert_eq!(this.state_dir(), Some(home.join(".local/state/myapp").as_path()));
+        assert_eq!(this.log_dir(), home.join(".local/state/myapp/logs"));
+        assert_eq!(this.project_path(), Path::new("myapp"));
+        assert!(this.project_dirs().is_none());
+        assert!(!this.parity_report().used_fallback);
+        assert!(this.parity_report().missing.is_empty(), "{:?}", this.parity_report().missing);
+
+        // not an AppImage
+        let this = builder(&[]).build().unwrap();
+
+        assert!(this.project_dirs().is_some());
+        assert!(this.parity_report().used_fallback);
+        assert!(!this.parity_report().missing.is_empty());
+        assert!(!this.config_dir().starts_with(home));
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+use crate::appimage::{self, AppImage, Relocation};
+use crate::paths_file::{self, PathsFileError};
+use crate::snapshot::Recorder;
+use crate::{DirKind, Env, HomeDirNotFoundError, InitializeError, ProjectDirsOrEnv, Validation, PATHS_FILE_SUFFIX};
+
+/// Builds a [`ProjectDirsOrEnv`] with programmatic overrides for individual directories.
+///
+/// Each directory is resolved from, in order of precedence:
+///
+/// 1. the override set on this builder, e.g. from a command line flag;
+/// 2. the environment variable `{PREFIX}{SUFFIX}` (see [`DirKind::env_suffix`]) or the one it was
+///    renamed to with [`env_var`](Self::env_var), unless no env prefix is set or
+///    [`skip_env`](Self::skip_env) was called;
+/// 3. the same variable in the paths file, see below;
+/// 4. the [relocated](Self::relocation) directory, if a relocation applies;
+/// 5. the platform defaults of [`directories::ProjectDirs`].
+///
+/// The paths file is read from the path in `{PREFIX}_PATHS_FILE` if it is set, or otherwise from
+/// `{APP_NAME}.paths` in the current directory if it exists, which is handy for services and
+/// containers where setting every variable is awkward. It consists of `KEY=VALUE` lines, or
+/// equivalently of a flat TOML table, with values optionally quoted like TOML strings. Blank lines
+/// and lines starting with `#` are skipped:
+///
+/// ```text
+/// # /etc/myapp/myapp.paths
+/// MYAPP_DATA_DIR=/srv/myapp
+/// MYAPP_CACHE_DIR = "/var/cache/myapp"
+/// ```
+///
+/// Like the environment, it is only consulted if an env prefix is set. With
+/// [`vars`](Self::vars), `{PREFIX}_PATHS_FILE` is looked up in the given variables and the current
+/// directory is never searched.
+#[derive(Clone)]
+pub struct ProjectDirsOrEnvBuilder {
+    app_name: String,
+    env_prefix: Option<String>,
+    env_names: HashMap<DirKind, String>,
+    overrides: Env,
+    validation: Validation,
+    normalize: bool,
+    vars: Option<HashMap<OsString, OsString>>,
+    relocation: Relocation,
+}
+
+macro_rules! override_fns {
+    ($($fn_name:ident => $field:ident;)*) => {
+        $(
+        pub fn $fn_name(mut self, path: impl Into<PathBuf>) -> Self {
+            self.overrides.$field = Some(path.into());
+            self
+        }
+        )*
+    };
+}
+
+impl ProjectDirsOrEnvBuilder {
+    pub fn new(app_name: impl Into<String>) -> Self {
+        Self {
+            app_name: app_name.into(),
+            env_prefix: None,
+            env_names: HashMap::new(),
+            overrides: Env::default(),
+            validation: Validation::None,
+            normalize: true,
+            vars: None,
+            relocation: Relocation::None,
+        }
+    }
+
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = app_name.into();
+        self
+    }
+
+    /// Consults environment variables starting with `env_prefix`. Without an env prefix, the
+    /// environment isn't consulted at all.
+    pub fn env_prefix(mut self, env_prefix: impl Into<String>) -> Self {
+        self.env_prefix = Some(env_prefix.into());
+        self
+    }
+
+    /// Doesn't consult the environment at all, even if an env prefix was set.
+    pub fn skip_env(mut self) -> Self {
+        self.env_prefix = None;
+        self
+    }
+
+    /// Consults the environment variable `name` for the directory of the given kind, instead of
+    /// `{PREFIX}{SUFFIX}`, e.g. to keep honoring a variable from before the env prefix was adopted.
+    /// Like every other variable, it is only consulted if an env prefix is set.
+    pub fn env_var(mut self, kind: DirKind, name: impl Into<String>) -> Self {
+        self.env_names.insert(kind, name.into());
+        self
+    }
+
+    /// Checks the directories taken from environment variables, so that e.g. a typo in one is
+    /// reported by [`build`](Self::build) rather than as a confusing IO error later on. Overridden
+    /// directories, the project path (which is relative) and the site config directories aren't
+    /// checked. Defaults to [`Validation::None`].
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Whether paths taken from environment variables are [normalized](crate::normalize_path),
+    /// e.g. by stripping trailing separators and `.` components. Enabled by default; disable it to
+    /// get the raw values.
+    pub fn normalize_paths(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Looks the variables up in `vars` instead of the environment, which is then never touched.
+    /// Everything else, e.g. renames and the fallback to the platform defaults, works the same.
+    pub fn vars(mut self, vars: impl IntoIterator<Item = (OsString, OsString)>) -> Self {
+        self.vars = Some(vars.into_iter().collect());
+        self
+    }
+
+    /// Where the directories which are neither overridden nor set by environment variables are
+    /// rooted. Defaults to [`Relocation::None`], i.e. the platform defaults. Relocated directories
+    /// count as provided, so the platform defaults are only consulted for the ones left unset.
+    pub fn relocation(mut self, relocation: Relocation) -> Self {
+        self.relocation = relocation;
+        self
+    }
+
+    /// Returns the names of every environment variable consulted, taking renames into account.
+    /// Empty if the environment isn't consulted.
+    pub fn env_vars(&self) -> Vec<String> {
+        match &self.env_prefix {
+            Some(env_prefix) => crate::env_vars(env_prefix, &self.env_names),
+            None => Vec::new(),
+        }
+    }
+
+    override_fns! {
+        override_cache_dir => cache_dir;
+        override_config_dir => config_dir;
+        override_config_local_dir => config_local_dir;
+        override_data_dir => data_dir;
+        override_data_local_dir => data_local_dir;
+        override_preference_dir => preference_dir;
+        override_project_path => project_path;
+        override_runtime_dir => runtime_dir;
+        override_state_dir => state_dir;
+        override_log_dir => log_dir;
+        override_executable_dir => executable_dir;
+    }
+
+    /// Overrides the directory of the given kind, like the `override_*` method for it.
+    pub fn override_dir(mut self, kind: DirKind, path: impl Into<PathBuf>) -> Self {
+        *self.overrides.get_mut(kind) = Some(path.into());
+        self
+    }
+
+    /// Overrides every directory in `overrides`, which typically come from command line flags such
+    /// as `--config-dir`. Later overrides for the same kind win.
+    pub fn overrides(self, overrides: impl IntoIterator<Item = (DirKind, PathBuf)>) -> Self {
+        overrides.into_iter().fold(self, |this, (kind, path)| this.override_dir(kind, path))
+    }
+
+    pub fn override_site_config_dirs(mut self, dirs: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        self.overrides.site_config_dirs = Some(dirs.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn build(self) -> Result<ProjectDirsOrEnv, InitializeError> {
+        if let Some(env_prefix) = &self.env_prefix {
+            if !is_valid_env_prefix(env_prefix) {
+                return Err(InitializeError::InvalidPrefix(env_prefix.clone()))
+            }
+        }
+
+        let recorder = Recorder::default();
+        let (env, app_image) = match &self.env_prefix {
+            Some(env_prefix) => {
+                let var = |name: &str| recorder.lookup(name, |name| match &self.vars {
+                    Some(vars) => vars.get(OsStr::new(name)).cloned(),
+                    None => env::var_os(name),
+                });
+                let file_vars = self.paths_file(env_prefix, var)?;
+
+                let env = Env::named(env_prefix, &self.env_names, self.normalize, |name| {
+                    var(name).or_else(|| file_vars.get(OsStr::new(name)).cloned())
+                })?;
+
+                (env, AppImage::detect(var))
+            }
+            None => (Env::default(), None),
+        };
+
+        if let Some(env_prefix) = &self.env_prefix {
+            for kind in DirKind::ALL {
+                if kind == DirKind::ProjectPath || self.overrides.get(kind).is_some() {
+                    continue
+                }
+
+                if let Some(path) = env.get(kind) {
+                    self.validation.check(&Env::var_name(env_prefix, &self.env_names, kind), path)?;
+                }
+            }
+        }
+
+        let mut env = self.overrides.or(env);
+
+        if self.relocation == Relocation::BesideAppImage {
+            if let Some(home) = app_image.as_ref().and_then(AppImage::home_dir) {
+                appimage::relocate(&mut env, &self.app_name, home);
+            }
+        }
+
+        // after relocating, which may fill in every missing directory
+        let missing = match &self.env_prefix {
+            Some(env_prefix) => env
+                .missing()
+                .into_iter()
+                .map(|kind| Env::var_name(env_prefix, &self.env_names, kind))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let mut this = match ProjectDirsOrEnv::resolve(&self.app_name, env) {
+            Err(InitializeError::ProjectDirs(error)) => {
+                return Err(InitializeError::ProjectDirs(HomeDirNotFoundError {
+                    env_prefix: self.env_prefix,
+                    missing_vars: missing,
+                    ..error
+                }))
+            }
+            result => result?,
+        };
+        this.parity.missing = missing;
+        this.app_image = app_image;
+        this.init_snapshot = recorder.finish(this.parity.used_fallback);
+
+        Ok(this)
+    }
+
+    /// Reads the variables in the paths file, if there is one.
+    fn paths_file(
+        &self,
+        env_prefix: &str,
+        var: impl Fn(&str) -> Option<OsString>,
+    ) -> Result<HashMap<OsString, OsString>, PathsFileError> {
+        let path = match var(&format!("{env_prefix}{PATHS_FILE_SUFFIX}")) {
+            Some(path) => PathBuf::from(path),
+            None if self.vars.is_none() => {
+                let path = PathBuf::from(format!("{}.paths", self.app_name));
+
+                if !path.is_file() {
+                    return Ok(HashMap::new())
+                }
+
+                path
+            }
+            None => return Ok(HashMap::new()),
+        };
+
+        paths_file::load(&path)
+    }
+}
+
+/// Whether variables named `{env_prefix}_{SUFFIX}` can be set from a shell, with a prefix that
+/// doesn't end with an underscore as every suffix starts with one.
+fn is_valid_env_prefix(env_prefix: &str) -> bool {
+    !env_prefix.is_empty()
+        && !env_prefix.starts_with(|c: char| c.is_ascii_digit())
+        && !env_prefix.ends_with('_')
+        && env_prefix.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::ffi::OsString;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use directories::ProjectDirs;
+    use crate::builder::ProjectDirsOrEnvBuilder;
+    use crate::{DirKind, InitializeError, PathsFileError, ProjectDirsOrEnv};
+
+    const PREFIX: &str = "ALPTK_LOCATION_BUILDER_TEST";
+
+    fn defaults() -> ProjectDirs {
+        ProjectDirs::from("", "ALinuxPerson", "app").unwrap()
+    }
+
+    #[test]
+    fn precedence() {
+        env::set_var(format!("{PREFIX}_CONFIG_DIR"), "/env/config");
+        env::set_var(format!("{PREFIX}_DATA_DIR"), "/env/data");
+        let builder = || ProjectDirsOrEnvBuilder::new("app").env_prefix(PREFIX);
+
+        // override > env
+        let this = builder().override_config_dir("/override/config").build().unwrap();
+
+        assert_eq!(this.config_dir(), Path::new("/override/config"));
+        assert_eq!(this.data_dir(), Path::new("/env/data"));
+
+        let this = builder().override_data_dir("/override/data").build().unwrap();
+
+        assert_eq!(this.config_dir(), Path::new("/env/config"));
+        assert_eq!(this.data_dir(), Path::new("/override/data"));
+
+        // override > default
+        let this = builder()
+            .skip_env()
+            .override_config_dir("/override/config")
+            .build()
+            .unwrap();
+
+        assert_eq!(this.config_dir(), Path::new("/override/config"));
+        assert_eq!(this.data_dir(), defaults().data_dir());
+
+        // env > default
+        let this = builder().build().unwrap();
+
+        assert_eq!(this.config_dir(), Path::new("/env/config"));
+        assert_eq!(this.cache_dir(), defaults().cache_dir());
+
+        // default
+        let this = ProjectDirsOrEnvBuilder::new("app").build().unwrap();
+
+        assert_eq!(this.config_dir(), defaults().config_dir());
+        assert_eq!(this.data_dir(), defaults().data_dir());
+    }
+
+    #[test]
+    fn overrides_beat_env_for_the_same_dir() {
+        env::set_var("ALPTK_LOCATION_BUILDER_FLAG_TEST_CONFIG_DIR", "/env/config");
+        env::set_var("ALPTK_LOCATION_BUILDER_FLAG_TEST_DATA_DIR", "/env/data");
+        let this = ProjectDirsOrEnv::with_overrides(
+            "app",
+            "ALPTK_LOCATION_BUILDER_FLAG_TEST",
+            [(DirKind::Config, PathBuf::from("/flag/config")), (DirKind::Cache, PathBuf::from("/flag/cache"))],
+        ).unwrap();
+
+        // flag > env
+        assert_eq!(this.config_dir(), Path::new("/flag/config"));
+        // env > default
+        assert_eq!(this.data_dir(), Path::new("/env/data"));
+        // flag > default
+        assert_eq!(this.cache_dir(), Path::new("/flag/cache"));
+        // default
+        assert_eq!(this.preference_dir(), defaults().preference_dir());
+    }
+
+    #[test]
+    fn optional_dirs_can_be_overridden() {
+        let this = ProjectDirsOrEnvBuilder::new("app")
+            .override_runtime_dir("/run/app")
+            .override_site_config_dirs(["/etc/app"])
+            .build()
+            .unwrap();
+
+        assert_eq!(this.runtime_dir(), Some(Path::new("/run/app")));
+        assert_eq!(this.site_config_dirs(), [PathBuf::from("/etc/app")]);
+    }
+
+    #[test]
+    fn renamed_env_var_is_consulted() {
+        env::set_var("ALPTK_LOCATION_BUILDER_RENAME_TEST_CONF", "/legacy/config");
+        let builder = ProjectDirsOrEnvBuilder::new("app")
+            .env_prefix("ALPTK_LOCATION_BUILDER_RENAME_TEST")
+            .env_var(DirKind::Config, "ALPTK_LOCATION_BUILDER_RENAME_TEST_CONF");
+
+        let vars = builder.env_vars();
+        assert!(vars.contains(&"ALPTK_LOCATION_BUILDER_RENAME_TEST_CONF".to_owned()));
+        assert!(!vars.contains(&"ALPTK_LOCATION_BUILDER_RENAME_TEST_CONFIG_DIR".to_owned()));
+        assert!(vars.contains(&"ALPTK_LOCATION_BUILDER_RENAME_TEST_DATA_DIR".to_owned()));
+
+        let this = builder.build().unwrap();
+
+        assert_eq!(this.config_dir(), Path::new("/legacy/config"));
+        assert_eq!(this.data_dir(), defaults().data_dir());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn renamed_env_var_appears_in_errors() {
+        use std::ffi::OsString;
+        use std::os::windows::ffi::OsStringExt;
+        use crate::InitializeError;
+
+        // an unpaired surrogate
+        env::set_var("ALPTK_LOCATION_BUILDER_RENAME_TEST_BAD", OsString::from_wide(&[0xD800]));
+        let error = ProjectDirsOrEnvBuilder::new("app")
+            .env_prefix("ALPTK_LOCATION_BUILDER_RENAME_ERROR_TEST")
+            .env_var(DirKind::Data, "ALPTK_LOCATION_BUILDER_RENAME_TEST_BAD")
+            .build()
+            .err()
+            .unwrap();
+
+        let InitializeError::Env(error) = error else { panic!("{error}") };
+        assert!(error.to_string().contains("'ALPTK_LOCATION_BUILDER_RENAME_TEST_BAD'"), "{error}");
+    }
+
+    #[test]
+    fn invalid_env_prefixes_are_rejected() {
+        use crate::InitializeError;
+
+        for env_prefix in ["", "myapp", "MY APP", "MYAPP_", "MY-APP", "1APP", "ÄPP"] {
+            let result = ProjectDirsOrEnvBuilder::new("app").env_prefix(env_prefix).build();
+
+            assert!(matches!(&result, Err(InitializeError::InvalidPrefix(p)) if p == env_prefix), "{env_prefix:?}");
+        }
+
+        for env_prefix in ["A", "MY_APP2", "_APP"] {
+            assert!(ProjectDirsOrEnvBuilder::new("app").env_prefix(env_prefix).build().is_ok(), "{env_prefix:?}");
+        }
+
+        // not validated when the environment isn't consulted
+        assert!(ProjectDirsOrEnvBuilder::new("app").env_prefix("my app").skip_env().build().is_ok());
+    }
+
+    #[test]
+    fn paths_file_is_overridden_by_env() {
+        let root = crate::scratch::create_unique(&env::temp_dir(), "alptk-location-paths-file-").unwrap();
+        let file = root.path().join("app.paths");
+        fs::write(&file, "APP_DATA_DIR=/file/data\nAPP_CONFIG_DIR = \"/file/config\"\n").unwrap();
+        let vars = |extra: &[(&str, &str)]| {
+            [("APP_PATHS_FILE", file.to_str().unwrap())]
+                .iter()
+                .chain(extra)
+                .map(|(name, value)| (OsString::from(name), OsString::from(value)))
+                .collect::<Vec<_>>()
+        };
+
+        // file only
+        let this = ProjectDirsOrEnv::builder("app").env_prefix("APP").vars(vars(&[])).build().unwrap();
+
+        assert_eq!(this.data_dir(), Path::new("/file/data"));
+        assert_eq!(this.config_dir(), Path::new("/file/config"));
+        assert_eq!(this.cache_dir(), defaults().cache_dir());
+
+        // env overrides file
+        let this = ProjectDirsOrEnv::builder("app")
+            .env_prefix("APP")
+            .vars(vars(&[("APP_DATA_DIR", "/env/data")]))
+            .build()
+            .unwrap();
+
+        assert_eq!(this.data_dir(), Path::new("/env/data"));
+        assert_eq!(this.config_dir(), Path::new("/file/config"));
+    }
+
+    #[test]
+    fn malformed_paths_file_is_an_error() {
+        let root = crate::scratch::create_unique(&env::temp_dir(), "alptk-location-paths-file-").unwrap();
+        let file = root.path().join("app.paths");
+        fs::write(&file, "APP_DATA_DIR=/file/data\n\nAPP_CONFIG_DIR\n").unwrap();
+        let build = |path: &Path| {
+            ProjectDirsOrEnv::builder("app")
+                .env_prefix("APP")
+                .vars([(OsString::from("APP_PATHS_FILE"), path.as_os_str().to_owned())])
+                .build()
+        };
+
+        match build(&file) {
+            Err(InitializeError::PathsFile(PathsFileError::Parse { line, .. })) => assert_eq!(line, 3),
+            _ => panic!("expected a parse error"),
+        }
+
+        assert!(matches!(
+            build(&root.path().join("missing.paths")),
+            Err(InitializeError::PathsFile(PathsFileError::Read { .. }))
+        ));
+    }
+}
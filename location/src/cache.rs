@@ -0,0 +1,404 @@
+use std::fs::{self, FileType};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use crate::ProjectDirsOrEnv;
+
+/// The grace period used by [`ProjectDirsOrEnv::prune_cache`].
+pub const DEFAULT_PRUNE_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+/// What [`ProjectDirsOrEnv::clear_cache`] removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClearReport {
+    /// The number of files (including symlinks) removed. Directories aren't counted.
+    pub files: u64,
+
+    /// The total size of the regular files removed, in bytes.
+    pub bytes: u64,
+}
+
+/// What [`ProjectDirsOrEnv::prune_cache`] removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PruneReport {
+    /// The files removed, oldest first.
+    pub removed: Vec<PathBuf>,
+
+    /// The total size of the removed files, in bytes.
+    pub bytes_freed: u64,
+
+    /// The total size of the files left in the cache, in bytes. May still exceed the budget if
+    /// the remaining files are all within the grace period.
+    pub bytes_remaining: u64,
+}
+
+impl ProjectDirsOrEnv {
+    /// Recursively removes everything inside [`cache_dir`](Self::cache_dir), but not the
+    /// directory itself.
+    ///
+    /// Symlinks are removed as links and never followed. Entries which disappear mid-walk (e.g.
+    /// because another process is clearing the cache too) are skipped, as is a nonexistent cache
+    /// directory.
+    ///
+    /// Refuses to run, with [`ErrorKind::InvalidInput`], if the cache directory is relative, or
+    /// resolves to a filesystem root, the home directory or one of its ancestors, symlinks
+    /// included; this usually means a misconfigured env override.
+    pub fn clear_cache(&self) -> io::Result<ClearReport> {
+        let cache_dir = self.cache_dir();
+        check_not_suspicious(cache_dir)?;
+
+        let mut report = ClearReport::default();
+        remove_contents(cache_dir, &mut report)?;
+
+        Ok(report)
+    }
+
+    /// Removes the least recently modified files in [`cache_dir`](Self::cache_dir) until the
+    /// total size of the cache is at most `max_bytes`. Files modified within the last
+    /// [`DEFAULT_PRUNE_GRACE_PERIOD`] are never removed.
+    ///
+    /// See [`prune_cache_with_grace_period`](Self::prune_cache_with_grace_period) for details.
+    pub fn prune_cache(&self, max_bytes: u64) -> io::Result<PruneReport> {
+        self.prune_cache_with_grace_period(max_bytes, DEFAULT_PRUNE_GRACE_PERIOD)
+    }
+
+    /// Removes the least recently modified files in [`cache_dir`](Self::cache_dir) until the
+    /// total size of the cache is at most `max_bytes`.
+    ///
+    /// Files modified within the last `grace_period` still count towards the total but are never
+    /// removed, so that artifacts which are still being written aren't deleted from under their
+    /// writer. Directories left empty by the removal are removed as well, except for the cache
+    /// directory itself. Symlinks are neither followed nor counted.
+    ///
+    /// Refuses to run under the same conditions as [`clear_cache`](Self::clear_cache).
+    pub fn prune_cache_with_grace_period(&self, max_bytes: u64, grace_period: Duration) -> io::Result<PruneReport> {
+        let cache_dir = self.cache_dir();
+        check_not_suspicious(cache_dir)?;
+
+        let mut files = Vec::new();
+        collect_files(cache_dir, &mut files)?;
+
+        // oldest first; ties broken by path so that the order is deterministic
+        files.sort_by(|a, b| a.modified.cmp(&b.modified).then_with(|| a.path.cmp(&b.path)));
+
+        let cutoff = SystemTime::now().checked_sub(grace_period).unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut report = PruneReport {
+            bytes_remaining: files.iter().map(|file| file.len).sum(),
+            ..PruneReport::default()
+        };
+
+        for file in files {
+            if report.bytes_remaining <= max_bytes {
+                break
+            }
+
+            if file.modified > cutoff {
+                continue
+            }
+
+            ignore_not_found(fs::remove_file(&file.path))?;
+            report.bytes_remaining -= file.len;
+            report.bytes_freed += file.len;
+            remove_empty_parents(cache_dir, &file.path)?;
+            report.removed.push(file.path);
+        }
+
+        Ok(report)
+    }
+}
+
+struct CachedFile {
+    path: PathBuf,
+    len: u64,
+    modified: SystemTime,
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<CachedFile>) -> io::Result<()> {
+    let Some(entries) = ignore_not_found(fs::read_dir(dir))? else {
+        return Ok(())
+    };
+
+    for entry in entries {
+        let Some(entry) = ignore_not_found(entry)? else {
+            continue
+        };
+        let path = entry.path();
+        let Some(metadata) = ignore_not_found(fs::symlink_metadata(&path))? else {
+            continue
+        };
+
+        if metadata.is_dir() {
+            collect_files(&path, files)?;
+        } else if metadata.is_file() {
+            files.push(CachedFile { path, len: metadata.len(), modified: metadata.modified()? });
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes the ancestors of `path` below `root` for as long as they're empty.
+fn remove_empty_parents(root: &Path, path: &Path) -> io::Result<()> {
+    for dir in path.ancestors().skip(1).take_while(|dir| *dir != root && dir.starts_with(root)) {
+        match fs::remove_dir(dir) {
+            Ok(()) => {}
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            // most likely not empty; there's no portable error kind for that
+            Err(_) => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails if `dir` is relative, such as `.`, or resolves to a filesystem root, the home directory
+/// or one of its ancestors, e.g. through a symlink. A nonexistent `dir` has nothing to remove, and
+/// passes.
+fn check_not_suspicious(dir: &Path) -> io::Result<()> {
+    let suspicious = || io::Error::new(
+        ErrorKind::InvalidInput,
+        format!("refusing to clear suspicious cache directory '{}'", dir.display()),
+    );
+
+    if !dir.is_absolute() {
+        return Err(suspicious())
+    }
+
+    let Some(resolved) = ignore_not_found(dir.canonicalize())? else {
+        return Ok(())
+    };
+    let is_root = resolved.parent().is_none();
+    let is_home_or_ancestor = directories::BaseDirs::new().is_some_and(|base_dirs| {
+        let home = base_dirs.home_dir();
+
+        home.canonicalize().as_deref().unwrap_or(home).starts_with(&resolved)
+    });
+
+    if is_root || is_home_or_ancestor {
+        return Err(suspicious())
+    }
+
+    Ok(())
+}
+
+/// Maps [`ErrorKind::NotFound`] to `Ok(None)`.
+fn ignore_not_found<T>(result: io::Result<T>) -> io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+fn remove_non_dir(path: &Path, file_type: FileType) -> io::Result<()> {
+    // directory symlinks need to be removed as directories on windows
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileTypeExt;
+
+        if file_type.is_symlink_dir() {
+            return fs::remove_dir(path)
+        }
+    }
+    #[cfg(not(windows))]
+    let _ = file_type;
+
+    fs::remove_file(path)
+}
+
+fn remove_contents(dir: &Path, report: &mut ClearReport) -> io::Result<()> {
+    let Some(entries) = ignore_not_found(fs::read_dir(dir))? else {
+        return Ok(())
+    };
+
+    for entry in entries {
+        let Some(entry) = ignore_not_found(entry)? else {
+            continue
+        };
+        let path = entry.path();
+
+        // doesn't follow symlinks, so `is_dir` is only true for actual directories
+        let Some(metadata) = ignore_not_found(fs::symlink_metadata(&path))? else {
+            continue
+        };
+
+        if metadata.is_dir() {
+            remove_contents(&path, report)?;
+            ignore_not_found(fs::remove_dir(&path))?;
+        } else if ignore_not_found(remove_non_dir(&path, metadata.file_type()))?.is_some() {
+            report.files += 1;
+
+            if metadata.is_file() {
+                report.bytes += metadata.len();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::ErrorKind;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+    use crate::cache::ClearReport;
+    use crate::tests::layout;
+
+    fn write_aged(path: &Path, len: usize, age_secs: u64) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0; len]).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn prune_cache_evicts_oldest_first() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut this = layout(false);
+        this.cache_dir = tmp.path().join("cache");
+
+        write_aged(&this.cache_dir.join("a"), 10, 2000);
+        write_aged(&this.cache_dir.join("nested/b"), 20, 3000);
+        write_aged(&this.cache_dir.join("nested/deeper/c"), 5, 1000);
+        write_aged(&this.cache_dir.join("d"), 30, 1000);
+
+        let report = this.prune_cache(35).unwrap();
+
+        assert_eq!(report.removed, [this.cache_dir.join("nested/b"), this.cache_dir.join("a")]);
+        assert_eq!(report.bytes_freed, 30);
+        assert_eq!(report.bytes_remaining, 35);
+        assert!(this.cache_dir.join("nested/deeper/c").exists());
+        assert!(this.cache_dir.join("d").exists());
+
+        let report = this.prune_cache(30).unwrap();
+
+        assert_eq!(report.removed, [this.cache_dir.join("nested/deeper/c")]);
+        assert!(!this.cache_dir.join("nested").exists());
+        assert!(this.cache_dir.is_dir());
+    }
+
+    #[test]
+    fn prune_cache_skips_files_within_grace_period() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut this = layout(false);
+        this.cache_dir = tmp.path().join("cache");
+
+        write_aged(&this.cache_dir.join("old"), 10, 3600);
+        write_aged(&this.cache_dir.join("downloading"), 10, 0);
+
+        let report = this.prune_cache_with_grace_period(0, Duration::from_secs(60)).unwrap();
+
+        assert_eq!(report.removed, [this.cache_dir.join("old")]);
+        assert_eq!(report.bytes_remaining, 10);
+        assert!(this.cache_dir.join("downloading").exists());
+    }
+
+    #[test]
+    fn clear_cache_removes_contents_but_not_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut this = layout(false);
+        this.cache_dir = tmp.path().join("cache");
+
+        fs::create_dir_all(this.cache_dir.join("nested/deeper")).unwrap();
+        fs::write(this.cache_dir.join("a"), [0; 10]).unwrap();
+        fs::write(this.cache_dir.join("nested/b"), [0; 20]).unwrap();
+        fs::write(this.cache_dir.join("nested/deeper/c"), [0; 30]).unwrap();
+
+        let report = this.clear_cache().unwrap();
+
+        assert_eq!(report, ClearReport { files: 3, bytes: 60 });
+        assert!(this.cache_dir.is_dir());
+        assert_eq!(fs::read_dir(&this.cache_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn clear_cache_tolerates_missing_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut this = layout(false);
+        this.cache_dir = tmp.path().join("does-not-exist");
+
+        assert_eq!(this.clear_cache().unwrap(), ClearReport::default());
+    }
+
+    #[test]
+    fn clear_cache_refuses_suspicious_dirs() {
+        let mut this = layout(false);
+        this.cache_dir = PathBuf::from("/");
+
+        assert_eq!(this.clear_cache().unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        if let Some(base_dirs) = directories::BaseDirs::new() {
+            this.cache_dir = base_dirs.home_dir().to_path_buf();
+
+            assert_eq!(this.clear_cache().unwrap_err().kind(), ErrorKind::InvalidInput);
+        }
+
+        for relative in [".", "..", "", "cache"] {
+            this.cache_dir = PathBuf::from(relative);
+
+            assert_eq!(this.clear_cache().unwrap_err().kind(), ErrorKind::InvalidInput, "{relative}");
+            assert_eq!(this.prune_cache(0).unwrap_err().kind(), ErrorKind::InvalidInput, "{relative}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn clear_cache_refuses_symlinks_to_suspicious_dirs() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let mut this = layout(false);
+        this.cache_dir = tmp.path().join("cache");
+        symlink("/", &this.cache_dir).unwrap();
+
+        assert_eq!(this.clear_cache().unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        if let Some(base_dirs) = directories::BaseDirs::new() {
+            this.cache_dir = tmp.path().join("home");
+            symlink(base_dirs.home_dir(), &this.cache_dir).unwrap();
+
+            assert_eq!(this.clear_cache().unwrap_err().kind(), ErrorKind::InvalidInput);
+            assert_eq!(this.prune_cache(0).unwrap_err().kind(), ErrorKind::InvalidInput);
+        }
+
+        // a symlink to a regular directory is fine
+        fs::create_dir(tmp.path().join("real")).unwrap();
+        fs::write(tmp.path().join("real/file"), [0; 10]).unwrap();
+        this.cache_dir = tmp.path().join("link");
+        symlink(tmp.path().join("real"), &this.cache_dir).unwrap();
+
+        assert_eq!(this.clear_cache().unwrap(), ClearReport { files: 1, bytes: 10 });
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn clear_cache_does_not_follow_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let outside = tmp.path().join("outside");
+        let mut this = layout(false);
+        this.cache_dir = tmp.path().join("cache");
+
+        fs::create_dir_all(&this.cache_dir).unwrap();
+        fs::create_dir_all(outside.join("dir")).unwrap();
+        fs::write(outside.join("file"), [0; 10]).unwrap();
+        fs::write(outside.join("dir/file"), [0; 10]).unwrap();
+        symlink(outside.join("file"), this.cache_dir.join("file-link")).unwrap();
+        symlink(outside.join("dir"), this.cache_dir.join("dir-link")).unwrap();
+
+        let report = this.clear_cache().unwrap();
+
+        assert_eq!(report, ClearReport { files: 2, bytes: 0 });
+        assert!(outside.join("file").exists());
+        assert!(outside.join("dir/file").exists());
+        assert_eq!(fs::read_dir(&this.cache_dir).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,146 @@
+//! Storage backing the modules generated by [`location!`](crate::location).
+//!
+//! Normally the provider lives in a [`OnceLock`](std::sync::OnceLock) and can only ever be set
+//! once. With the `test-util` feature enabled (or when testing this crate) it instead lives in a
+//! [`RwLock`] so that it can be torn down with `reset()` and initialized again.
+
+#[cfg(not(any(test, feature = "test-util")))]
+mod imp {
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
+    use crate::{Locations, ProjectDirsOrEnv};
+
+    pub struct ProviderCell(OnceLock<Locations>);
+
+    impl ProviderCell {
+        #[allow(clippy::new_without_default)]
+        pub const fn new() -> Self {
+            Self(OnceLock::new())
+        }
+
+        /// Returns `false` if the provider was already set.
+        pub fn set(&self, provider: Locations) -> bool {
+            self.0.set(provider).is_ok()
+        }
+
+        pub fn get(&'static self) -> Option<&'static Locations> {
+            self.0.get()
+        }
+    }
+
+    pub struct PathCell(OnceLock<PathBuf>);
+
+    impl PathCell {
+        #[allow(clippy::new_without_default)]
+        pub const fn new() -> Self {
+            Self(OnceLock::new())
+        }
+
+        pub fn get_or_init(
+            &'static self,
+            provider: &'static Locations,
+            f: fn(&'static ProjectDirsOrEnv) -> PathBuf,
+        ) -> &'static Path {
+            self.0.get_or_init(|| f(provider))
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+mod imp {
+    use std::path::{Path, PathBuf};
+    use std::sync::{Mutex, RwLock};
+    use crate::{Locations, ProjectDirsOrEnv};
+
+    // every provider is leaked so that the `&'static` references handed out by the generated
+    // module stay valid even after a `reset()`.
+    pub struct ProviderCell(RwLock<Option<&'static Locations>>);
+
+    impl ProviderCell {
+        #[allow(clippy::new_without_default)]
+        pub const fn new() -> Self {
+            Self(RwLock::new(None))
+        }
+
+        /// Returns `false` if the provider was already set.
+        pub fn set(&self, provider: Locations) -> bool {
+            let mut slot = self.0.write().unwrap_or_else(|e| e.into_inner());
+
+            if slot.is_some() {
+                return false
+            }
+
+            *slot = Some(Box::leak(Box::new(provider)));
+            true
+        }
+
+        pub fn get(&'static self) -> Option<&'static Locations> {
+            *self.0.read().unwrap_or_else(|e| e.into_inner())
+        }
+
+        pub fn reset(&self) {
+            *self.0.write().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+    }
+
+    // keyed by the address of the provider the path was derived from, so that a reinitialized
+    // provider causes the path to be derived again.
+    pub struct PathCell(Mutex<Option<(usize, &'static Path)>>);
+
+    impl PathCell {
+        #[allow(clippy::new_without_default)]
+        pub const fn new() -> Self {
+            Self(Mutex::new(None))
+        }
+
+        pub fn get_or_init(
+            &'static self,
+            provider: &'static Locations,
+            f: fn(&'static ProjectDirsOrEnv) -> PathBuf,
+        ) -> &'static Path {
+            let key = provider as *const Locations as usize;
+            let mut slot = self.0.lock().unwrap_or_else(|e| e.into_inner());
+
+            match *slot {
+                Some((k, path)) if k == key => path,
+                _ => {
+                    let path: &'static Path = Box::leak(f(provider).into_boxed_path());
+                    *slot = Some((key, path));
+                    path
+                }
+            }
+        }
+    }
+}
+
+pub use imp::*;
+
+/// Expands to the `reset()` function of a generated module when `test-util` is enabled.
+#[cfg(any(test, feature = "test-util"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __reset_fn {
+    ($provider:ident; $env_prefix:expr) => {
+        /// Tears down the provider and unregisters its layout, so that [`initialize`] can be called
+        /// again.
+        ///
+        /// Only available with the `test-util` feature, and intended for tests only:
+        ///
+        /// * References previously returned by this module are *not* invalidated; they keep
+        ///   pointing at the old layout, which is leaked rather than freed.
+        /// * Tests within the same binary run in parallel by default and share this module, so a
+        ///   test resetting the layout races with every other test reading it. Serialize such
+        ///   tests (e.g. behind a shared `Mutex`, or with `--test-threads=1`).
+        pub fn reset() {
+            $provider.reset();
+            $crate::registry::remove($env_prefix);
+        }
+    };
+}
+
+#[cfg(not(any(test, feature = "test-util")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __reset_fn {
+    ($provider:ident; $env_prefix:expr) => {};
+}
@@ -0,0 +1,99 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use crate::scratch::{self, ScratchDir};
+use crate::{InitSnapshot, ParityReport, ProjectDirsOrEnv};
+
+impl ProjectDirsOrEnv {
+    /// Creates a fresh layout in the system temp directory for hermetic tests, with every
+    /// directory (including a single site config directory) created under one root. The root is
+    /// removed along with its contents when the returned guard is dropped, even while unwinding
+    /// from a panic.
+    ///
+    /// Environment variables are neither read nor set. Only available with the `test-util`
+    /// feature. To initialize a [`location!`](crate::location) module with it, replace the
+    /// resolved layout in `initialize_with`:
+    ///
+    /// ```ignore
+    /// let (layout, _root) = ProjectDirsOrEnv::ephemeral()?;
+    /// dirs::initialize_with(|dirs| { dirs.replace(layout); })?;
+    /// ```
+    pub fn ephemeral() -> io::Result<(Self, ScratchDir)> {
+        let root = scratch::create_unique(&env::temp_dir(), "alptk-location-ephemeral-")?;
+        let dir = |name: &str| -> io::Result<PathBuf> {
+            let path = root.path().join(name);
+            fs::create_dir(&path)?;
+            Ok(path)
+        };
+
+        let this = Self {
+            cache_dir: dir("cache")?,
+            config_dir: dir("config")?,
+            config_local_dir: dir("config_local")?,
+            data_dir: dir("data")?,
+            data_local_dir: dir("data_local")?,
+            preference_dir: dir("preference")?,
+            project_path: root.path().file_name().map(PathBuf::from).unwrap_or_default(),
+            runtime_dir: Some(dir("runtime")?),
+            state_dir: Some(dir("state")?),
+            log_dir: dir("log")?,
+            executable_dir: Some(dir("executable")?),
+            site_config_dirs: vec![dir("site_config")?],
+            parity: ParityReport::default(),
+            project_dirs: None,
+            app_image: None,
+            init_snapshot: InitSnapshot::default(),
+        };
+
+        Ok((this, root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+    use std::path::PathBuf;
+    use crate::{DirKind, ProjectDirsOrEnv};
+
+    #[test]
+    fn every_dir_exists_under_root() {
+        let (dirs, root) = ProjectDirsOrEnv::ephemeral().unwrap();
+
+        for kind in DirKind::ALL.into_iter().filter(|kind| *kind != DirKind::ProjectPath) {
+            let path = dirs.get(kind).unwrap();
+
+            assert!(path.starts_with(root.path()), "{kind:?}");
+            assert!(path.is_dir(), "{kind:?}");
+        }
+
+        assert!(dirs.site_config_dirs()[0].is_dir());
+        assert!(dirs.project_path().is_relative());
+    }
+
+    #[test]
+    fn removed_on_drop_and_panic() {
+        let (_, root) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let path = root.path().to_owned();
+        drop(root);
+
+        assert!(!path.exists());
+
+        let result = panic::catch_unwind(|| {
+            let (dirs, _root) = ProjectDirsOrEnv::ephemeral().unwrap();
+            panic!("{}", dirs.cache_dir().display());
+        });
+        let message = result.unwrap_err();
+        let cache_dir = PathBuf::from(message.downcast_ref::<String>().unwrap());
+
+        assert!(!cache_dir.parent().unwrap().exists());
+    }
+
+    #[test]
+    fn distinct_roots() {
+        let (first, _first) = ProjectDirsOrEnv::ephemeral().unwrap();
+        let (second, _second) = ProjectDirsOrEnv::ephemeral().unwrap();
+
+        assert_ne!(first.data_dir(), second.data_dir());
+    }
+}
@@ -1,79 +1,439 @@
-use std::env;
-use std::env::VarError;
-use std::ffi::OsString;
-use std::path::{Path, PathBuf};
+use std::{env, fmt};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path, PathBuf};
 use directories::ProjectDirs;
 use thiserror::Error;
 
+mod access;
+mod adjust;
+mod appimage;
+mod builder;
+mod cache;
+mod cell;
+#[cfg(any(test, feature = "test-util"))]
+mod ephemeral;
+mod locations;
+mod paths_file;
+pub mod registry;
+mod reload;
+mod scratch;
+mod snapshot;
+mod validate;
+mod versions;
+
+pub use access::{AccessReport, DirAccess};
+pub use adjust::ProjectDirsOrEnvMut;
+pub use appimage::{AppImage, Relocation};
+pub use builder::ProjectDirsOrEnvBuilder;
+pub use cache::{ClearReport, PruneReport, DEFAULT_PRUNE_GRACE_PERIOD};
+pub use locations::Locations;
+pub use paths_file::PathsFileError;
+pub use reload::{ChangedDirs, ReloadableLocations};
+pub use scratch::ScratchDir;
+pub use snapshot::InitSnapshot;
+pub use validate::{Validation, ValidationError, ValidationFailure};
+pub use directories;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::cell::{PathCell, ProviderCell};
+
+    /// Derives the env prefix of a [`location!`](crate::location) module without one from the
+    /// package name, e.g. `MY_APP` from `my-app`.
+    pub fn derive_env_prefix(package_name: &str) -> String {
+        package_name.to_ascii_uppercase().replace('-', "_")
+    }
+}
+
+/// Input that doesn't start with `inline;` or `mod name;` is a compile error:
+///
+/// ```compile_fail
+/// alptk_location::location! {
+///     dirs;
+///     "APP";
+/// }
+/// ```
 #[macro_export]
 macro_rules! location {
     (
+        #[canonicalize]
+        $($rest:tt)*
+    ) => {
+        $crate::location!(@impl true; $($rest)*);
+    };
+    (
+        @impl $canonicalize:literal;
+        inline;
+        $env_prefix:literal;
+
+        $($fns:tt)*
+    ) => {
+        $crate::location!(@inline $canonicalize; { $env_prefix }; $($fns)*);
+    };
+    (
+        @impl $canonicalize:literal;
+        inline;
+
+        $($fns:tt)*
+    ) => {
+        $crate::location!(@inline $canonicalize; $crate::location!(@derived_env_prefix); $($fns)*);
+    };
+    (
+        @impl $canonicalize:literal;
         $mod_vis:vis mod $module_name:ident;
         $env_prefix:literal;
 
-        $($fn_name:ident = $fn_expr:expr;)*
+        $($fns:tt)*
+    ) => {
+        $crate::location!(@expand $canonicalize; $mod_vis mod $module_name; { $env_prefix }; $($fns)*);
+    };
+    (
+        @impl $canonicalize:literal;
+        $mod_vis:vis mod $module_name:ident;
+
+        $($fns:tt)*
+    ) => {
+        $crate::location!(
+            @expand $canonicalize;
+            $mod_vis mod $module_name;
+            $crate::location!(@derived_env_prefix);
+            $($fns)*
+        );
+    };
+    (@derived_env_prefix) => {{
+        static ENV_PREFIX: ::std::sync::OnceLock<::std::string::String> = ::std::sync::OnceLock::new();
+        ENV_PREFIX.get_or_init(|| $crate::__private::derive_env_prefix(APP_NAME))
+    }};
+    (
+        @expand $canonicalize:literal;
+        $mod_vis:vis mod $module_name:ident;
+        $env_prefix:expr;
+
+        $($fns:tt)*
     ) => {
         $mod_vis mod $module_name {
-            static PROVIDER: ::std::sync::OnceLock<$crate::ProjectDirsOrEnv> = ::std::sync::OnceLock::new();
-            
-            pub fn initialize() -> ::core::result::Result<(), $crate::InitializeError> {
-                if PROVIDER.set($crate::ProjectDirsOrEnv::new(env!("CARGO_PKG_NAME"), $env_prefix)?).is_err() {
-                    panic!("project directories/env provider already initialized")
-                }
-                
-                Ok(())
-            }
-            
-            fn provider() -> &'static $crate::ProjectDirsOrEnv {
-                PROVIDER.get().expect("project directories/env provider not yet initialized")
-            }
-            
-            pub fn cache_dir() -> &'static ::std::path::Path {
-                provider().cache_dir()
-            }
-            
-            pub fn config_dir() -> &'static ::std::path::Path {
-                provider().config_dir()
-            }
-            
-             pub fn config_local_dir() -> &'static ::std::path::Path {
-                provider().config_local_dir()
-            }
-            
-            pub fn data_dir() -> &'static ::std::path::Path {
-                provider().data_dir()
-            }
-            
-            pub fn data_local_dir() -> &'static ::std::path::Path {
-                provider().data_local_dir()
-            }           
-            
-            pub fn preference_dir() -> &'static ::std::path::Path {
-                provider().preference_dir()
-            }
-            
-            pub fn project_path() -> &'static ::std::path::Path {
-                provider().project_path()
-            }
-            
-            pub fn runtime_dir() -> Option<&'static ::std::path::Path> {
-                provider().runtime_dir()
+            $crate::location!(@items $canonicalize; $env_prefix; $($fns)*);
+        }
+    };
+    (
+        @inline $canonicalize:literal;
+        $env_prefix:expr;
+
+        $($fns:tt)*
+    ) => {
+        #[doc(hidden)]
+        mod __alptk_location {
+            // lets custom functions refer to the items around the invocation, as if they were
+            // defined there
+            #[allow(unused_imports)]
+            use super::*;
+
+            $crate::location!(@items $canonicalize; $env_prefix; $($fns)*);
+        }
+
+        pub use self::__alptk_location::*;
+    };
+    (
+        @items $canonicalize:literal;
+        $env_prefix:expr;
+
+        $($fns:tt)*
+    ) => {
+        static PROVIDER: $crate::__private::ProviderCell = $crate::__private::ProviderCell::new();
+        const APP_NAME: &str = env!("CARGO_PKG_NAME");
+
+        /// The env prefix of this module, derived from the package name if it wasn't given.
+        pub fn env_prefix() -> &'static str {
+            $env_prefix
+        }
+
+        /// Resolves the layout, unless another module with the same env prefix already did, even
+        /// in another crate, in which case its layout is shared. See [`registry`]($crate::registry).
+        pub fn initialize() -> ::core::result::Result<(), $crate::InitializeError> {
+            let locations = $crate::registry::get_or_try_register(env_prefix(), || resolve(builder(), |_| {}))?;
+
+            freeze(locations)
+        }
+
+        /// Like `initialize`, but lets `adjust` edit the resolved directories before they are
+        /// frozen. See [`ProjectDirsOrEnv::adjusted`]($crate::ProjectDirsOrEnv::adjusted).
+        ///
+        /// The layout is always resolved rather than shared. It is [registered]($crate::registry)
+        /// for the modules initialized later, unless a layout is registered already.
+        pub fn initialize_with(
+            adjust: impl ::core::ops::FnOnce(&mut $crate::ProjectDirsOrEnvMut<'_>),
+        ) -> ::core::result::Result<(), $crate::InitializeError> {
+            register(resolve(builder(), adjust)?)
+        }
+
+        /// Like `initialize`, but with directories given e.g. as command line flags, which take
+        /// precedence over the environment. See
+        /// [`ProjectDirsOrEnvBuilder::overrides`]($crate::ProjectDirsOrEnvBuilder::overrides).
+        ///
+        /// Like `initialize_with`, the layout is always resolved rather than shared.
+        pub fn initialize_with_overrides(
+            overrides: impl ::core::iter::IntoIterator<Item = ($crate::DirKind, ::std::path::PathBuf)>,
+        ) -> ::core::result::Result<(), $crate::InitializeError> {
+            register(resolve(builder().overrides(overrides), |_| {})?)
+        }
+
+        fn resolve(
+            builder: $crate::ProjectDirsOrEnvBuilder,
+            adjust: impl ::core::ops::FnOnce(&mut $crate::ProjectDirsOrEnvMut<'_>),
+        ) -> ::core::result::Result<$crate::Locations, $crate::InitializeError> {
+            let mut provider = builder.build()?.adjusted(adjust);
+
+            if $canonicalize {
+                provider = provider.canonicalized();
             }
 
-            pub fn state_dir() -> Option<&'static ::std::path::Path> {
-                provider().state_dir()
+            Ok($crate::Locations::from(provider))
+        }
+
+        fn register(locations: $crate::Locations) -> ::core::result::Result<(), $crate::InitializeError> {
+            // a layout registered already is kept for the modules sharing it
+            let _ = $crate::registry::register(env_prefix(), locations.clone());
+
+            freeze(locations)
+        }
+
+        fn freeze(locations: $crate::Locations) -> ::core::result::Result<(), $crate::InitializeError> {
+            if !PROVIDER.set(locations) {
+                panic!("project directories/env provider already initialized")
             }
             
-            $(pub fn $fn_name() -> &'static ::std::path::Path {
-                static VALUE: ::std::sync::OnceLock<::std::path::PathBuf> = ::std::sync::OnceLock::new();
-                
-                VALUE.get_or_init(|| {
-                    let f: fn(&'static $crate::ProjectDirsOrEnv) -> ::std::path::PathBuf = $fn_expr;
-                    
-                    f(provider())
-                })
-            })*
+            Ok(())
+        }
+
+        $crate::__reset_fn!(PROVIDER; env_prefix());
+
+        fn builder() -> $crate::ProjectDirsOrEnvBuilder {
+            let builder = $crate::ProjectDirsOrEnv::builder(APP_NAME).env_prefix(env_prefix());
+
+            $crate::__location_env!(builder; $($fns)*)
+        }
+
+        /// Returns the names of every environment variable consulted by `initialize`.
+        pub fn env_vars() -> ::std::vec::Vec<::std::string::String> {
+            builder().env_vars()
+        }
+        
+        fn provider() -> &'static $crate::Locations {
+            PROVIDER.get().expect("project directories/env provider not yet initialized")
+        }
+
+        /// Returns a handle to the layout, for code which takes a [`Locations`]($crate::Locations).
+        pub fn locations() -> $crate::Locations {
+            provider().clone()
+        }
+        
+        pub fn cache_dir() -> &'static ::std::path::Path {
+            provider().cache_dir()
+        }
+        
+        pub fn config_dir() -> &'static ::std::path::Path {
+            provider().config_dir()
+        }
+        
+         pub fn config_local_dir() -> &'static ::std::path::Path {
+            provider().config_local_dir()
+        }
+        
+        pub fn data_dir() -> &'static ::std::path::Path {
+            provider().data_dir()
+        }
+        
+        pub fn data_local_dir() -> &'static ::std::path::Path {
+            provider().data_local_dir()
+        }           
+        
+        pub fn preference_dir() -> &'static ::std::path::Path {
+            provider().preference_dir()
+        }
+        
+        pub fn project_path() -> &'static ::std::path::Path {
+            provider().project_path()
+        }
+        
+        pub fn runtime_dir() -> Option<&'static ::std::path::Path> {
+            provider().runtime_dir()
+        }
+
+        pub fn state_dir() -> Option<&'static ::std::path::Path> {
+            provider().state_dir()
+        }
+
+        pub fn log_dir() -> &'static ::std::path::Path {
+            provider().log_dir()
+        }
+
+        pub fn executable_dir() -> Option<&'static ::std::path::Path> {
+            provider().executable_dir()
+        }
+
+        pub fn site_config_dirs() -> &'static [::std::path::PathBuf] {
+            provider().site_config_dirs()
+        }
+
+        pub fn clear_cache() -> ::std::io::Result<$crate::ClearReport> {
+            provider().clear_cache()
+        }
+
+        pub fn prune_cache(max_bytes: u64) -> ::std::io::Result<$crate::PruneReport> {
+            provider().prune_cache(max_bytes)
+        }
+
+        pub fn scratch_dir() -> ::std::io::Result<$crate::ScratchDir> {
+            provider().scratch_dir()
+        }
+
+        pub fn versioned_data_dir(version: u32) -> ::std::path::PathBuf {
+            provider().versioned_data_dir(version)
+        }
+
+        pub fn list_data_versions() -> ::std::io::Result<::std::vec::Vec<u32>> {
+            provider().list_data_versions()
+        }
+
+        pub fn parity_report() -> &'static $crate::ParityReport {
+            provider().parity_report()
+        }
+
+        pub fn init_snapshot() -> &'static $crate::InitSnapshot {
+            provider().init_snapshot()
+        }
+
+        pub fn table_rows() -> ::std::vec::Vec<(::std::string::String, ::std::string::String)> {
+            provider().table_rows()
+        }
+
+        pub fn as_map() -> ::std::collections::BTreeMap<&'static str, Option<&'static ::std::path::Path>> {
+            provider().as_map()
+        }
+
+        pub fn as_string_map() -> ::std::collections::BTreeMap<&'static str, Option<::std::string::String>> {
+            provider().as_string_map()
         }
+        
+        $crate::__location_fns!($($fns)*);
+    };
+    (@impl $($rest:tt)*) => {
+        ::core::compile_error!("expected `inline;` or `mod name;`, optionally followed by an env prefix literal and `;`");
+    };
+    ($($rest:tt)*) => {
+        $crate::location!(@impl false; $($rest)*);
+    };
+}
+
+/// Expands the custom functions of [`location!`], which are either `name = closure;` or, for a
+/// fixed subdirectory of a standard directory, `name = base_dir / "segment" / ...;`. Renamed
+/// environment variables are handled by [`__location_env!`] instead.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __location_fns {
+    () => {};
+    (env $dir:ident = $var:literal; $($rest:tt)*) => {
+        $crate::__location_fns!($($rest)*);
+    };
+    ($fn_name:ident = $base:ident $(/ $segment:literal)+; $($rest:tt)*) => {
+        $crate::__location_fns!(@fn $fn_name, |provider| $crate::__location_base!(provider, $base)$(.join($segment))*);
+        $crate::__location_fns!($($rest)*);
+    };
+    ($fn_name:ident = $fn_expr:expr; $($rest:tt)*) => {
+        $crate::__location_fns!(@fn $fn_name, $fn_expr);
+        $crate::__location_fns!($($rest)*);
+    };
+    (@fn $fn_name:ident, $fn_expr:expr) => {
+        pub fn $fn_name() -> &'static ::std::path::Path {
+            static VALUE: $crate::__private::PathCell = $crate::__private::PathCell::new();
+
+            VALUE.get_or_init(provider(), $fn_expr)
+        }
+    };
+}
+
+/// Applies the `env dir_name = "VAR";` lines of [`location!`] to `$builder`, skipping the custom
+/// functions.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __location_env {
+    ($builder:expr;) => { $builder };
+    ($builder:expr; env $dir:ident = $var:literal; $($rest:tt)*) => {
+        $crate::__location_env!($builder.env_var($crate::__location_kind!($dir), $var); $($rest)*)
+    };
+    ($builder:expr; $fn_name:ident = $base:ident $(/ $segment:literal)+; $($rest:tt)*) => {
+        $crate::__location_env!($builder; $($rest)*)
+    };
+    ($builder:expr; $fn_name:ident = $fn_expr:expr; $($rest:tt)*) => {
+        $crate::__location_env!($builder; $($rest)*)
+    };
+}
+
+/// Maps the name of a directory function to its [`DirKind`].
+///
+/// ```compile_fail
+/// alptk_location::location! {
+///     mod dirs;
+///     "APP";
+///
+///     env conf_dir = "APP_CONF";
+/// }
+/// ```
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __location_kind {
+    (cache_dir) => { $crate::DirKind::Cache };
+    (config_dir) => { $crate::DirKind::Config };
+    (config_local_dir) => { $crate::DirKind::ConfigLocal };
+    (data_dir) => { $crate::DirKind::Data };
+    (data_local_dir) => { $crate::DirKind::DataLocal };
+    (preference_dir) => { $crate::DirKind::Preference };
+    (project_path) => { $crate::DirKind::ProjectPath };
+    (runtime_dir) => { $crate::DirKind::Runtime };
+    (state_dir) => { $crate::DirKind::State };
+    (log_dir) => { $crate::DirKind::Log };
+    (executable_dir) => { $crate::DirKind::Executable };
+    ($other:ident) => {
+        ::core::compile_error!(::core::concat!("`", ::core::stringify!($other), "` is not a directory"))
+    };
+}
+
+/// Resolves the base directory of a `name = base_dir / "segment";` function of [`location!`],
+/// rejecting anything which isn't a standard directory that is always set.
+///
+/// ```compile_fail
+/// alptk_location::location! {
+///     mod dirs;
+///     "APP";
+///
+///     plugins_dir = date_dir / "plugins";
+/// }
+/// ```
+///
+/// ```compile_fail
+/// alptk_location::location! {
+///     mod dirs;
+///     "APP";
+///
+///     sockets_dir = runtime_dir / "sockets";
+/// }
+/// ```
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __location_base {
+    ($provider:ident, cache_dir) => { $provider.cache_dir() };
+    ($provider:ident, config_dir) => { $provider.config_dir() };
+    ($provider:ident, config_local_dir) => { $provider.config_local_dir() };
+    ($provider:ident, data_dir) => { $provider.data_dir() };
+    ($provider:ident, data_local_dir) => { $provider.data_local_dir() };
+    ($provider:ident, preference_dir) => { $provider.preference_dir() };
+    ($provider:ident, log_dir) => { $provider.log_dir() };
+    ($provider:ident, $other:ident) => {
+        ::core::compile_error!(::core::concat!(
+            "`", ::core::stringify!($other), "` is not a base directory, expected one of `cache_dir`, ",
+            "`config_dir`, `config_local_dir`, `data_dir`, `data_local_dir`, `preference_dir` or `log_dir`",
+        ))
     };
 }
 
@@ -82,67 +442,49 @@ trait Provider: Sized {
     type Error;
 
     fn new(init: Self::Init<'_>) -> Result<Self, Self::Error>;
-    fn cache_dir(&self) -> Option<&Path>;
-    fn config_dir(&self) -> Option<&Path>;
-    fn config_local_dir(&self) -> Option<&Path>;
-    fn data_dir(&self) -> Option<&Path>;
-    fn data_local_dir(&self) -> Option<&Path>;
-    fn preference_dir(&self) -> Option<&Path>;
-    fn project_path(&self) -> Option<&Path>;
-    fn runtime_dir(&self) -> Option<&Path>;
-    fn state_dir(&self) -> Option<&Path>;
 }
 
+/// The home directory, which the platform defaults are derived from, could not be found. This
+/// typically happens when `$HOME` is unset, e.g. in minimal containers or systemd units.
 #[derive(Error, Debug)]
-#[error("the home directory could not be found")]
 #[non_exhaustive]
-pub struct HomeDirNotFoundError;
-
-impl Provider for ProjectDirs {
-    type Init<'a> = &'a str;
-    type Error = HomeDirNotFoundError;
-
-    fn new(app_name: Self::Init<'_>) -> Result<Self, Self::Error> {
-        Self::from("", "ALinuxPerson", app_name).ok_or(HomeDirNotFoundError)
-    }
-
-    fn cache_dir(&self) -> Option<&Path> {
-        Some(self.cache_dir())
-    }
-
-    fn config_dir(&self) -> Option<&Path> {
-        Some(self.config_dir())
-    }
+pub struct HomeDirNotFoundError {
+    pub app_name: String,
 
-    fn config_local_dir(&self) -> Option<&Path> {
-        Some(self.config_local_dir())
-    }
+    /// The env prefix of the layout, or `None` if the environment wasn't consulted.
+    pub env_prefix: Option<String>,
 
-    fn data_dir(&self) -> Option<&Path> {
-        Some(self.data_dir())
-    }
+    /// The names of the variables which weren't set, but would have avoided the fallback to the
+    /// platform defaults altogether.
+    pub missing_vars: Vec<String>,
+}
 
-    fn data_local_dir(&self) -> Option<&Path> {
-        Some(self.data_local_dir())
-    }
+impl fmt::Display for HomeDirNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the home directory could not be found for the app '{}'", self.app_name)?;
 
-    fn preference_dir(&self) -> Option<&Path> {
-        Some(self.preference_dir())
-    }
+        if self.env_prefix.is_some() && !self.missing_vars.is_empty() {
+            write!(f, "; set {} to run without it", self.missing_vars.join(", "))?;
+        }
 
-    fn project_path(&self) -> Option<&Path> {
-        Some(self.project_path())
+        Ok(())
     }
+}
 
-    fn runtime_dir(&self) -> Option<&Path> {
-        self.runtime_dir()
-    }
+impl Provider for ProjectDirs {
+    type Init<'a> = &'a str;
+    type Error = HomeDirNotFoundError;
 
-    fn state_dir(&self) -> Option<&Path> {
-        self.state_dir()
+    fn new(app_name: Self::Init<'_>) -> Result<Self, Self::Error> {
+        Self::from("", "ALinuxPerson", app_name).ok_or_else(|| HomeDirNotFoundError {
+            app_name: app_name.to_owned(),
+            env_prefix: None,
+            missing_vars: Vec::new(),
+        })
     }
 }
 
+#[derive(Default, Clone)]
 struct Env {
     cache_dir: Option<PathBuf>,
     config_dir: Option<PathBuf>,
@@ -153,8 +495,13 @@ struct Env {
     project_path: Option<PathBuf>,
     runtime_dir: Option<PathBuf>,
     state_dir: Option<PathBuf>,
+    log_dir: Option<PathBuf>,
+    executable_dir: Option<PathBuf>,
+    site_config_dirs: Option<Vec<PathBuf>>,
 }
 
+/// Only produced on platforms where paths aren't arbitrary bytes (i.e. not on unix), as converting
+/// non-unicode data to a path there could corrupt it.
 #[derive(Error, Debug)]
 #[error("the environment variable '{name}' does not contain valid unicode data")]
 #[non_exhaustive]
@@ -163,77 +510,235 @@ pub struct EnvVarNotUnicodeError {
     pub value: OsString,
 }
 
-impl Provider for Env {
-    type Init<'a> = &'a str;
-    type Error = EnvVarNotUnicodeError;
+/// Converts the value of an environment variable to a path. Paths are bytes on unix, so any value
+/// is accepted there.
+#[cfg(unix)]
+fn env_path(value: OsString) -> PathBuf {
+    PathBuf::from(value)
+}
 
-    fn new(env_prefix: Self::Init<'_>) -> Result<Self, Self::Error> {
-        let x = |suffix: &str| {
-            let key = format!("{env_prefix}{suffix}");
+/// Converts the value of the environment variable `name` to a path.
+#[cfg(not(unix))]
+fn env_path(name: String, value: OsString) -> Result<PathBuf, EnvVarNotUnicodeError> {
+    value
+        .into_string()
+        .map(PathBuf::from)
+        .map_err(|value| EnvVarNotUnicodeError { name, value })
+}
 
-            match env::var(&key) {
-                Ok(value) => Ok(Some(PathBuf::from(value))),
-                Err(VarError::NotPresent) => Ok(None),
-                Err(VarError::NotUnicode(value)) => Err(EnvVarNotUnicodeError { name: key, value }),
-            }
-        };
+/// Normalizes a windows path pasted into an environment variable, so that joining and comparing
+/// it behaves:
+///
+/// * the verbatim prefix is stripped from drive (`\\?\C:\app` becomes `C:\app`) and UNC
+///   (`\\?\UNC\server\share` becomes `\\server\share`) paths, other verbatim paths are left
+///   as they are, since separators are taken literally in them;
+/// * forward slashes become backslashes;
+/// * `.` components and duplicate separators are removed, while `..` components are kept;
+/// * trailing separators are stripped, unless they are part of the root, as in `C:\`.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn normalize_windows_path(path: &str) -> String {
+    let path = if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{rest}")
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        if !is_drive_absolute(rest) {
+            return path.to_owned()
+        }
 
-        Ok(Self {
-            cache_dir: x("_CACHE_DIR")?,
-            config_dir: x("_CONFIG_DIR")?,
-            config_local_dir: x("_CONFIG_LOCAL_DIR")?,
-            data_dir: x("_DATA_DIR")?,
-            data_local_dir: x("_DATA_LOCAL_DIR")?,
-            preference_dir: x("_PREFERENCE_DIR")?,
-            project_path: x("_PROJECT_PATH")?,
-            runtime_dir: x("_RUNTIME_DIR")?,
-            state_dir: x("_STATE_DIR")?,
-        })
+        rest.to_owned()
+    } else {
+        path.to_owned()
+    };
+    let path = path.replace('/', r"\");
+
+    let root_len = if is_drive_absolute(&path) {
+        3
+    } else if path.starts_with(r"\\") {
+        2
+    } else if path.starts_with('\\') {
+        1
+    } else {
+        0
+    };
+    let (root, rest) = path.split_at(root_len);
+    let rest = rest.split('\\').filter(|component| !component.is_empty() && *component != ".").collect::<Vec<_>>();
+
+    match (root, rest.is_empty()) {
+        ("", true) => ".".to_owned(),
+        _ => format!("{root}{}", rest.join(r"\")),
     }
+}
+
+/// Whether `path` starts with a drive letter followed by a separator, e.g. `C:\`.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn is_drive_absolute(path: &str) -> bool {
+    matches!(path.as_bytes(), [drive, b':', b'\\' | b'/', ..] if drive.is_ascii_alphabetic())
+}
 
-    fn cache_dir(&self) -> Option<&Path> {
-        self.cache_dir.as_deref()
+/// Lexically normalizes `path`, without touching the filesystem: `.` components, duplicate
+/// separators and trailing separators are removed, e.g. `/home/me/./.config//app/` becomes
+/// `/home/me/.config/app`. `..` components are kept, as removing them along with their parent
+/// would be wrong if the parent is a symlink. A path consisting of `.` components only becomes
+/// `.`.
+///
+/// On windows, verbatim prefixes are also stripped where possible and forward slashes become
+/// backslashes.
+pub fn normalize_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+
+    #[cfg(windows)]
+    if let Some(path) = path.to_str() {
+        return PathBuf::from(normalize_windows_path(path))
     }
 
-    fn config_dir(&self) -> Option<&Path> {
-        self.config_dir.as_deref()
+    let normalized = path.components().filter(|component| *component != Component::CurDir).collect::<PathBuf>();
+
+    match normalized.as_os_str().is_empty() && !path.as_os_str().is_empty() {
+        true => PathBuf::from("."),
+        false => normalized,
     }
+}
 
-    fn config_local_dir(&self) -> Option<&Path> {
-        self.config_local_dir.as_deref()
+/// Applies [`normalize_path`] to a path taken from an environment variable if `normalize` is set.
+fn normalize_env_path(path: PathBuf, normalize: bool) -> PathBuf {
+    match normalize {
+        true => normalize_path(path),
+        false => path,
     }
+}
 
-    fn data_dir(&self) -> Option<&Path> {
-        self.data_dir.as_deref()
+impl Provider for Env {
+    type Init<'a> = &'a str;
+    type Error = EnvVarNotUnicodeError;
+
+    fn new(env_prefix: Self::Init<'_>) -> Result<Self, Self::Error> {
+        Self::named(env_prefix, &HashMap::new(), true, |name| env::var_os(name))
     }
+}
 
-    fn data_local_dir(&self) -> Option<&Path> {
-        self.data_local_dir.as_deref()
+impl Env {
+    fn get(&self, kind: DirKind) -> Option<&Path> {
+        match kind {
+            DirKind::Cache => self.cache_dir.as_deref(),
+            DirKind::Config => self.config_dir.as_deref(),
+            DirKind::ConfigLocal => self.config_local_dir.as_deref(),
+            DirKind::Data => self.data_dir.as_deref(),
+            DirKind::DataLocal => self.data_local_dir.as_deref(),
+            DirKind::Preference => self.preference_dir.as_deref(),
+            DirKind::ProjectPath => self.project_path.as_deref(),
+            DirKind::Runtime => self.runtime_dir.as_deref(),
+            DirKind::State => self.state_dir.as_deref(),
+            DirKind::Log => self.log_dir.as_deref(),
+            DirKind::Executable => self.executable_dir.as_deref(),
+        }
     }
 
-    fn preference_dir(&self) -> Option<&Path> {
-        self.preference_dir.as_deref()
+    fn get_mut(&mut self, kind: DirKind) -> &mut Option<PathBuf> {
+        match kind {
+            DirKind::Cache => &mut self.cache_dir,
+            DirKind::Config => &mut self.config_dir,
+            DirKind::ConfigLocal => &mut self.config_local_dir,
+            DirKind::Data => &mut self.data_dir,
+            DirKind::DataLocal => &mut self.data_local_dir,
+            DirKind::Preference => &mut self.preference_dir,
+            DirKind::ProjectPath => &mut self.project_path,
+            DirKind::Runtime => &mut self.runtime_dir,
+            DirKind::State => &mut self.state_dir,
+            DirKind::Log => &mut self.log_dir,
+            DirKind::Executable => &mut self.executable_dir,
+        }
     }
 
-    fn project_path(&self) -> Option<&Path> {
-        self.project_path.as_deref()
+    /// The environment variable consulted for `kind`, which is `{PREFIX}{SUFFIX}` unless it was
+    /// renamed in `names`.
+    fn var_name(env_prefix: &str, names: &HashMap<DirKind, String>, kind: DirKind) -> String {
+        match names.get(&kind) {
+            Some(name) => name.clone(),
+            None => format!("{env_prefix}{}", kind.env_suffix()),
+        }
     }
 
-    fn runtime_dir(&self) -> Option<&Path> {
-        self.runtime_dir.as_deref()
+    /// Like [`Provider::new`], but consults the variables in `names` instead of the conventional
+    /// ones for the directories in it, looking them up with `var`. Paths are passed through
+    /// [`normalize_path`] on every platform if `normalize` is set.
+    fn named(
+        env_prefix: &str,
+        names: &HashMap<DirKind, String>,
+        normalize: bool,
+        var: impl Fn(&str) -> Option<OsString>,
+    ) -> Result<Self, EnvVarNotUnicodeError> {
+        let x = |kind: DirKind| {
+            let key = Self::var_name(env_prefix, names, kind);
+
+            var(&key)
+                .map(|value| {
+                    #[cfg(unix)]
+                    let path = env_path(value);
+                    #[cfg(not(unix))]
+                    let path = env_path(key, value)?;
+
+                    Ok::<_, EnvVarNotUnicodeError>(normalize_env_path(path, normalize))
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            cache_dir: x(DirKind::Cache)?,
+            config_dir: x(DirKind::Config)?,
+            config_local_dir: x(DirKind::ConfigLocal)?,
+            data_dir: x(DirKind::Data)?,
+            data_local_dir: x(DirKind::DataLocal)?,
+            preference_dir: x(DirKind::Preference)?,
+            project_path: x(DirKind::ProjectPath)?,
+            runtime_dir: x(DirKind::Runtime)?,
+            state_dir: x(DirKind::State)?,
+            log_dir: x(DirKind::Log)?,
+            executable_dir: x(DirKind::Executable)?,
+            site_config_dirs: var(&format!("{env_prefix}{SITE_CONFIG_DIRS_SUFFIX}"))
+                .map(|value| {
+                    parse_path_list(&value)
+                        .into_iter()
+                        .map(|path| normalize_env_path(path, normalize))
+                        .collect()
+                }),
+        })
     }
 
-    fn state_dir(&self) -> Option<&Path> {
-        self.state_dir.as_deref()
+    /// Takes each directory from `self`, or from `other` if it isn't set in `self`.
+    fn or(self, other: Self) -> Self {
+        Self {
+            cache_dir: self.cache_dir.or(other.cache_dir),
+            config_dir: self.config_dir.or(other.config_dir),
+            config_local_dir: self.config_local_dir.or(other.config_local_dir),
+            data_dir: self.data_dir.or(other.data_dir),
+            data_local_dir: self.data_local_dir.or(other.data_local_dir),
+            preference_dir: self.preference_dir.or(other.preference_dir),
+            project_path: self.project_path.or(other.project_path),
+            runtime_dir: self.runtime_dir.or(other.runtime_dir),
+            state_dir: self.state_dir.or(other.state_dir),
+            log_dir: self.log_dir.or(other.log_dir),
+            executable_dir: self.executable_dir.or(other.executable_dir),
+            site_config_dirs: self.site_config_dirs.or(other.site_config_dirs),
+        }
+    }
+
+    /// The directories which must all be set for the environment to be used on its own.
+    const REQUIRED: [DirKind; 7] = [
+        DirKind::Cache,
+        DirKind::Config,
+        DirKind::ConfigLocal,
+        DirKind::Data,
+        DirKind::DataLocal,
+        DirKind::Preference,
+        DirKind::ProjectPath,
+    ];
+
+    /// Returns the [required](Self::REQUIRED) directories which aren't set.
+    fn missing(&self) -> Vec<DirKind> {
+        Self::REQUIRED.into_iter().filter(|&kind| self.get(kind).is_none()).collect()
     }
-}
 
-impl Env {
     fn _parity(&mut self) -> Option<EnvParity> {
-        if self.cache_dir.is_none() || self.config_dir.is_none() || self.config_dir.is_none() 
-            || self.config_local_dir.is_none() || self.data_dir.is_none() 
-            || self.data_local_dir.is_none() || self.preference_dir.is_none() 
-            || self.project_path.is_none() {
+        if !self.missing().is_empty() {
             return None
         }
         
@@ -247,6 +752,9 @@ impl Env {
             project_path: self.project_path.take()?,
             runtime_dir: self.runtime_dir.take(),
             state_dir: self.state_dir.take(),
+            log_dir: self.log_dir.take(),
+            executable_dir: self.executable_dir.take(),
+            site_config_dirs: self.site_config_dirs.take(),
         })       
     }
    
@@ -269,17 +777,120 @@ struct EnvParity {
     project_path:     PathBuf,
     runtime_dir:      Option<PathBuf>,
     state_dir:        Option<PathBuf>,
+    log_dir:          Option<PathBuf>,
+    executable_dir:   Option<PathBuf>,
+    site_config_dirs: Option<Vec<PathBuf>>,
 }
 
 #[derive(Error, Debug)]
 pub enum InitializeError {
-    #[error("project dirs provider failed to initialize")]
+    #[error(transparent)]
     ProjectDirs(#[from] HomeDirNotFoundError),
 
     #[error("env provider failed to initialize")]
     Env(#[from] EnvVarNotUnicodeError),
+
+    #[error("env provider failed validation")]
+    Validation(#[from] ValidationError),
+
+    #[error("the env prefix '{0}' is invalid; it must consist of uppercase ASCII letters, digits and underscores, not start with a digit and not end with an underscore")]
+    InvalidPrefix(String),
+
+    #[error(transparent)]
+    PathsFile(#[from] PathsFileError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum DirKind {
+    Cache,
+    Config,
+    ConfigLocal,
+    Data,
+    DataLocal,
+    Preference,
+    ProjectPath,
+    Runtime,
+    State,
+    Log,
+    Executable,
+}
+
+impl DirKind {
+    pub const ALL: [Self; 11] = [
+        Self::Cache,
+        Self::Config,
+        Self::ConfigLocal,
+        Self::Data,
+        Self::DataLocal,
+        Self::Preference,
+        Self::ProjectPath,
+        Self::Runtime,
+        Self::State,
+        Self::Log,
+        Self::Executable,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cache => "Cache",
+            Self::Config => "Config",
+            Self::ConfigLocal => "Config (local)",
+            Self::Data => "Data",
+            Self::DataLocal => "Data (local)",
+            Self::Preference => "Preference",
+            Self::ProjectPath => "Project path",
+            Self::Runtime => "Runtime",
+            Self::State => "State",
+            Self::Log => "Log",
+            Self::Executable => "Executable",
+        }
+    }
+
+    /// The name of the accessor of this directory, e.g. `cache_dir`, which is also its key in
+    /// [`ProjectDirsOrEnv::as_map`].
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Cache => "cache_dir",
+            Self::Config => "config_dir",
+            Self::ConfigLocal => "config_local_dir",
+            Self::Data => "data_dir",
+            Self::DataLocal => "data_local_dir",
+            Self::Preference => "preference_dir",
+            Self::ProjectPath => "project_path",
+            Self::Runtime => "runtime_dir",
+            Self::State => "state_dir",
+            Self::Log => "log_dir",
+            Self::Executable => "executable_dir",
+        }
+    }
+
+    /// The suffix appended to the env prefix to form the variable overriding this directory.
+    pub fn env_suffix(self) -> &'static str {
+        match self {
+            Self::Cache => "_CACHE_DIR",
+            Self::Config => "_CONFIG_DIR",
+            Self::ConfigLocal => "_CONFIG_LOCAL_DIR",
+            Self::Data => "_DATA_DIR",
+            Self::DataLocal => "_DATA_LOCAL_DIR",
+            Self::Preference => "_PREFERENCE_DIR",
+            Self::ProjectPath => "_PROJECT_PATH",
+            Self::Runtime => "_RUNTIME_DIR",
+            Self::State => "_STATE_DIR",
+            Self::Log => "_LOG_DIR",
+            Self::Executable => "_EXECUTABLE_DIR",
+        }
+    }
+}
+
+impl fmt::Display for DirKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProjectDirsOrEnv {
     cache_dir:        PathBuf,
     config_dir:       PathBuf,
@@ -290,10 +901,39 @@ pub struct ProjectDirsOrEnv {
     project_path:     PathBuf,
     runtime_dir:      Option<PathBuf>,
     state_dir:        Option<PathBuf>,
+    log_dir:          PathBuf,
+    executable_dir:   Option<PathBuf>,
+    site_config_dirs: Vec<PathBuf>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    parity:           ParityReport,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    project_dirs:     Option<ProjectDirs>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    app_image:        Option<AppImage>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    init_snapshot:    InitSnapshot,
+}
+
+/// Describes whether the environment alone provided every required directory, or whether the
+/// platform defaults had to be used. See [`ProjectDirsOrEnv::parity_report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ParityReport {
+    /// The environment variables of the required directories which weren't set, and weren't
+    /// overridden either. Empty if the environment wasn't consulted at all.
+    pub missing: Vec<String>,
+
+    /// Whether the platform defaults were used, because a required directory was missing.
+    pub used_fallback: bool,
 }
 
-impl From<EnvParity> for ProjectDirsOrEnv {
-    fn from(value: EnvParity) -> Self {
+impl ProjectDirsOrEnv {
+    fn from_parity(app_name: &str, value: EnvParity) -> Self {
+        let log_dir = value.log_dir.unwrap_or_else(|| fallback_log_dir(value.state_dir.as_deref(), &value.data_local_dir));
+        let site_config_dirs = value
+            .site_config_dirs
+            .unwrap_or_else(|| default_site_config_dirs(site_dir_name(&value.project_path, app_name)));
+
         Self {
             cache_dir: value.cache_dir,
             config_dir: value.config_dir,
@@ -304,18 +944,176 @@ impl From<EnvParity> for ProjectDirsOrEnv {
             project_path: value.project_path,
             runtime_dir: value.runtime_dir,
             state_dir: value.state_dir,
+            log_dir,
+            executable_dir: value.executable_dir,
+            site_config_dirs,
+            parity: ParityReport::default(),
+            project_dirs: None,
+            app_image: None,
+            init_snapshot: InitSnapshot::default(),
         }
     }
 }
 
-impl ProjectDirsOrEnv {
-    pub fn new(app_name: &str, env_prefix: &str) -> Result<Self, InitializeError> {
-        let env = Env::new(env_prefix)?;
-        
-        match env.parity().map(Self::from) {
-            Ok(this) => Ok(this),
+/// The conventional log directory of the platform, if there is one, whether it exists or not:
+///
+/// * Linux and other unixes: `$XDG_STATE_HOME/<project>/logs`
+/// * macOS: `~/Library/Logs/<project>`
+/// * Windows: `{FOLDERID_LocalAppData}\<project>\Logs`
+#[cfg(target_os = "macos")]
+fn native_log_dir(project_dirs: &ProjectDirs) -> Option<PathBuf> {
+    let base_dirs = directories::BaseDirs::new()?;
+
+    Some(base_dirs.home_dir().join("Library").join("Logs").join(project_dirs.project_path()))
+}
+
+#[cfg(windows)]
+fn native_log_dir(project_dirs: &ProjectDirs) -> Option<PathBuf> {
+    let base_dirs = directories::BaseDirs::new()?;
+
+    Some(base_dirs.data_local_dir().join(project_dirs.project_path()).join("Logs"))
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn native_log_dir(project_dirs: &ProjectDirs) -> Option<PathBuf> {
+    project_dirs.state_dir().map(|state_dir| state_dir.join("logs"))
+}
+
+fn fallback_log_dir(state_dir: Option<&Path>, data_local_dir: &Path) -> PathBuf {
+    state_dir.unwrap_or(data_local_dir).join("logs")
+}
+
+/// The conventional per-user executable directory, which only exists on Linux
+/// (`$XDG_BIN_HOME` or `~/.local/bin`).
+fn default_executable_dir() -> Option<PathBuf> {
+    let base_dirs = directories::BaseDirs::new()?;
+
+    base_dirs.executable_dir().map(PathBuf::from)
+}
+
+const SITE_CONFIG_DIRS_SUFFIX: &str = "_SITE_CONFIG_DIRS";
+const PATHS_FILE_SUFFIX: &str = "_PATHS_FILE";
+
+/// Splits a list of paths separated by the platform's path separator (`:` on unix, `;` on
+/// windows), skipping empty entries.
+fn parse_path_list(value: &OsStr) -> Vec<PathBuf> {
+    env::split_paths(value).filter(|path| !path.as_os_str().is_empty()).collect()
+}
+
+/// Resolves the system-wide configuration directories from `$XDG_CONFIG_DIRS` (defaulting to
+/// `/etc/xdg`), followed by `/etc`, each with `project_path` appended. Relative entries are ignored
+/// as per the XDG base directory specification.
+#[cfg_attr(any(target_os = "macos", windows), allow(dead_code))]
+fn xdg_site_config_dirs(xdg_config_dirs: Option<&OsStr>, project_path: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = xdg_config_dirs
+        .map(parse_path_list)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|dir| dir.is_absolute())
+        .collect();
+
+    if dirs.is_empty() {
+        dirs.push(PathBuf::from("/etc/xdg"));
+    }
+
+    dirs.push(PathBuf::from("/etc"));
+    dirs.into_iter().map(|dir| dir.join(project_path)).collect()
+}
+
+/// The name of the directory of the app within the system-wide directories, which is the project
+/// path if it is a plain relative path, or the app name otherwise. A project path given by
+/// `{PREFIX}_PROJECT_PATH` may be absolute or climb up with `..`, and would then escape the
+/// system-wide directory it is joined onto.
+fn site_dir_name<'a>(project_path: &'a Path, app_name: &'a str) -> &'a Path {
+    let mut components = project_path.components().peekable();
+    let relative = components.peek().is_some() && components.all(|component| matches!(component, Component::Normal(_)));
+
+    if relative {
+        project_path
+    } else {
+        Path::new(app_name)
+    }
+}
+
+/// The system-wide configuration directories of the platform:
+///
+/// * Linux and other unixes: `$XDG_CONFIG_DIRS/<project>` (or `/etc/xdg/<project>`), then
+///   `/etc/<project>`
+/// * macOS: `/Library/Application Support/<project>`
+/// * Windows: `{FOLDERID_ProgramData}\<project>`
+#[cfg(target_os = "macos")]
+fn default_site_config_dirs(project_path: &Path) -> Vec<PathBuf> {
+    vec![Path::new("/Library/Application Support").join(project_path)]
+}
+
+#[cfg(windows)]
+fn default_site_config_dirs(project_path: &Path) -> Vec<PathBuf> {
+    let program_data = env::var_os("ProgramData").map_or_else(|| PathBuf::from(r"C:\ProgramData"), PathBuf::from);
+
+    vec![program_data.join(project_path)]
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn default_site_config_dirs(project_path: &Path) -> Vec<PathBuf> {
+    xdg_site_config_dirs(env::var_os("XDG_CONFIG_DIRS").as_deref(), project_path)
+}
+
+/// The [native log directory](native_log_dir) if it exists, or the [fallback](fallback_log_dir)
+/// otherwise, which is the same on Linux.
+fn default_log_dir(project_dirs: &ProjectDirs) -> PathBuf {
+    native_log_dir(project_dirs)
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(|| fallback_log_dir(project_dirs.state_dir(), project_dirs.data_local_dir()))
+}
+
+fn env_vars(env_prefix: &str, names: &HashMap<DirKind, String>) -> Vec<String> {
+    DirKind::ALL
+        .into_iter()
+        .map(|kind| Env::var_name(env_prefix, names, kind))
+        .chain([format!("{env_prefix}{SITE_CONFIG_DIRS_SUFFIX}"), format!("{env_prefix}{PATHS_FILE_SUFFIX}")])
+        .collect()
+}
+
+impl ProjectDirsOrEnv {
+    pub fn new(app_name: &str, env_prefix: &str) -> Result<Self, InitializeError> {
+        Self::builder(app_name).env_prefix(env_prefix).build()
+    }
+
+    /// Like [`new`](Self::new), but looks the variables up in `vars` instead of the environment,
+    /// e.g. to source them from the config of the application, or to test without touching the
+    /// environment. See [`ProjectDirsOrEnvBuilder::vars`].
+    pub fn from_vars(
+        app_name: &str,
+        env_prefix: &str,
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+    ) -> Result<Self, InitializeError> {
+        Self::builder(app_name).env_prefix(env_prefix).vars(vars).build()
+    }
+
+    /// Like [`new`](Self::new), but with directories given e.g. as command line flags, which take
+    /// precedence over the environment. See [`ProjectDirsOrEnvBuilder::overrides`].
+    pub fn with_overrides(
+        app_name: &str,
+        env_prefix: &str,
+        overrides: impl IntoIterator<Item = (DirKind, PathBuf)>,
+    ) -> Result<Self, InitializeError> {
+        Self::builder(app_name).env_prefix(env_prefix).overrides(overrides).build()
+    }
+
+    pub fn builder(app_name: &str) -> ProjectDirsOrEnvBuilder {
+        ProjectDirsOrEnvBuilder::new(app_name)
+    }
+
+    fn resolve(app_name: &str, env: Env) -> Result<Self, InitializeError> {
+        match env.parity().map(|parity| Self::from_parity(app_name, parity)) {
+            Ok(this) => Ok(this),
             Err(env) => {
                 let project_dirs = ProjectDirs::new(app_name)?;
+                let log_dir = env.log_dir.unwrap_or_else(|| default_log_dir(&project_dirs));
+                let project_path = env.project_path.unwrap_or(PathBuf::from(project_dirs.project_path()));
+                let site_config_dirs = env
+                    .site_config_dirs
+                    .unwrap_or_else(|| default_site_config_dirs(site_dir_name(&project_path, app_name)));
 
                 Ok(Self {
                     cache_dir: env.cache_dir.unwrap_or(PathBuf::from(project_dirs.cache_dir())),
@@ -324,15 +1122,56 @@ impl ProjectDirsOrEnv {
                     data_dir: env.data_dir.unwrap_or(PathBuf::from(project_dirs.data_dir())),
                     data_local_dir: env.data_local_dir.unwrap_or(PathBuf::from(project_dirs.data_local_dir())),
                     preference_dir: env.preference_dir.unwrap_or(PathBuf::from(project_dirs.preference_dir())),
-                    project_path: env.project_path.unwrap_or(PathBuf::from(project_dirs.project_path())),
-                    runtime_dir: None,
-                    state_dir: None,
+                    project_path,
+                    runtime_dir: env.runtime_dir.or_else(|| project_dirs.runtime_dir().map(PathBuf::from)),
+                    state_dir: env.state_dir.or_else(|| project_dirs.state_dir().map(PathBuf::from)),
+                    log_dir,
+                    executable_dir: env.executable_dir.or_else(default_executable_dir),
+                    site_config_dirs,
+                    parity: ParityReport { missing: Vec::new(), used_fallback: true },
+                    project_dirs: Some(project_dirs),
+                    app_image: None,
+                    init_snapshot: InitSnapshot::default(),
                 })               
             }
         }
     }
 }
 
+impl ProjectDirsOrEnv {
+    /// Canonicalizes every resolved directory, resolving symlinks and normalizing components.
+    /// Directories which can't be canonicalized, usually because they don't exist yet, are left
+    /// untouched. The project path is a relative fragment rather than a directory, and is left
+    /// untouched as well.
+    ///
+    /// This changes the returned paths, so it isn't done by default. With the [`location!`]
+    /// macro, opt in by putting `#[canonicalize]` before the module declaration.
+    pub fn canonicalized(self) -> Self {
+        fn canonicalize(path: PathBuf) -> PathBuf {
+            std::fs::canonicalize(&path).unwrap_or(path)
+        }
+
+        Self {
+            cache_dir: canonicalize(self.cache_dir),
+            config_dir: canonicalize(self.config_dir),
+            config_local_dir: canonicalize(self.config_local_dir),
+            data_dir: canonicalize(self.data_dir),
+            data_local_dir: canonicalize(self.data_local_dir),
+            preference_dir: canonicalize(self.preference_dir),
+            project_path: self.project_path,
+            runtime_dir: self.runtime_dir.map(canonicalize),
+            state_dir: self.state_dir.map(canonicalize),
+            log_dir: canonicalize(self.log_dir),
+            executable_dir: self.executable_dir.map(canonicalize),
+            site_config_dirs: self.site_config_dirs.into_iter().map(canonicalize).collect(),
+            parity: self.parity,
+            project_dirs: self.project_dirs,
+            app_image: self.app_image,
+            init_snapshot: self.init_snapshot,
+        }
+    }
+}
+
 impl ProjectDirsOrEnv {
     pub fn cache_dir(&self) -> &Path {
         &self.cache_dir
@@ -369,4 +1208,837 @@ impl ProjectDirsOrEnv {
     pub fn state_dir(&self) -> Option<&Path> {
         self.state_dir.as_deref()
     }
+
+    pub fn log_dir(&self) -> &Path {
+        &self.log_dir
+    }
+
+    pub fn executable_dir(&self) -> Option<&Path> {
+        self.executable_dir.as_deref()
+    }
+
+    /// System-wide configuration directories, in order of precedence. These are typically
+    /// provided by administrators and should be read before the user's [`config_dir`].
+    ///
+    /// Overridden by `{PREFIX}_SITE_CONFIG_DIRS`, a list of paths separated by `:` on unix and
+    /// `;` on windows.
+    ///
+    /// [`config_dir`]: Self::config_dir
+    pub fn site_config_dirs(&self) -> &[PathBuf] {
+        &self.site_config_dirs
+    }
+
+    /// Returns whether the environment alone provided every required directory, and which
+    /// variables were missing if it didn't.
+    pub fn parity_report(&self) -> &ParityReport {
+        &self.parity
+    }
+
+    /// Returns the environment variables consulted while initializing, with the values observed,
+    /// and whether the platform defaults were used, e.g. to attach to bug reports.
+    pub fn init_snapshot(&self) -> &InitSnapshot {
+        &self.init_snapshot
+    }
+
+    /// Returns the platform defaults if they were consulted, i.e. if the environment and the
+    /// overrides didn't provide every required directory, e.g. to pass them on to another library.
+    /// The version of `directories` they come from is re-exported as [`directories`].
+    pub fn project_dirs(&self) -> Option<&ProjectDirs> {
+        self.project_dirs.as_ref()
+    }
+
+    /// Returns the AppImage the app runs as, if the environment was consulted and says so. See
+    /// [`Relocation::BesideAppImage`] to keep the directories next to it.
+    pub fn app_image(&self) -> Option<&AppImage> {
+        self.app_image.as_ref()
+    }
+
+    /// Returns the names of every environment variable consulted for the given env prefix. See
+    /// [`ProjectDirsOrEnvBuilder::env_vars`] when some of them are renamed.
+    pub fn env_vars(env_prefix: &str) -> Vec<String> {
+        env_vars(env_prefix, &HashMap::new())
+    }
+
+    pub fn get(&self, kind: DirKind) -> Option<&Path> {
+        match kind {
+            DirKind::Cache => Some(self.cache_dir()),
+            DirKind::Config => Some(self.config_dir()),
+            DirKind::ConfigLocal => Some(self.config_local_dir()),
+            DirKind::Data => Some(self.data_dir()),
+            DirKind::DataLocal => Some(self.data_local_dir()),
+            DirKind::Preference => Some(self.preference_dir()),
+            DirKind::ProjectPath => Some(self.project_path()),
+            DirKind::Runtime => self.runtime_dir(),
+            DirKind::State => self.state_dir(),
+            DirKind::Log => Some(self.log_dir()),
+            DirKind::Executable => self.executable_dir(),
+        }
+    }
+
+    /// Iterates over every directory which is set, skipping unset optional directories.
+    pub fn iter(&self) -> impl Iterator<Item = (DirKind, &Path)> + '_ {
+        DirKind::ALL.into_iter().filter_map(|kind| self.get(kind).map(|path| (kind, path)))
+    }
+
+    /// Returns `(name, path)` rows for every directory, suitable for printing as a table. Unset
+    /// optional directories are marked as `(unset)`.
+    pub fn table_rows(&self) -> Vec<(String, String)> {
+        DirKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let path = match self.get(kind) {
+                    Some(path) => path.display().to_string(),
+                    None => String::from("(unset)"),
+                };
+
+                (kind.name().to_owned(), path)
+            })
+            .collect()
+    }
+
+    /// Returns every directory keyed by the name of its accessor (see [`DirKind::key`]), e.g. for
+    /// filling `{{data_dir}}` placeholders in templates. Unset optional directories are `None`.
+    pub fn as_map(&self) -> BTreeMap<&'static str, Option<&Path>> {
+        DirKind::ALL.into_iter().map(|kind| (kind.key(), self.get(kind))).collect()
+    }
+
+    /// Like [`as_map`](Self::as_map), but with the paths converted to strings lossily.
+    pub fn as_string_map(&self) -> BTreeMap<&'static str, Option<String>> {
+        DirKind::ALL
+            .into_iter()
+            .map(|kind| (kind.key(), self.get(kind).map(|path| path.to_string_lossy().into_owned())))
+            .collect()
+    }
+}
+
+/// Modules generated by [`location!`], public so that the functions the tests don't use aren't dead
+/// code.
+#[cfg(test)]
+pub mod fixture {
+    crate::location! {
+        pub mod dirs;
+        "ALPTK_LOCATION_RESET_TEST";
+
+        logs_dir = |provider| provider.state_dir().unwrap().join("logs");
+        plugins_dir = data_dir / "plugins";
+        themes_dir = config_dir / "ui" / "themes";
+    }
+
+    crate::location! {
+        #[canonicalize]
+        pub mod canonical_dirs;
+        "ALPTK_LOCATION_CANONICAL_TEST";
+    }
+
+    crate::location! {
+        pub mod adjusted_dirs;
+        "ALPTK_LOCATION_ADJUST_TEST";
+    }
+
+    crate::location! {
+        pub mod overridden_dirs;
+        "ALPTK_LOCATION_OVERRIDE_TEST";
+    }
+
+    crate::location! {
+        pub mod legacy_dirs;
+        "ALPTK_LOCATION_LEGACY_TEST";
+
+        env config_dir = "ALPTK_LOCATION_LEGACY_CONF";
+        plugins_dir = config_dir / "plugins";
+    }
+
+    crate::location! {
+        pub mod derived_dirs;
+
+        plugins_dir = data_dir / "plugins";
+    }
+
+    crate::location! {
+        pub mod shared_dirs;
+        "ALPTK_LOCATION_SHARED_TEST";
+    }
+
+    crate::location! {
+        pub mod also_shared_dirs;
+        "ALPTK_LOCATION_SHARED_TEST";
+    }
+
+    pub mod inline_dirs {
+        const PLUGINS: &str = "plugins";
+
+        crate::location! {
+            inline;
+            "ALPTK_LOCATION_INLINE_TEST";
+
+            plugins_dir = |provider| provider.data_dir().join(PLUGINS);
+        }
+    }
+
+    pub mod other_inline_dirs {
+        crate::location! {
+            #[canonicalize]
+            inline;
+            "ALPTK_LOCATION_OTHER_INLINE_TEST";
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::ffi::{OsStr, OsString};
+    use std::path::{Path, PathBuf};
+    use directories::ProjectDirs;
+    use crate::{
+        default_executable_dir, default_log_dir, fallback_log_dir, normalize_windows_path, parse_path_list,
+        xdg_site_config_dirs, DirKind, HomeDirNotFoundError, InitSnapshot, InitializeError, Locations,
+        ParityReport, ProjectDirsOrEnv,
+    };
+    use crate::fixture::dirs;
+
+    fn set_env_layout(root: &str) {
+        for (suffix, dir) in [
+            ("CACHE_DIR", "cache"),
+            ("CONFIG_DIR", "config"),
+            ("CONFIG_LOCAL_DIR", "config"),
+            ("DATA_DIR", "data"),
+            ("DATA_LOCAL_DIR", "data"),
+            ("PREFERENCE_DIR", "config"),
+            ("PROJECT_PATH", "project"),
+            ("RUNTIME_DIR", "run"),
+            ("STATE_DIR", "state"),
+        ] {
+            env::set_var(format!("ALPTK_LOCATION_RESET_TEST_{suffix}"), format!("{root}/{dir}"));
+        }
+    }
+
+    #[test]
+    fn reset_allows_reinitialization() {
+        set_env_layout("/first");
+        dirs::initialize().unwrap();
+        let first_config_dir = dirs::config_dir();
+
+        assert_eq!(first_config_dir, Path::new("/first/config"));
+        assert_eq!(dirs::logs_dir(), Path::new("/first/state/logs"));
+        assert_eq!(dirs::log_dir(), Path::new("/first/state/logs"));
+        assert_eq!(dirs::plugins_dir(), Path::new("/first/data/plugins"));
+        assert_eq!(dirs::themes_dir(), Path::new("/first/config/ui/themes"));
+
+        dirs::reset();
+        set_env_layout("/second");
+        dirs::initialize().unwrap();
+
+        assert_eq!(dirs::config_dir(), Path::new("/second/config"));
+        assert_eq!(dirs::logs_dir(), Path::new("/second/state/logs"));
+        assert_eq!(first_config_dir, Path::new("/first/config"));
+
+        dirs::reset();
+    }
+
+    #[test]
+    fn initialize_with_adjusts_before_freezing() {
+        use crate::fixture::adjusted_dirs;
+
+        let resolved = ProjectDirsOrEnv::new(env!("CARGO_PKG_NAME"), "ALPTK_LOCATION_ADJUST_TEST").unwrap();
+        adjusted_dirs::initialize_with(|dirs| {
+            let data_dir = dirs.data_dir().join("host-a");
+            *dirs.data_dir_mut() = data_dir;
+        }).unwrap();
+
+        assert_eq!(adjusted_dirs::data_dir(), resolved.data_dir().join("host-a"));
+        assert_eq!(adjusted_dirs::cache_dir(), resolved.cache_dir());
+
+        adjusted_dirs::reset();
+    }
+
+    #[test]
+    fn initialize_with_overrides_beats_env() {
+        use crate::fixture::overridden_dirs;
+
+        env::set_var("ALPTK_LOCATION_OVERRIDE_TEST_CONFIG_DIR", "/env/config");
+        env::set_var("ALPTK_LOCATION_OVERRIDE_TEST_DATA_DIR", "/env/data");
+        overridden_dirs::initialize_with_overrides([(DirKind::Config, PathBuf::from("/flag/config"))]).unwrap();
+
+        assert_eq!(overridden_dirs::config_dir(), Path::new("/flag/config"));
+        assert_eq!(overridden_dirs::data_dir(), Path::new("/env/data"));
+
+        overridden_dirs::reset();
+    }
+
+    #[test]
+    fn env_prefix_is_derived_from_package_name() {
+        use crate::fixture::derived_dirs;
+
+        assert_eq!(crate::__private::derive_env_prefix("my-app_cli"), "MY_APP_CLI");
+        assert_eq!(derived_dirs::env_prefix(), "ALPTK_LOCATION");
+        assert_eq!(crate::fixture::dirs::env_prefix(), "ALPTK_LOCATION_RESET_TEST");
+
+        env::set_var("ALPTK_LOCATION_DATA_DIR", "/derived/data");
+        derived_dirs::initialize().unwrap();
+
+        assert_eq!(derived_dirs::plugins_dir(), Path::new("/derived/data/plugins"));
+        assert!(derived_dirs::env_vars().contains(&"ALPTK_LOCATION_CONFIG_DIR".to_owned()));
+
+        derived_dirs::reset();
+    }
+
+    #[test]
+    fn inline_expansion() {
+        use crate::fixture::{inline_dirs, other_inline_dirs};
+
+        env::set_var("ALPTK_LOCATION_INLINE_TEST_DATA_DIR", "/inline/data");
+        inline_dirs::initialize().unwrap();
+
+        assert_eq!(inline_dirs::data_dir(), Path::new("/inline/data"));
+        assert_eq!(inline_dirs::plugins_dir(), Path::new("/inline/data/plugins"));
+        assert_eq!(other_inline_dirs::env_prefix(), "ALPTK_LOCATION_OTHER_INLINE_TEST");
+
+        inline_dirs::reset();
+    }
+
+    #[test]
+    fn modules_with_the_same_key_share_a_layout() {
+        use crate::fixture::{also_shared_dirs, shared_dirs};
+
+        env::set_var("ALPTK_LOCATION_SHARED_TEST_CONFIG_DIR", "/first/config");
+        shared_dirs::initialize().unwrap();
+        env::set_var("ALPTK_LOCATION_SHARED_TEST_CONFIG_DIR", "/second/config");
+        also_shared_dirs::initialize().unwrap();
+
+        assert!(std::ptr::eq(shared_dirs::config_dir(), also_shared_dirs::config_dir()));
+        assert_eq!(also_shared_dirs::config_dir(), Path::new("/first/config"));
+
+        // a layout of its own is resolved, and the registered one is kept for the others
+        also_shared_dirs::reset();
+        crate::registry::register("ALPTK_LOCATION_SHARED_TEST", shared_dirs::locations()).unwrap();
+        also_shared_dirs::initialize_with(|_| {}).unwrap();
+
+        assert!(!std::ptr::eq(shared_dirs::config_dir(), also_shared_dirs::config_dir()));
+        assert_eq!(also_shared_dirs::config_dir(), Path::new("/second/config"));
+        assert!(std::ptr::eq(crate::registry::get("ALPTK_LOCATION_SHARED_TEST").unwrap().config_dir(), shared_dirs::config_dir()));
+
+        // layouts are keyed by the env prefix alone, so one registered by another app name is shared
+        shared_dirs::reset();
+        also_shared_dirs::reset();
+        env::set_var("ALPTK_LOCATION_SHARED_TEST_CONFIG_DIR", "/third/config");
+        crate::registry::register("ALPTK_LOCATION_SHARED_TEST", Locations::new("other-app", "ALPTK_LOCATION_SHARED_TEST").unwrap()).unwrap();
+        shared_dirs::initialize().unwrap();
+
+        assert_eq!(shared_dirs::config_dir(), Path::new("/third/config"));
+        assert!(std::ptr::eq(crate::registry::get("ALPTK_LOCATION_SHARED_TEST").unwrap().config_dir(), shared_dirs::config_dir()));
+
+        shared_dirs::reset();
+        also_shared_dirs::reset();
+    }
+
+    #[test]
+    fn renamed_env_var_is_honored() {
+        use crate::fixture::legacy_dirs;
+
+        env::set_var("ALPTK_LOCATION_LEGACY_CONF", "/legacy/config");
+        legacy_dirs::initialize().unwrap();
+
+        assert_eq!(legacy_dirs::config_dir(), Path::new("/legacy/config"));
+        assert_eq!(legacy_dirs::plugins_dir(), Path::new("/legacy/config/plugins"));
+        assert!(legacy_dirs::env_vars().contains(&"ALPTK_LOCATION_LEGACY_CONF".to_owned()));
+        assert!(!legacy_dirs::env_vars().contains(&"ALPTK_LOCATION_LEGACY_TEST_CONFIG_DIR".to_owned()));
+
+        legacy_dirs::reset();
+    }
+
+    pub(crate) fn layout(optional: bool) -> ProjectDirsOrEnv {
+        ProjectDirsOrEnv {
+            cache_dir: PathBuf::from("/home/x/.cache/app"),
+            config_dir: PathBuf::from("/home/x/.config/app"),
+            config_local_dir: PathBuf::from("/home/x/.config/app"),
+            data_dir: PathBuf::from("/home/x/.local/share/app"),
+            data_local_dir: PathBuf::from("/home/x/.local/share/app"),
+            preference_dir: PathBuf::from("/home/x/.config/app"),
+            project_path: PathBuf::from("app"),
+            runtime_dir: optional.then(|| PathBuf::from("/run/user/1000/app")),
+            state_dir: optional.then(|| PathBuf::from("/home/x/.local/state/app")),
+            log_dir: PathBuf::from("/home/x/.local/state/app/logs"),
+            executable_dir: optional.then(|| PathBuf::from("/home/x/.local/bin")),
+            site_config_dirs: vec![PathBuf::from("/etc/xdg/app"), PathBuf::from("/etc/app")],
+            parity: ParityReport::default(),
+            project_dirs: None,
+            app_image: None,
+            init_snapshot: InitSnapshot::default(),
+        }
+    }
+
+    #[test]
+    fn parity_report() {
+        let prefix = "ALPTK_LOCATION_PARITY_TEST";
+        let required = ["CACHE_DIR", "CONFIG_DIR", "CONFIG_LOCAL_DIR", "DATA_DIR", "DATA_LOCAL_DIR", "PREFERENCE_DIR", "PROJECT_PATH"];
+
+        // no vars set
+        let report = ProjectDirsOrEnv::new("app", prefix).unwrap().parity_report().clone();
+
+        assert!(report.used_fallback);
+        assert_eq!(report.missing, required.map(|suffix| format!("{prefix}_{suffix}")));
+
+        // full parity
+        for suffix in required {
+            env::set_var(format!("{prefix}_{suffix}"), format!("/env/{suffix}"));
+        }
+        let report = ProjectDirsOrEnv::new("app", prefix).unwrap().parity_report().clone();
+
+        assert_eq!(report, ParityReport { missing: Vec::new(), used_fallback: false });
+
+        // one missing var
+        env::remove_var(format!("{prefix}_DATA_LOCAL_DIR"));
+        let report = ProjectDirsOrEnv::new("app", prefix).unwrap().parity_report().clone();
+
+        assert_eq!(report, ParityReport { missing: vec![format!("{prefix}_DATA_LOCAL_DIR")], used_fallback: true });
+
+        // which an override makes up for
+        let report = ProjectDirsOrEnv::builder("app")
+            .env_prefix(prefix)
+            .override_data_local_dir("/flag/data")
+            .build()
+            .unwrap()
+            .parity_report()
+            .clone();
+
+        assert_eq!(report, ParityReport { missing: Vec::new(), used_fallback: false });
+    }
+
+    fn vars(prefix: &str, suffixes: &[&str]) -> Vec<(OsString, OsString)> {
+        suffixes
+            .iter()
+            .map(|suffix| (format!("{prefix}_{suffix}").into(), format!("/vars/{suffix}").into()))
+            .collect()
+    }
+
+    #[test]
+    fn from_vars_full_parity() {
+        // never set in the environment
+        let prefix = "ALPTK_LOCATION_FROM_VARS_TEST";
+        let required = ["CACHE_DIR", "CONFIG_DIR", "CONFIG_LOCAL_DIR", "DATA_DIR", "DATA_LOCAL_DIR", "PREFERENCE_DIR", "PROJECT_PATH"];
+        let this = ProjectDirsOrEnv::from_vars("app", prefix, vars(prefix, &required)).unwrap();
+
+        assert_eq!(this.parity_report(), &ParityReport { missing: Vec::new(), used_fallback: false });
+        assert_eq!(this.config_dir(), Path::new("/vars/CONFIG_DIR"));
+        assert_eq!(this.log_dir(), Path::new("/vars/DATA_LOCAL_DIR/logs"));
+        assert!(env::var_os(format!("{prefix}_CONFIG_DIR")).is_none());
+    }
+
+    #[test]
+    fn from_vars_partial_parity() {
+        let prefix = "ALPTK_LOCATION_FROM_VARS_TEST";
+        let this = ProjectDirsOrEnv::from_vars("app", prefix, vars(prefix, &["DATA_DIR", "STATE_DIR"])).unwrap();
+
+        assert!(this.parity_report().used_fallback);
+        assert!(this.parity_report().missing.contains(&format!("{prefix}_CONFIG_DIR")));
+        assert_eq!(this.data_dir(), Path::new("/vars/DATA_DIR"));
+        assert_eq!(this.state_dir(), Some(Path::new("/vars/STATE_DIR")));
+        assert_eq!(this.config_dir(), ProjectDirs::from("", "ALinuxPerson", "app").unwrap().config_dir());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn from_vars_accepts_non_unicode_values() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let prefix = "ALPTK_LOCATION_FROM_VARS_TEST";
+        let value = OsString::from_vec(b"/vars/\xff".to_vec());
+        let this = ProjectDirsOrEnv::from_vars("app", prefix, [(format!("{prefix}_DATA_DIR").into(), value.clone())]).unwrap();
+
+        assert_eq!(this.data_dir().as_os_str(), value);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn from_vars_rejects_non_unicode_values() {
+        use std::os::windows::ffi::OsStringExt;
+        use crate::InitializeError;
+
+        let prefix = "ALPTK_LOCATION_FROM_VARS_TEST";
+        // an unpaired surrogate
+        let vars = [(format!("{prefix}_DATA_DIR").into(), OsString::from_wide(&[0xD800]))];
+        let error = ProjectDirsOrEnv::from_vars("app", prefix, vars).err().unwrap();
+
+        let InitializeError::Env(error) = error else { panic!("{error}") };
+        assert_eq!(error.name, format!("{prefix}_DATA_DIR"));
+    }
+
+    #[test]
+    fn project_dirs_kept_only_when_consulted() {
+        let prefix = "ALPTK_LOCATION_PROJECT_DIRS_TEST";
+
+        // fallback
+        let this = ProjectDirsOrEnv::new("app", prefix).unwrap();
+
+        assert_eq!(this.project_dirs().unwrap().config_dir(), ProjectDirs::from("", "ALinuxPerson", "app").unwrap().config_dir());
+
+        // env parity
+        for suffix in ["CACHE_DIR", "CONFIG_DIR", "CONFIG_LOCAL_DIR", "DATA_DIR", "DATA_LOCAL_DIR", "PREFERENCE_DIR", "PROJECT_PATH"] {
+            env::set_var(format!("{prefix}_{suffix}"), format!("/env/{suffix}"));
+        }
+
+        assert!(ProjectDirsOrEnv::new("app", prefix).unwrap().project_dirs().is_none());
+
+        // everything overridden by a builder
+        let this = DirKind::ALL
+            .into_iter()
+            .fold(ProjectDirsOrEnv::builder("app"), |builder, kind| builder.override_dir(kind, "/flag"))
+            .build()
+            .unwrap();
+
+        assert!(this.project_dirs().is_none());
+        assert!(layout(false).project_dirs().is_none());
+    }
+
+    #[test]
+    fn home_dir_not_found_message() {
+        let error = InitializeError::from(HomeDirNotFoundError {
+            app_name: "myapp".to_owned(),
+            env_prefix: Some("MYAPP".to_owned()),
+            missing_vars: vec!["MYAPP_CACHE_DIR".to_owned(), "MYAPP_PROJECT_PATH".to_owned()],
+        });
+
+        assert_eq!(
+            error.to_string(),
+            "the home directory could not be found for the app 'myapp'; set MYAPP_CACHE_DIR, MYAPP_PROJECT_PATH to run without it",
+        );
+
+        let error = HomeDirNotFoundError { app_name: "myapp".to_owned(), env_prefix: None, missing_vars: Vec::new() };
+
+        assert_eq!(error.to_string(), "the home directory could not be found for the app 'myapp'");
+    }
+
+    #[test]
+    fn env_vars_lists_log_dir() {
+        let vars = ProjectDirsOrEnv::env_vars("APP");
+
+        assert_eq!(vars.len(), DirKind::ALL.len() + 2);
+        assert!(vars.contains(&"APP_CACHE_DIR".to_owned()));
+        assert!(vars.contains(&"APP_LOG_DIR".to_owned()));
+        assert!(vars.contains(&"APP_SITE_CONFIG_DIRS".to_owned()));
+        assert!(vars.contains(&"APP_PATHS_FILE".to_owned()));
+    }
+
+    #[test]
+    fn log_dir_falls_back_to_state_then_data_local() {
+        assert_eq!(
+            fallback_log_dir(Some(Path::new("/state")), Path::new("/data")),
+            Path::new("/state/logs"),
+        );
+        assert_eq!(fallback_log_dir(None, Path::new("/data")), Path::new("/data/logs"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn default_log_dir_linux() {
+        let project_dirs = ProjectDirs::from("", "ALinuxPerson", "app").unwrap();
+
+        assert_eq!(default_log_dir(&project_dirs), project_dirs.state_dir().unwrap().join("logs"));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn default_log_dir_macos() {
+        let project_dirs = ProjectDirs::from("", "ALinuxPerson", "app").unwrap();
+        let log_dir = crate::native_log_dir(&project_dirs).unwrap();
+
+        assert!(log_dir.ends_with(Path::new("Library/Logs").join(project_dirs.project_path())));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn default_log_dir_windows() {
+        let project_dirs = ProjectDirs::from("", "ALinuxPerson", "app").unwrap();
+        let log_dir = crate::native_log_dir(&project_dirs).unwrap();
+
+        assert!(log_dir.ends_with(project_dirs.project_path().join("Logs")));
+    }
+
+    #[cfg(any(target_os = "macos", windows))]
+    #[test]
+    fn default_log_dir_falls_back_if_the_native_one_is_missing() {
+        let project_dirs = ProjectDirs::from("", "ALinuxPerson", &format!("alptk-location-missing-{}", std::process::id())).unwrap();
+
+        assert!(!crate::native_log_dir(&project_dirs).unwrap().exists());
+        assert_eq!(
+            default_log_dir(&project_dirs),
+            fallback_log_dir(project_dirs.state_dir(), project_dirs.data_local_dir())
+        );
+    }
+
+    #[test]
+    fn executable_dir_env_override() {
+        env::set_var("ALPTK_LOCATION_EXECUTABLE_TEST_EXECUTABLE_DIR", "/opt/app/bin");
+        let this = ProjectDirsOrEnv::new("app", "ALPTK_LOCATION_EXECUTABLE_TEST").unwrap();
+
+        assert_eq!(this.executable_dir(), Some(Path::new("/opt/app/bin")));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn default_executable_dir_linux() {
+        let executable_dir = default_executable_dir().unwrap();
+
+        match env::var_os("XDG_BIN_HOME") {
+            Some(bin_home) => assert_eq!(executable_dir, Path::new(&bin_home)),
+            None => assert!(executable_dir.ends_with(".local/bin")),
+        }
+    }
+
+    #[test]
+    fn normalize_windows_path_shapes() {
+        for (raw, normalized) in [
+            (r"C:\Users\me\app", r"C:\Users\me\app"),
+            ("C:/Users/me/app", r"C:\Users\me\app"),
+            (r"C:\Users/me\app/", r"C:\Users\me\app"),
+            (r"C:\Users\me\app\\", r"C:\Users\me\app"),
+            (r"C:\", r"C:\"),
+            ("C:/", r"C:\"),
+            (r"\\?\C:\Users\me\app\", r"C:\Users\me\app"),
+            (r"\\?\UNC\server\share\app", r"\\server\share\app"),
+            (r"\\server\share\", r"\\server\share"),
+            ("//server/share/app/", r"\\server\share\app"),
+            (r"\\?\Volume{1234}\app/", r"\\?\Volume{1234}\app/"),
+            (r"relative\app\", r"relative\app"),
+            (r"\", r"\"),
+            (r"C:\Users\.\me\\app", r"C:\Users\me\app"),
+            ("C://Users/./me/../app/.", r"C:\Users\me\..\app"),
+            (r"C:\.\", r"C:\"),
+            (r"\\server\share\.\\app", r"\\server\share\app"),
+            (r".\app\", "app"),
+            (r".\.", "."),
+            (r"..\app", r"..\app"),
+        ] {
+            assert_eq!(normalize_windows_path(raw), normalized, "{raw}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn normalize_path_shapes() {
+        for (raw, normalized) in [
+            ("/home/me/.config/app", "/home/me/.config/app"),
+            ("/home/me/.config/app/", "/home/me/.config/app"),
+            ("/home/me/./.config/app", "/home/me/.config/app"),
+            ("/home//me///.config/app//", "/home/me/.config/app"),
+            ("/home/me/../you/./app", "/home/me/../you/app"),
+            ("/", "/"),
+            ("/./", "/"),
+            ("./app/", "app"),
+            ("relative//app/.", "relative/app"),
+            ("./.", "."),
+            ("../app", "../app"),
+        ] {
+            assert_eq!(crate::normalize_path(raw), Path::new(normalized), "{raw}");
+        }
+    }
+
+    #[test]
+    fn vars_are_normalized_unless_disabled() {
+        let prefix = "ALPTK_LOCATION_NORMALIZE_VARS_TEST";
+        let builder = || ProjectDirsOrEnv::builder("app")
+            .env_prefix(prefix)
+            .vars([(format!("{prefix}_CONFIG_DIR").into(), "/etc/./app//".into())]);
+
+        assert_eq!(builder().build().unwrap().config_dir(), Path::new("/etc/app"));
+        assert_eq!(builder().normalize_paths(false).build().unwrap().config_dir().as_os_str(), "/etc/./app//");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn env_paths_are_normalized_unless_disabled() {
+        env::set_var("ALPTK_LOCATION_NORMALIZE_TEST_DATA_DIR", r"\\?\C:/data/app\");
+        let builder = || ProjectDirsOrEnv::builder("app").env_prefix("ALPTK_LOCATION_NORMALIZE_TEST");
+
+        assert_eq!(builder().build().unwrap().data_dir(), Path::new(r"C:\data\app"));
+        assert_eq!(
+            builder().normalize_paths(false).build().unwrap().data_dir(),
+            Path::new(r"\\?\C:/data/app\"),
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_env_values_are_accepted() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let data_dir = OsStr::from_bytes(b"/srv/caf\xe9");
+        env::set_var("ALPTK_LOCATION_NON_UNICODE_TEST_DATA_DIR", data_dir);
+        let this = ProjectDirsOrEnv::new("app", "ALPTK_LOCATION_NON_UNICODE_TEST").unwrap();
+
+        assert_eq!(this.data_dir(), Path::new(data_dir));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    #[test]
+    fn default_executable_dir_none() {
+        assert_eq!(default_executable_dir(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parse_path_list_splits_and_skips_empty() {
+        assert_eq!(
+            parse_path_list(OsStr::new("/etc/app::/opt/app/etc:")),
+            [PathBuf::from("/etc/app"), PathBuf::from("/opt/app/etc")],
+        );
+        assert!(parse_path_list(OsStr::new("")).is_empty());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn parse_path_list_splits_and_skips_empty() {
+        assert_eq!(
+            parse_path_list(OsStr::new(r"C:\ProgramData\app;;D:\app;")),
+            [PathBuf::from(r"C:\ProgramData\app"), PathBuf::from(r"D:\app")],
+        );
+    }
+
+    #[test]
+    fn xdg_site_config_dirs_appends_project_path() {
+        assert_eq!(
+            xdg_site_config_dirs(None, Path::new("app")),
+            [PathBuf::from("/etc/xdg/app"), PathBuf::from("/etc/app")],
+        );
+        assert_eq!(
+            xdg_site_config_dirs(Some(OsStr::new("/usr/local/etc/xdg:relative:/etc/xdg")), Path::new("app")),
+            [PathBuf::from("/usr/local/etc/xdg/app"), PathBuf::from("/etc/xdg/app"), PathBuf::from("/etc/app")],
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn site_config_dirs_env_override() {
+        env::set_var("ALPTK_LOCATION_SITE_TEST_SITE_CONFIG_DIRS", "/srv/app/etc:/etc/app");
+        let this = ProjectDirsOrEnv::new("app", "ALPTK_LOCATION_SITE_TEST").unwrap();
+
+        assert_eq!(this.site_config_dirs(), [PathBuf::from("/srv/app/etc"), PathBuf::from("/etc/app")]);
+    }
+
+    #[test]
+    fn site_config_dirs_stay_within_the_site_dirs() {
+        let site_config_dirs = |project_path: &str| {
+            let vars = [(OsString::from("ALPTK_LOCATION_SITE_ESCAPE_TEST_PROJECT_PATH"), OsString::from(project_path))];
+            let this = ProjectDirsOrEnv::from_vars("app", "ALPTK_LOCATION_SITE_ESCAPE_TEST", vars).unwrap();
+
+            this.site_config_dirs().to_vec()
+        };
+        let escaping = if cfg!(windows) { r"C:\evil" } else { "/evil" };
+
+        for project_path in [escaping, "../../evil", "evil/.."] {
+            let dirs = site_config_dirs(project_path);
+
+            assert!(!dirs.is_empty());
+            assert!(dirs.iter().all(|dir| dir.ends_with("app") && !dir.starts_with(escaping)), "{project_path}: {dirs:?}");
+        }
+
+        assert!(site_config_dirs("vendor/app").iter().all(|dir| dir.ends_with("vendor/app")));
+    }
+
+    #[test]
+    fn canonicalized_resolves_existing_dirs_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        let mut this = layout(false);
+
+        std::fs::create_dir(root.join("plain")).unwrap();
+        this.cache_dir = root.join("plain/./");
+        this.config_dir = root.join("missing/../missing");
+
+        #[cfg(unix)]
+        {
+            std::fs::create_dir(root.join("dotfiles")).unwrap();
+            std::os::unix::fs::symlink(root.join("dotfiles"), root.join("link")).unwrap();
+            this.data_dir = root.join("link");
+        }
+
+        let this = this.canonicalized();
+
+        assert_eq!(this.cache_dir(), root.join("plain"));
+        assert_eq!(this.config_dir(), root.join("missing/../missing"));
+        assert_eq!(this.project_path(), Path::new("app"));
+
+        #[cfg(unix)]
+        assert_eq!(this.data_dir(), root.join("dotfiles"));
+    }
+
+    #[test]
+    fn iter_skips_unset_optional_dirs() {
+        assert_eq!(layout(true).iter().count(), DirKind::ALL.len());
+        assert!(layout(false)
+            .iter()
+            .all(|(kind, _)| !matches!(kind, DirKind::Runtime | DirKind::State | DirKind::Executable)));
+    }
+
+    #[test]
+    fn table_rows_contain_every_dir() {
+        let this = layout(false);
+        let rows = this.table_rows();
+
+        assert_eq!(rows.len(), DirKind::ALL.len());
+        assert!(rows.contains(&("Cache".to_owned(), "/home/x/.cache/app".to_owned())));
+        assert!(rows.contains(&("Config".to_owned(), "/home/x/.config/app".to_owned())));
+        assert!(rows.contains(&("Runtime".to_owned(), "(unset)".to_owned())));
+
+        for (kind, path) in this.iter() {
+            assert!(rows.contains(&(kind.name().to_owned(), path.display().to_string())));
+        }
+    }
+
+    #[test]
+    fn map_covers_every_accessor() {
+        let this = layout(false);
+        let map = this.as_map();
+
+        assert_eq!(map.len(), DirKind::ALL.len());
+        assert_eq!(map["cache_dir"], Some(this.cache_dir()));
+        assert_eq!(map["config_dir"], Some(this.config_dir()));
+        assert_eq!(map["config_local_dir"], Some(this.config_local_dir()));
+        assert_eq!(map["data_dir"], Some(this.data_dir()));
+        assert_eq!(map["data_local_dir"], Some(this.data_local_dir()));
+        assert_eq!(map["preference_dir"], Some(this.preference_dir()));
+        assert_eq!(map["project_path"], Some(this.project_path()));
+        assert_eq!(map["runtime_dir"], None);
+        assert_eq!(map["state_dir"], None);
+        assert_eq!(map["log_dir"], Some(this.log_dir()));
+        assert_eq!(map["executable_dir"], None);
+        assert_eq!(this.as_string_map()["cache_dir"].as_deref(), Some("/home/x/.cache/app"));
+        assert_eq!(layout(true).as_map()["runtime_dir"], layout(true).runtime_dir());
+    }
+
+    #[cfg(feature = "serde")]
+    fn roundtrip(original: &ProjectDirsOrEnv) -> ProjectDirsOrEnv {
+        let json = serde_json::to_string(original).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip_all_fields() {
+        let original = layout(true);
+        let parsed = roundtrip(&original);
+
+        assert_eq!(parsed.cache_dir(), original.cache_dir());
+        assert_eq!(parsed.config_dir(), original.config_dir());
+        assert_eq!(parsed.project_path(), original.project_path());
+        assert_eq!(parsed.runtime_dir(), original.runtime_dir());
+        assert_eq!(parsed.state_dir(), original.state_dir());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip_optional_fields_absent() {
+        let original = layout(false);
+        let value = serde_json::to_value(&original).unwrap();
+
+        assert!(value["runtime_dir"].is_null());
+        assert!(value["state_dir"].is_null());
+        assert_eq!(value["cache_dir"], "/home/x/.cache/app");
+
+        let parsed = roundtrip(&original);
+
+        assert_eq!(parsed.runtime_dir(), None);
+        assert_eq!(parsed.state_dir(), None);
+    }
 }
@@ -0,0 +1,127 @@
+use std::fs;
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::{DirKind, InitializeError, ProjectDirsOrEnv};
+
+/// A cheaply clonable handle to a resolved directory layout.
+///
+/// Unlike the [`location!`](crate::location) macro, this doesn't involve any global state, so
+/// several layouts (e.g. with different app names or env prefixes) can be used at once. Every
+/// accessor of [`ProjectDirsOrEnv`] is available through [`Deref`].
+#[derive(Clone)]
+pub struct Locations {
+    inner: Arc<ProjectDirsOrEnv>,
+}
+
+impl Locations {
+    /// See [`ProjectDirsOrEnv::new`].
+    pub fn new(app_name: &str, env_prefix: &str) -> Result<Self, InitializeError> {
+        ProjectDirsOrEnv::new(app_name, env_prefix).map(Self::from)
+    }
+
+    /// Joins `relative` onto the directory of the given kind, or returns `None` if it isn't set.
+    pub fn join(&self, kind: DirKind, relative: impl AsRef<Path>) -> Option<PathBuf> {
+        self.get(kind).map(|dir| dir.join(relative))
+    }
+
+    /// Creates every directory which is set, along with their parents. The project path is a
+    /// relative fragment rather than a directory and the site directories are system-wide, so
+    /// neither is created.
+    pub fn create_all(&self) -> io::Result<()> {
+        for (kind, dir) in self.iter() {
+            if kind != DirKind::ProjectPath {
+                fs::create_dir_all(dir)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<ProjectDirsOrEnv> for Locations {
+    fn from(value: ProjectDirsOrEnv) -> Self {
+        Self { inner: Arc::new(value) }
+    }
+}
+
+impl Deref for Locations {
+    type Target = ProjectDirsOrEnv;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl AsRef<ProjectDirsOrEnv> for Locations {
+    fn as_ref(&self) -> &ProjectDirsOrEnv {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::locations::Locations;
+    use crate::tests::layout;
+    use crate::DirKind;
+
+    #[test]
+    fn join_appends_to_the_kind() {
+        let locations = Locations::from(layout(false));
+
+        assert_eq!(
+            locations.join(DirKind::Config, "config.toml"),
+            Some(PathBuf::from("/home/x/.config/app/config.toml")),
+        );
+        assert_eq!(locations.join(DirKind::Runtime, "app.sock"), None);
+    }
+
+    #[test]
+    fn clones_share_the_layout_and_instances_are_independent() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut first = layout(false);
+        first.cache_dir = tmp.path().join("first/cache");
+        let mut second = layout(false);
+        second.cache_dir = tmp.path().join("second/cache");
+
+        let first = Locations::from(first);
+        let second = Locations::from(second);
+        let clone = first.clone();
+
+        assert_eq!(clone.cache_dir(), first.cache_dir());
+        assert_ne!(first.cache_dir(), second.cache_dir());
+    }
+
+    #[test]
+    fn create_all_creates_every_set_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut this = layout(true);
+
+        for (dir, name) in [
+            (&mut this.cache_dir, "cache"),
+            (&mut this.config_dir, "config"),
+            (&mut this.config_local_dir, "config-local"),
+            (&mut this.data_dir, "data"),
+            (&mut this.data_local_dir, "data-local"),
+            (&mut this.preference_dir, "preference"),
+            (&mut this.log_dir, "log"),
+        ] {
+            *dir = tmp.path().join(name);
+        }
+
+        this.runtime_dir = Some(tmp.path().join("runtime"));
+        this.state_dir = Some(tmp.path().join("state/nested"));
+        this.executable_dir = Some(tmp.path().join("bin"));
+
+        let locations = Locations::from(this);
+        locations.create_all().unwrap();
+
+        for (kind, dir) in locations.iter() {
+            if kind != DirKind::ProjectPath {
+                assert!(dir.is_dir(), "{kind} wasn't created");
+            }
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum PathsFileError {
+    #[error("failed to read the paths file '{}'", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("invalid paths file '{}' at line {line}: {reason}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        reason: &'static str,
+    },
+}
+
+/// Reads the variables in the paths file at `path`.
+pub(crate) fn load(path: &Path) -> Result<HashMap<OsString, OsString>, PathsFileError> {
+    let contents = fs::read_to_string(path).map_err(|source| PathsFileError::Read { path: path.to_owned(), source })?;
+
+    parse(&contents).map_err(|(line, reason)| PathsFileError::Parse { path: path.to_owned(), line, reason })
+}
+
+/// Parses `KEY=VALUE` lines, where values may be quoted like TOML strings, so that a flat TOML
+/// table parses as well. Blank lines and lines starting with `#` are skipped. Errors carry the
+/// line number, starting at 1.
+fn parse(contents: &str) -> Result<HashMap<OsString, OsString>, (usize, &'static str)> {
+    let mut vars = HashMap::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+
+        let parse_line = || {
+            if line.starts_with('[') {
+                return Err("tables are not supported")
+            }
+
+            let (key, value) = line.split_once('=').ok_or("expected KEY=VALUE")?;
+            let key = key.trim();
+
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err("the key must consist of ASCII letters, digits and underscores")
+            }
+
+            Ok((key, parse_value(value.trim())?))
+        };
+        let (key, value) = parse_line().map_err(|reason| (index + 1, reason))?;
+
+        vars.insert(OsString::from(key), OsString::from(value));
+    }
+
+    Ok(vars)
+}
+
+fn parse_value(value: &str) -> Result<String, &'static str> {
+    if let Some(value) = value.strip_prefix('\'') {
+        return value.strip_suffix('\'').map(ToOwned::to_owned).ok_or("unterminated string")
+    }
+
+    let Some(value) = value.strip_prefix('"') else {
+        return Ok(value.to_owned())
+    };
+    let mut chars = value.chars();
+    let mut unescaped = String::new();
+
+    loop {
+        match chars.next().ok_or("unterminated string")? {
+            '"' if chars.as_str().is_empty() => return Ok(unescaped),
+            '"' => return Err("unexpected characters after the string"),
+            '\\' => unescaped.push(match chars.next().ok_or("unterminated string")? {
+                '\\' => '\\',
+                '"' => '"',
+                'n' => '\n',
+                't' => '\t',
+                _ => return Err("unsupported escape sequence"),
+            }),
+            c => unescaped.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use crate::paths_file::parse;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<OsString, OsString> {
+        pairs.iter().map(|(key, value)| (OsString::from(key), OsString::from(value))).collect()
+    }
+
+    #[test]
+    fn parses_env_and_toml_lines() {
+        let contents = r#"
+            # env style
+            APP_DATA_DIR=/srv/app/data
+            APP_CACHE_DIR = /var/cache/app
+
+            # toml style
+            APP_CONFIG_DIR = "/etc/app \"main\""
+            APP_LOG_DIR = 'C:\logs'
+        "#;
+
+        assert_eq!(parse(contents).unwrap(), vars(&[
+            ("APP_DATA_DIR", "/srv/app/data"),
+            ("APP_CACHE_DIR", "/var/cache/app"),
+            ("APP_CONFIG_DIR", "/etc/app \"main\""),
+            ("APP_LOG_DIR", r"C:\logs"),
+        ]));
+    }
+
+    #[test]
+    fn reports_the_line_of_errors() {
+        assert_eq!(parse("A=1\n\n[paths]"), Err((3, "tables are not supported")));
+        assert_eq!(parse("A=1\nB"), Err((2, "expected KEY=VALUE")));
+        assert_eq!(parse("A-B=1"), Err((1, "the key must consist of ASCII letters, digits and underscores")));
+        assert_eq!(parse("A=\"1"), Err((1, "unterminated string")));
+        assert_eq!(parse("A=\"1\" # one"), Err((1, "unexpected characters after the string")));
+        assert_eq!(parse("A=\"\\q\""), Err((1, "unsupported escape sequence")));
+    }
+}
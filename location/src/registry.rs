@@ -0,0 +1,108 @@
+//! A process-wide registry of layouts keyed by env prefix.
+//!
+//! Modules generated by [`location!`](crate::location) with the same env prefix share the layout
+//! registered here, even if they are expanded by different crates: the first `initialize()`
+//! resolves and registers it, and every later one reuses it, so the environment is only read once
+//! and the modules can't diverge. The env prefix is the key, rather than the package name, as it
+//! decides which variables a layout is read from. Layouts may also be registered by hand, e.g. to
+//! give every module of a prefix a layout resolved differently, as long as it happens before the
+//! modules are initialized.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::sync::{Mutex, PoisonError};
+use thiserror::Error;
+use crate::Locations;
+
+static REGISTRY: Mutex<BTreeMap<String, Locations>> = Mutex::new(BTreeMap::new());
+
+#[derive(Error, Debug)]
+#[error("a layout is already registered for the env prefix '{env_prefix}'")]
+#[non_exhaustive]
+pub struct AlreadyRegisteredError {
+    pub env_prefix: String,
+}
+
+/// Returns the layout registered for `env_prefix`, if any.
+pub fn get(env_prefix: &str) -> Option<Locations> {
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner).get(env_prefix).cloned()
+}
+
+/// Registers `locations` for `env_prefix`, unless a layout is registered for it already.
+pub fn register(env_prefix: &str, locations: Locations) -> Result<(), AlreadyRegisteredError> {
+    match REGISTRY.lock().unwrap_or_else(PoisonError::into_inner).entry(env_prefix.to_owned()) {
+        Entry::Occupied(_) => Err(AlreadyRegisteredError { env_prefix: env_prefix.to_owned() }),
+        Entry::Vacant(entry) => {
+            entry.insert(locations);
+            Ok(())
+        }
+    }
+}
+
+/// Returns the layout registered for `env_prefix`, or registers the one returned by `resolve` if
+/// there is none.
+///
+/// `resolve` runs without the registry being locked, as it usually reads the environment and the
+/// filesystem. If several callers race, each may resolve a layout, but only the first one to finish
+/// is registered, and every caller gets that one.
+pub fn get_or_try_register<E>(env_prefix: &str, resolve: impl FnOnce() -> Result<Locations, E>) -> Result<Locations, E> {
+    if let Some(locations) = get(env_prefix) {
+        return Ok(locations)
+    }
+
+    let locations = resolve()?;
+    let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+
+    Ok(registry.entry(env_prefix.to_owned()).or_insert(locations).clone())
+}
+
+/// Unregisters the layout for `env_prefix`, returning it. Modules which were already initialized
+/// with it keep using it.
+pub fn remove(env_prefix: &str) -> Option<Locations> {
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner).remove(env_prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use crate::registry;
+    use crate::tests::layout;
+    use crate::Locations;
+
+    #[test]
+    fn registers_once_per_key() {
+        let first = Locations::from(layout(false));
+
+        registry::register("ALPTK_LOCATION_REGISTRY_TEST", first.clone()).unwrap();
+        let error = registry::register("ALPTK_LOCATION_REGISTRY_TEST", Locations::from(layout(true))).unwrap_err();
+        registry::register("ALPTK_LOCATION_REGISTRY_OTHER_TEST", Locations::from(layout(true))).unwrap();
+
+        let got = registry::get_or_try_register("ALPTK_LOCATION_REGISTRY_TEST", || -> Result<_, Infallible> {
+            panic!("resolved again")
+        }).unwrap();
+
+        assert_eq!(error.env_prefix, "ALPTK_LOCATION_REGISTRY_TEST");
+        assert!(std::ptr::eq(got.config_dir(), first.config_dir()));
+        assert!(registry::get("ALPTK_LOCATION_REGISTRY_OTHER_TEST").unwrap().state_dir().is_some());
+        assert!(registry::remove("ALPTK_LOCATION_REGISTRY_TEST").is_some());
+        assert!(registry::get("ALPTK_LOCATION_REGISTRY_TEST").is_none());
+
+        registry::remove("ALPTK_LOCATION_REGISTRY_OTHER_TEST");
+    }
+
+    #[test]
+    fn resolving_may_use_the_registry() {
+        let registered = Locations::from(layout(false));
+
+        // the registry isn't locked while resolving, and a layout registered in the meantime wins
+        let got = registry::get_or_try_register("ALPTK_LOCATION_REGISTRY_RACE_TEST", || -> Result<_, Infallible> {
+            registry::register("ALPTK_LOCATION_REGISTRY_RACE_TEST", registered.clone()).unwrap();
+
+            Ok(Locations::from(layout(true)))
+        }).unwrap();
+
+        assert!(std::ptr::eq(got.config_dir(), registered.config_dir()));
+
+        registry::remove("ALPTK_LOCATION_REGISTRY_RACE_TEST");
+    }
+}
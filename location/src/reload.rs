@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+use std::sync::{PoisonError, RwLock};
+use crate::{DirKind, InitializeError, Locations, ProjectDirsOrEnvBuilder};
+
+/// A layout which can be resolved again at runtime with [`refresh`](Self::refresh), e.g. when a
+/// daemon receives `SIGHUP` after an operator repointed one of its environment variables.
+///
+/// This is the opt-in alternative to the [`location!`](crate::location) macro, whose layout is
+/// frozen once initialized so that it can hand out `&'static Path`s. Since the layout here may be
+/// replaced at any time, it instead hands out [snapshots](Self::current) and owned paths, which
+/// keep pointing at the layout they were taken from after a refresh. Look directories up again
+/// after refreshing rather than holding onto them.
+pub struct ReloadableLocations {
+    builder: ProjectDirsOrEnvBuilder,
+    current: RwLock<Locations>,
+}
+
+/// Which directories changed in a [`refresh`](ReloadableLocations::refresh).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChangedDirs {
+    /// The kinds of the directories which changed, including optional directories which became
+    /// set or unset, in the order of [`DirKind::ALL`].
+    pub kinds: Vec<DirKind>,
+
+    /// Whether the site config directories changed.
+    pub site_config_dirs: bool,
+}
+
+impl ChangedDirs {
+    /// Returns whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty() && !self.site_config_dirs
+    }
+
+    pub fn contains(&self, kind: DirKind) -> bool {
+        self.kinds.contains(&kind)
+    }
+}
+
+impl ReloadableLocations {
+    /// Resolves the layout described by `builder`, which is kept to resolve it again on every
+    /// refresh.
+    pub fn new(builder: ProjectDirsOrEnvBuilder) -> Result<Self, InitializeError> {
+        let current = Locations::from(builder.clone().build()?);
+
+        Ok(Self { builder, current: RwLock::new(current) })
+    }
+
+    /// Returns a snapshot of the current layout, which isn't affected by later refreshes.
+    pub fn current(&self) -> Locations {
+        self.current.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Returns the current directory of the given kind, or `None` if it isn't set.
+    pub fn get(&self, kind: DirKind) -> Option<PathBuf> {
+        self.current().get(kind).map(ToOwned::to_owned)
+    }
+
+    /// Resolves the layout again from the environment and replaces the current one with it,
+    /// returning which directories changed. On error, the current layout is kept.
+    pub fn refresh(&self) -> Result<ChangedDirs, InitializeError> {
+        let new = Locations::from(self.builder.clone().build()?);
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        let changed = ChangedDirs {
+            kinds: DirKind::ALL.into_iter().filter(|kind| current.get(*kind) != new.get(*kind)).collect(),
+            site_config_dirs: current.site_config_dirs() != new.site_config_dirs(),
+        };
+
+        *current = new;
+
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::{Path, PathBuf};
+    use crate::{DirKind, ProjectDirsOrEnv, ReloadableLocations};
+
+    const PREFIX: &str = "ALPTK_LOCATION_RELOAD_TEST";
+
+    #[test]
+    fn refresh_observes_env_changes() {
+        env::set_var(format!("{PREFIX}_DATA_DIR"), "/old/data");
+        env::set_var(format!("{PREFIX}_RUNTIME_DIR"), "/old/run");
+        let reloadable = ReloadableLocations::new(ProjectDirsOrEnv::builder("app").env_prefix(PREFIX)).unwrap();
+        let snapshot = reloadable.current();
+
+        assert!(reloadable.refresh().unwrap().is_empty());
+
+        env::set_var(format!("{PREFIX}_DATA_DIR"), "/new/data");
+        let changed = reloadable.refresh().unwrap();
+
+        assert_eq!(changed.kinds, [DirKind::Data]);
+        assert!(!changed.site_config_dirs);
+        assert_eq!(reloadable.get(DirKind::Data), Some(PathBuf::from("/new/data")));
+        assert_eq!(snapshot.data_dir(), Path::new("/old/data"));
+
+        env::set_var(format!("{PREFIX}_SITE_CONFIG_DIRS"), "/etc/app");
+        env::set_var(format!("{PREFIX}_DATA_DIR"), "/old/data");
+        let changed = reloadable.refresh().unwrap();
+
+        assert!(changed.contains(DirKind::Data));
+        assert!(changed.site_config_dirs);
+        assert_eq!(reloadable.current().site_config_dirs(), [PathBuf::from("/etc/app")]);
+    }
+}
@@ -0,0 +1,140 @@
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::ProjectDirsOrEnv;
+
+/// How many names are tried before giving up on creating a scratch directory or probe file.
+pub(crate) const ATTEMPTS: u32 = 16;
+
+/// A uniquely named scratch directory, which is removed along with its contents when dropped.
+/// Created by [`ProjectDirsOrEnv::scratch_dir`].
+#[must_use = "the directory is removed as soon as the guard is dropped"]
+pub struct ScratchDir {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl ScratchDir {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keeps the directory around after all, e.g. to inspect it while debugging, and returns its
+    /// path.
+    pub fn keep(mut self) -> PathBuf {
+        self.keep = true;
+        std::mem::take(&mut self.path)
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+impl ProjectDirsOrEnv {
+    /// Creates a uniquely named directory under `tmp` in [`cache_dir`](Self::cache_dir), which is
+    /// created if needed. If that isn't possible, the directory is created in the system temp
+    /// directory instead, prefixed with the name of the project.
+    pub fn scratch_dir(&self) -> io::Result<ScratchDir> {
+        let tmp = self.cache_dir().join("tmp");
+
+        match fs::create_dir_all(&tmp) {
+            Ok(()) => create_unique(&tmp, ""),
+            Err(_) => {
+                let project = self.project_path().file_name().map_or("".into(), |name| name.to_string_lossy());
+
+                create_unique(&env::temp_dir(), &format!("{project}-"))
+            }
+        }
+    }
+}
+
+/// Creates a directory in `parent` named `prefix` followed by something unique, retrying with
+/// another name if it already exists.
+pub(crate) fn create_unique(parent: &Path, prefix: &str) -> io::Result<ScratchDir> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    for _ in 0..ATTEMPTS {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = parent.join(format!("{prefix}{}-{nanos:x}-{count}", process::id()));
+
+        match fs::create_dir(&path) {
+            Ok(()) => return Ok(ScratchDir { path, keep: false }),
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+
+    Err(io::Error::new(ErrorKind::AlreadyExists, "failed to find an unused name for a scratch directory"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use crate::tests::layout;
+
+    #[test]
+    fn removed_on_drop() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut this = layout(false);
+        this.cache_dir = tmp.path().join("cache");
+
+        let scratch = this.scratch_dir().unwrap();
+        let path = scratch.path().to_owned();
+        fs::create_dir(path.join("nested")).unwrap();
+        fs::write(path.join("nested/download.part"), "...").unwrap();
+
+        assert_eq!(path.parent(), Some(tmp.path().join("cache/tmp").as_path()));
+
+        drop(scratch);
+
+        assert!(!path.exists());
+        assert!(tmp.path().join("cache/tmp").is_dir());
+    }
+
+    #[test]
+    fn kept_after_keep() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut this = layout(false);
+        this.cache_dir = tmp.path().to_owned();
+
+        let first = this.scratch_dir().unwrap();
+        let second = this.scratch_dir().unwrap();
+
+        assert_ne!(first.path(), second.path());
+
+        let path = first.keep();
+        drop(second);
+
+        assert!(path.is_dir());
+    }
+
+    #[test]
+    fn falls_back_to_system_temp_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut this = layout(false);
+        this.cache_dir = tmp.path().join("file");
+        fs::write(&this.cache_dir, "").unwrap();
+
+        let scratch = this.scratch_dir().unwrap();
+
+        assert_eq!(scratch.path().parent(), Some(env::temp_dir().as_path()));
+        assert!(scratch.path().file_name().unwrap().to_str().unwrap().starts_with("app-"));
+    }
+}
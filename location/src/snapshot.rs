@@ -0,0 +1,150 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The environment variables consulted while initializing a [`ProjectDirsOrEnv`], e.g. to attach
+/// to bug reports. See [`ProjectDirsOrEnv::init_snapshot`].
+///
+/// The [`Display`](fmt::Display) implementation lists every variable on its own line, with the
+/// home directory in values replaced by `~` so that the output can be pasted into an issue
+/// without revealing the user name.
+///
+/// [`ProjectDirsOrEnv`]: crate::ProjectDirsOrEnv
+/// [`ProjectDirsOrEnv::init_snapshot`]: crate::ProjectDirsOrEnv::init_snapshot
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct InitSnapshot {
+    /// Every variable consulted, with the value observed, converted to a string lossily, or `None`
+    /// if it was unset. Variables which were only set in the paths file are recorded as unset,
+    /// as only the path of the paths file itself comes from the environment. Empty if no env
+    /// prefix was set.
+    pub vars: BTreeMap<String, Option<String>>,
+
+    /// Whether the platform defaults were used, because the environment and the overrides didn't
+    /// provide every required directory. See [`ParityReport`](crate::ParityReport).
+    pub used_fallback: bool,
+}
+
+impl InitSnapshot {
+    fn fmt_redacted(&self, f: &mut fmt::Formatter<'_>, home: Option<&Path>) -> fmt::Result {
+        if self.used_fallback {
+            writeln!(f, "resolved from: the platform defaults (fallback)")?;
+        } else {
+            writeln!(f, "resolved from: the environment and overrides (parity)")?;
+        }
+
+        for (name, value) in &self.vars {
+            match value {
+                Some(value) => writeln!(f, "{name}={}", redact(value, home))?,
+                None => writeln!(f, "{name} (unset)")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for InitSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let home = directories::BaseDirs::new().map(|base_dirs| base_dirs.home_dir().to_owned());
+
+        self.fmt_redacted(f, home.as_deref())
+    }
+}
+
+/// Replaces the home directory at the start of `value`, or of any path in a list of paths, with
+/// `~`.
+fn redact(value: &str, home: Option<&Path>) -> String {
+    let Some(home) = home.and_then(Path::to_str).filter(|home| !home.is_empty() && *home != "/") else {
+        return value.to_owned()
+    };
+
+    std::env::split_paths(value)
+        .map(|path| match path.strip_prefix(home) {
+            Ok(rest) if rest.as_os_str().is_empty() => PathBuf::from("~"),
+            Ok(rest) => Path::new("~").join(rest),
+            Err(_) => path,
+        })
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join(if cfg!(windows) { ";" } else { ":" })
+}
+
+/// Records the variables looked up through [`lookup`](Self::lookup) while initializing.
+#[derive(Default)]
+pub(crate) struct Recorder(RefCell<BTreeMap<String, Option<String>>>);
+
+impl Recorder {
+    pub(crate) fn lookup(&self, name: &str, var: impl Fn(&str) -> Option<OsString>) -> Option<OsString> {
+        let value = var(name);
+        let observed = value.as_ref().map(|value| value.to_string_lossy().into_owned());
+        self.0.borrow_mut().entry(name.to_owned()).or_insert(observed);
+
+        value
+    }
+
+    pub(crate) fn finish(self, used_fallback: bool) -> InitSnapshot {
+        InitSnapshot { vars: self.0.into_inner(), used_fallback }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::path::Path;
+    use crate::ProjectDirsOrEnv;
+    use super::redact;
+
+    const PREFIX: &str = "ALPTK_LOCATION_SNAPSHOT_TEST";
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+        pairs.iter().map(|(name, value)| (OsString::from(name), OsString::from(value))).collect()
+    }
+
+    #[test]
+    fn records_consulted_vars() {
+        let this = ProjectDirsOrEnv::from_vars("app", PREFIX, vars(&[
+            (&format!("{PREFIX}_DATA_DIR"), "/srv/data"),
+            ("UNRELATED", "value"),
+        ])).unwrap();
+        let snapshot = this.init_snapshot();
+
+        assert!(snapshot.used_fallback);
+        assert_eq!(snapshot.vars[&format!("{PREFIX}_DATA_DIR")].as_deref(), Some("/srv/data"));
+        assert_eq!(snapshot.vars[&format!("{PREFIX}_CACHE_DIR")], None);
+        assert!(!snapshot.vars.contains_key("UNRELATED"));
+
+        for name in ProjectDirsOrEnv::env_vars(PREFIX).into_iter().chain(["APPIMAGE".to_owned(), "APPDIR".to_owned()]) {
+            assert!(snapshot.vars.contains_key(&name), "{name} wasn't recorded");
+        }
+
+        let display = snapshot.to_string();
+
+        assert!(display.contains("(fallback)"), "{display}");
+        assert!(display.contains(&format!("{PREFIX}_DATA_DIR=/srv/data\n")), "{display}");
+        assert!(display.contains(&format!("{PREFIX}_CACHE_DIR (unset)\n")), "{display}");
+
+        let required = ["CACHE_DIR", "CONFIG_DIR", "CONFIG_LOCAL_DIR", "DATA_DIR", "DATA_LOCAL_DIR", "PREFERENCE_DIR", "PROJECT_PATH"]
+            .map(|suffix| (format!("{PREFIX}_{suffix}"), "/srv"));
+        let pairs: Vec<_> = required.iter().map(|(name, value)| (name.as_str(), *value)).collect();
+        let this = ProjectDirsOrEnv::from_vars("app", PREFIX, vars(&pairs)).unwrap();
+
+        assert!(!this.init_snapshot().used_fallback);
+        assert!(ProjectDirsOrEnv::builder("app").build().unwrap().init_snapshot().vars.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn redacts_the_home_directory() {
+        let home = Some(Path::new("/home/alice"));
+
+        assert_eq!(redact("/home/alice/.cache/app", home), "~/.cache/app");
+        assert_eq!(redact("/home/alice", home), "~");
+        assert_eq!(redact("/etc/xdg:/home/alice/.config", home), "/etc/xdg:~/.config");
+        assert_eq!(redact("/home/alicia/data", home), "/home/alicia/data");
+        assert_eq!(redact("/home/alice/data", None), "/home/alice/data");
+    }
+}
@@ -0,0 +1,69 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use crate::ProjectDirsOrEnv;
+
+impl ProjectDirsOrEnv {
+    /// The directory holding the data of the given major version of the data layout,
+    /// `<data_dir>/v<version>`. It isn't created.
+    pub fn versioned_data_dir(&self, version: u32) -> PathBuf {
+        self.data_dir().join(format!("v{version}"))
+    }
+
+    /// Lists the versions which have a [versioned data directory](Self::versioned_data_dir), in
+    /// ascending order, e.g. to find the newest previous version to migrate from.
+    ///
+    /// Entries which aren't directories or aren't named exactly like `v<version>`, e.g. `v01` or
+    /// `v2-backup`, are skipped. A nonexistent data directory has no versions.
+    pub fn list_data_versions(&self) -> io::Result<Vec<u32>> {
+        let entries = match fs::read_dir(self.data_dir()) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+        let mut versions = Vec::new();
+
+        for entry in entries {
+            let entry = entry?;
+            let Some(version) = entry.file_name().to_str().and_then(parse_version) else { continue };
+
+            if entry.path().is_dir() {
+                versions.push(version);
+            }
+        }
+
+        versions.sort_unstable();
+
+        Ok(versions)
+    }
+}
+
+fn parse_version(name: &str) -> Option<u32> {
+    let version = name.strip_prefix('v')?.parse().ok()?;
+
+    // rejects signs and leading zeros, so that every version has a single directory
+    (format!("v{version}") == name).then_some(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::{DirKind, ProjectDirsOrEnv};
+
+    #[test]
+    fn lists_versions_in_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let this = ProjectDirsOrEnv::builder("app").override_dir(DirKind::Data, tmp.path().join("data")).build().unwrap();
+
+        assert!(this.list_data_versions().unwrap().is_empty());
+
+        for name in ["v3", "v1", "v01", "v+2", "v", "version", "v4294967296", "v2-backup", "misc"] {
+            fs::create_dir_all(tmp.path().join("data").join(name)).unwrap();
+        }
+
+        fs::write(tmp.path().join("data/v5"), "").unwrap();
+
+        assert_eq!(this.versioned_data_dir(3), tmp.path().join("data/v3"));
+        assert_eq!(this.list_data_versions().unwrap(), [1, 3]);
+    }
+}
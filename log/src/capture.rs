@@ -0,0 +1,88 @@
+use std::sync::{Arc, Mutex, PoisonError};
+use crate::{Level, Logger};
+
+pub(crate) type Records = Arc<Mutex<Vec<(Level, String)>>>;
+
+/// Records the messages of a logger instead of writing them while alive. Created by
+/// [`capture`](crate::capture).
+///
+/// Messages are recorded unrendered, i.e. without glyphs, timestamps or indentation, and only if
+/// they aren't discarded by the [level](Logger::set_level) of the logger. Messages logged with
+/// [`custom`](Logger::custom) are recorded at [`Level::Info`].
+#[must_use = "messages are only captured while the guard is alive"]
+pub struct CaptureGuard<'a> {
+    logger: &'a Logger,
+    records: Records,
+    previous: Option<Records>,
+}
+
+impl<'a> CaptureGuard<'a> {
+    pub(crate) fn new(logger: &'a Logger) -> Self {
+        let records = Records::default();
+        let previous = logger.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace(Arc::clone(&records));
+
+        Self { logger, records, previous }
+    }
+
+    /// Returns the messages recorded so far, oldest first.
+    pub fn records(&self) -> Vec<(Level, String)> {
+        self.records.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+impl Drop for CaptureGuard<'_> {
+    fn drop(&mut self) {
+        // restores an outer capture, if any
+        *self.logger.records.lock().unwrap_or_else(PoisonError::into_inner) = self.previous.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Level, Logger};
+    use crate::tests::Capture;
+
+    #[test]
+    fn output_is_restored_on_drop() {
+        let (logger, output) = Capture::logger();
+        logger.set_level(Level::Info);
+
+        let outer = logger.capture();
+        logger.debug("discarded");
+        logger.info("outer");
+
+        let inner = logger.capture();
+        logger.warn("inner");
+        drop(inner);
+
+        logger.tip("outer again");
+        drop(outer);
+
+        logger.info("written");
+
+        assert!(!output.output().contains("outer"));
+        assert!(!output.output().contains("inner"));
+        assert!(output.output().contains("written"));
+    }
+
+    #[test]
+    fn nested_captures_record_separately() {
+        let logger = Logger::new(Vec::new());
+        let outer = logger.capture();
+        logger.info("one");
+
+        {
+            let inner = logger.capture();
+            logger.warn("two");
+
+            assert_eq!(inner.records(), [(Level::Warn, "two".to_owned())]);
+        }
+
+        logger.info("three");
+
+        assert_eq!(outer.records(), [(Level::Info, "one".to_owned()), (Level::Info, "three".to_owned())]);
+    }
+}
@@ -1,41 +1,621 @@
-use std::fmt;
-use owo_colors::{Color, OwoColorize};
-use owo_colors::colors::*;
-
-const PROLOGUE: char = '┃';
-const PROLOGUE_CONTINUATION: char = '=';
-
-fn log<C: Color, M: fmt::Display>(message: M) {
-    let message = message.to_string();
-    let mut lines = message.lines();
-    let first_line = if let Some(first_line) = lines.next() {
-        first_line
+use std::{fmt, io};
+use std::io::IsTerminal;
+use std::cell::Cell;
+use std::fmt::Write;
+use std::marker::PhantomData;
+use std::sync::{LazyLock, Mutex, PoisonError};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use owo_colors::OwoColorize;
+use owo_colors::colors::{Blue, Cyan, Green, Red, Yellow};
+
+pub use owo_colors::{colors, Color};
+pub use capture::CaptureGuard;
+pub use spinner::Spinner;
+
+mod capture;
+mod spinner;
+#[cfg(feature = "tracing")]
+pub mod tracing;
+
+const DEFAULT_PROLOGUE: char = '┃';
+const DEFAULT_PROLOGUE_CONTINUATION: char = '=';
+
+/// The severity of a message. Messages below the [level of a logger](Logger::set_level) are
+/// discarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Debug,
+    Info,
+    Tip,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn from_u8(level: u8) -> Self {
+        match level {
+            0 => Self::Debug,
+            1 => Self::Info,
+            2 => Self::Tip,
+            3 => Self::Warn,
+            _ => Self::Error,
+        }
+    }
+}
+
+/// Whether the prologue glyph is colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorPolicy {
+    Always,
+    Never,
+}
+
+// encodings of `Logger::wrap`; anything else is a fixed width
+const WRAP_DISABLED: usize = 0;
+const WRAP_TERMINAL: usize = usize::MAX;
+
+fn load_glyph(glyph: &AtomicU32) -> char {
+    // only ever stored from a `char`, so this can't fail
+    char::from_u32(glyph.load(Ordering::Relaxed)).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+/// A logger with its own writer and configuration.
+///
+/// The free functions and macros of this crate log through the [global] logger, which writes to
+/// stderr. Separate loggers are useful for libraries which don't want to share that
+/// configuration, or for capturing output in tests.
+///
+/// The lines of a message are always written at once, so concurrently logged messages never
+/// interleave.
+pub struct Logger {
+    writer: Mutex<Box<dyn io::Write + Send>>,
+    terminal: AtomicBool,
+    level: AtomicU8,
+    prologue: AtomicU32,
+    continuation: AtomicU32,
+    colors: AtomicBool,
+    timestamps: AtomicBool,
+    wrap: AtomicUsize,
+    records: Mutex<Option<capture::Records>>,
+}
+
+macro_rules! log_fn {
+    ($($vis:vis $fn_name:ident, $level:ident, $color:ident;)*) => {
+        $(
+        $vis fn $fn_name(&self, message: impl fmt::Display) {
+            self.log::<$color, _>(Level::$level, message);
+        }
+        )*
+    };
+}
+
+impl Logger {
+    /// Creates a logger writing to `writer`, with every level enabled, the default glyphs, colors,
+    /// and neither timestamps nor wrapping.
+    pub fn new(writer: impl io::Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            terminal: AtomicBool::new(false),
+            level: AtomicU8::new(Level::Debug as u8),
+            prologue: AtomicU32::new(DEFAULT_PROLOGUE as u32),
+            continuation: AtomicU32::new(DEFAULT_PROLOGUE_CONTINUATION as u32),
+            colors: AtomicBool::new(true),
+            timestamps: AtomicBool::new(false),
+            wrap: AtomicUsize::new(WRAP_DISABLED),
+            records: Mutex::new(None),
+        }
+    }
+
+    /// Creates a logger writing to stderr.
+    pub fn stderr() -> Self {
+        let logger = Self::new(io::stderr());
+        logger.terminal.store(io::stderr().is_terminal(), Ordering::Relaxed);
+
+        logger
+    }
+
+    /// Replaces the writer messages are written to. The writer is never considered a terminal, so
+    /// [spinners](Self::spinner) aren't animated on it.
+    pub fn set_writer(&self, writer: impl io::Write + Send + 'static) {
+        *self.writer.lock().unwrap_or_else(PoisonError::into_inner) = Box::new(writer);
+        self.terminal.store(false, Ordering::Relaxed);
+    }
+
+    pub fn level(&self) -> Level {
+        Level::from_u8(self.level.load(Ordering::Relaxed))
+    }
+
+    /// Discards messages below `level`. Defaults to [`Level::Debug`], i.e. nothing is discarded.
+    pub fn set_level(&self, level: Level) {
+        self.level.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// Returns the glyph printed before the first line of every message.
+    pub fn prologue(&self) -> char {
+        load_glyph(&self.prologue)
+    }
+
+    /// Returns the glyph printed before every continuation line of a multi-line message.
+    pub fn continuation(&self) -> char {
+        load_glyph(&self.continuation)
+    }
+
+    /// Sets the glyph printed before the first line of every message. Defaults to `┃`.
+    pub fn set_prologue(&self, glyph: char) {
+        self.prologue.store(glyph as u32, Ordering::Relaxed);
+    }
+
+    /// Sets the glyph printed before every continuation line of a multi-line message. Defaults to
+    /// `=`.
+    pub fn set_continuation(&self, glyph: char) {
+        self.continuation.store(glyph as u32, Ordering::Relaxed);
+    }
+
+    pub fn color_policy(&self) -> ColorPolicy {
+        if self.colors.load(Ordering::Relaxed) {
+            ColorPolicy::Always
+        } else {
+            ColorPolicy::Never
+        }
+    }
+
+    /// Defaults to [`ColorPolicy::Always`].
+    pub fn set_color_policy(&self, policy: ColorPolicy) {
+        self.colors.store(policy == ColorPolicy::Always, Ordering::Relaxed);
+    }
+
+    /// Prefixes every message with the current time, as an RFC 3339 timestamp in UTC. Disabled by
+    /// default.
+    pub fn enable_timestamps(&self) {
+        self.timestamps.store(true, Ordering::Relaxed);
+    }
+
+    pub fn disable_timestamps(&self) {
+        self.timestamps.store(false, Ordering::Relaxed);
+    }
+
+    /// Hard-wraps lines longer than the width of the terminal stderr is attached to, prefixing the
+    /// wrapped segments with the [continuation](Self::continuation) glyph. Nothing is wrapped if
+    /// stderr isn't a terminal. Disabled by default.
+    pub fn enable_wrapping(&self) {
+        self.wrap.store(WRAP_TERMINAL, Ordering::Relaxed);
+    }
+
+    /// Like [`enable_wrapping`](Self::enable_wrapping), but wraps to a fixed width instead.
+    pub fn set_wrap_width(&self, width: usize) {
+        self.wrap.store(width.clamp(1, WRAP_TERMINAL - 1), Ordering::Relaxed);
+    }
+
+    pub fn disable_wrapping(&self) {
+        self.wrap.store(WRAP_DISABLED, Ordering::Relaxed);
+    }
+
+    fn wrap_width(&self) -> Option<usize> {
+        match self.wrap.load(Ordering::Relaxed) {
+            WRAP_DISABLED => None,
+            WRAP_TERMINAL => terminal_size::terminal_size_of(io::stderr()).map(|(width, _)| usize::from(width.0)),
+            width => Some(width),
+        }
+    }
+
+    fn options(&self) -> RenderOptions {
+        RenderOptions {
+            prologue: self.prologue(),
+            continuation: self.continuation(),
+            colors: self.colors.load(Ordering::Relaxed),
+            timestamp: self.timestamps.load(Ordering::Relaxed).then(SystemTime::now),
+            indent: INDENT.with(Cell::get),
+            width: self.wrap_width(),
+        }
+    }
+
+    fn render<C: Color>(&self, message: impl fmt::Display) -> String {
+        render::<C>(&message.to_string(), &self.options())
+    }
+
+    fn write_rendered(&self, rendered: impl AsRef<[u8]>) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+
+        writer.write_all(rendered.as_ref())?;
+        writer.flush()
+    }
+
+    /// Returns whether a [`CaptureGuard`] is alive.
+    fn capturing(&self) -> bool {
+        self.records.lock().unwrap_or_else(PoisonError::into_inner).is_some()
+    }
+
+    /// Records `message` if a [`CaptureGuard`] is alive, returning whether it did.
+    fn record(&self, level: Level, message: &impl fmt::Display) -> bool {
+        let records = self.records.lock().unwrap_or_else(PoisonError::into_inner).clone();
+
+        match records {
+            Some(records) => {
+                // formatted before taking the lock, as the message itself may log
+                let message = message.to_string();
+                records.lock().unwrap_or_else(PoisonError::into_inner).push((level, message));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn log<C: Color, M: fmt::Display>(&self, level: Level, message: M) {
+        if level < self.level() || self.record(level, &message) {
+            return
+        }
+
+        // rendered before taking the lock, as the message itself may log
+        let rendered = self.render::<C>(message);
+        let _ = self.write_rendered(&rendered);
+    }
+
+    /// Logs `message` with the prologue glyph colored `C`, rather than the color of a log level.
+    /// Such messages are never discarded.
+    pub fn custom<C: Color>(&self, message: impl fmt::Display) {
+        if self.record(Level::Info, &message) {
+            return
+        }
+
+        let rendered = self.render::<C>(message);
+        let _ = self.write_rendered(&rendered);
+    }
+
+    /// Records messages instead of writing them until the returned guard is dropped, for
+    /// assertions in tests. Captures can be nested, in which case only the innermost one records.
+    pub fn capture(&self) -> CaptureGuard<'_> {
+        CaptureGuard::new(self)
+    }
+
+    /// Logs `label` with [`info`](Self::info), then indents every message subsequently logged on
+    /// the current thread by two more spaces until the returned guard is dropped. The indentation
+    /// applies to every logger.
+    pub fn scope(&self, label: impl fmt::Display) -> Scope {
+        self.info(label);
+        INDENT.with(|indent| indent.set(indent.get() + 1));
+
+        Scope { _not_send: PhantomData }
+    }
+
+    /// Shows a [`Spinner`] labelled `label` until it is finished. The animation runs on its own
+    /// thread, hence the `'static` logger.
+    pub fn spinner(&'static self, label: impl Into<String>) -> Spinner {
+        Spinner::new(self, label.into())
+    }
+
+    /// Logs `bytes` with [`debug`](Self::debug) as a hex dump in the style of `hexdump -C`, one
+    /// continuation line per 16 bytes.
+    pub fn hexdump(&self, bytes: &[u8]) {
+        self.debug(format_args!("{} bytes{}", bytes.len(), hex_lines(bytes)));
+    }
+
+    log_fn! {
+        pub info, Info, Blue;
+        pub warn, Warn, Yellow;
+        pub error, Error, Red;
+        pub tip, Tip, Green;
+        pub debug, Debug, Cyan;
+    }
+}
+
+static GLOBAL: LazyLock<Logger> = LazyLock::new(Logger::stderr);
+
+/// Returns the logger used by the free functions and macros of this crate, which writes to
+/// stderr.
+pub fn global() -> &'static Logger {
+    &GLOBAL
+}
+
+/// Replaces the writer messages are written to, stderr by default.
+pub fn set_writer(writer: impl io::Write + Send + 'static) {
+    global().set_writer(writer)
+}
+
+/// Returns the glyph printed before the first line of every message.
+pub fn prologue() -> char {
+    global().prologue()
+}
+
+/// Returns the glyph printed before every continuation line of a multi-line message.
+pub fn continuation() -> char {
+    global().continuation()
+}
+
+/// Sets the glyph printed before the first line of every message. Defaults to `┃`.
+pub fn set_prologue(glyph: char) {
+    global().set_prologue(glyph)
+}
+
+/// Sets the glyph printed before every continuation line of a multi-line message. Defaults to `=`.
+pub fn set_continuation(glyph: char) {
+    global().set_continuation(glyph)
+}
+
+/// Discards messages below `level`. Defaults to [`Level::Debug`], i.e. nothing is discarded.
+pub fn set_level(level: Level) {
+    global().set_level(level)
+}
+
+/// Defaults to [`ColorPolicy::Always`].
+pub fn set_color_policy(policy: ColorPolicy) {
+    global().set_color_policy(policy)
+}
+
+/// Prefixes every message with the current time, as an RFC 3339 timestamp in UTC. Disabled by
+/// default.
+pub fn enable_timestamps() {
+    global().enable_timestamps()
+}
+
+pub fn disable_timestamps() {
+    global().disable_timestamps()
+}
+
+/// Formats `time` as an RFC 3339 timestamp in UTC with second precision, e.g.
+/// `2001-09-09T01:46:40Z`. Times before the unix epoch are clamped to it.
+fn rfc3339(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    )
+}
+
+thread_local! {
+    static INDENT: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Indents messages logged on the current thread while alive. Created by [`scope`].
+#[must_use = "the indentation is undone as soon as the scope is dropped"]
+pub struct Scope {
+    // the indentation is thread-local, so the guard must stay on its thread
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        INDENT.with(|indent| indent.set(indent.get().saturating_sub(1)));
+    }
+}
+
+/// Logs `label` with [`info`], then indents every message subsequently logged on the current
+/// thread by two more spaces until the returned guard is dropped. Scopes can be nested.
+pub fn scope(label: impl fmt::Display) -> Scope {
+    global().scope(label)
+}
+
+/// Hard-wraps lines longer than the width of the terminal stderr is attached to, prefixing the
+/// wrapped segments with the [continuation](continuation) glyph. Nothing is wrapped if stderr
+/// isn't a terminal. Disabled by default.
+pub fn enable_wrapping() {
+    global().enable_wrapping()
+}
+
+pub fn disable_wrapping() {
+    global().disable_wrapping()
+}
+
+/// Records messages of the global logger instead of writing them until the returned guard is
+/// dropped.
+///
+/// ```
+/// let capture = alptk_log::capture();
+/// alptk_log::info!("hello");
+///
+/// assert_eq!(capture.records(), [(alptk_log::Level::Info, "hello".to_owned())]);
+/// ```
+pub fn capture() -> CaptureGuard<'static> {
+    global().capture()
+}
+
+/// Shows a [`Spinner`] labelled `label` until it is finished.
+///
+/// ```
+/// let spinner = alptk_log::spinner("fetching index");
+/// spinner.finish_success("42 packages");
+/// ```
+pub fn spinner(label: impl Into<String>) -> Spinner {
+    global().spinner(label)
+}
+
+/// Logs `bytes` with [`debug`] as a hex dump in the style of `hexdump -C`, one continuation line
+/// per 16 bytes.
+///
+/// ```
+/// alptk_log::hexdump(b"GET / HTTP/1.1\r\n");
+/// ```
+pub fn hexdump(bytes: &[u8]) {
+    global().hexdump(bytes)
+}
+
+/// Formats `bytes` as lines of an offset, 16 bytes in hex and those bytes as ASCII, with
+/// non-printable bytes shown as `.`. Every line, including the first, is preceded by a newline.
+fn hex_lines(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+
+    for (index, chunk) in bytes.chunks(16).enumerate() {
+        let _ = write!(dump, "\n{:08x}  ", index * 16);
+
+        for column in 0..16 {
+            if column == 8 {
+                dump.push(' ');
+            }
+
+            match chunk.get(column) {
+                Some(byte) => { let _ = write!(dump, "{byte:02x} "); }
+                None => dump.push_str("   "),
+            }
+        }
+
+        dump.push_str(" |");
+        dump.extend(chunk.iter().map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }));
+        dump.push('|');
+    }
+
+    dump
+}
+
+/// Splits `line` into chunks of at most `width` characters.
+fn wrap(line: &str, width: usize) -> impl Iterator<Item = &str> {
+    let width = width.max(1);
+    let mut rest = line;
+    let mut first = true;
+
+    std::iter::from_fn(move || {
+        if rest.is_empty() && !first {
+            return None
+        }
+
+        first = false;
+        let split = rest.char_indices().nth(width).map_or(rest.len(), |(i, _)| i);
+        let (chunk, tail) = rest.split_at(split);
+        rest = tail;
+
+        Some(chunk)
+    })
+}
+
+/// The number of terminal columns `glyph` occupies: two for east asian wide characters and most
+/// emoji, one otherwise. An approximation of UAX #11, which is plenty for a single glyph.
+fn glyph_width(glyph: char) -> usize {
+    match glyph as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+struct RenderOptions {
+    prologue: char,
+    continuation: char,
+    colors: bool,
+    timestamp: Option<SystemTime>,
+    indent: usize,
+    width: Option<usize>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            prologue: DEFAULT_PROLOGUE,
+            continuation: DEFAULT_PROLOGUE_CONTINUATION,
+            colors: true,
+            timestamp: None,
+            indent: 0,
+            width: None,
+        }
+    }
+}
+
+fn render<C: Color>(message: &str, options: &RenderOptions) -> String {
+    let mut rendered = String::new();
+    if message.is_empty() {
+        return rendered
+    }
+
+    let (timestamp, padding) = match options.timestamp {
+        Some(timestamp) => {
+            let timestamp = format!("{} ", rfc3339(timestamp));
+            let padding = " ".repeat(timestamp.len());
+
+            (timestamp, padding)
+        }
+        None => (String::new(), String::new()),
+    };
+    let indent = "  ".repeat(options.indent);
+    let (mut prologue, mut continuation) = if options.colors {
+        (options.prologue.bold().fg::<C>().to_string(), options.continuation.bold().to_string())
     } else {
-        return
+        (options.prologue.to_string(), options.continuation.to_string())
     };
-    eprintln!("{} {first_line}", PROLOGUE.bold().fg::<C>());
 
-    for line in lines {
-        eprintln!("{} {line}", PROLOGUE_CONTINUATION.bold());
+    // pad the narrower glyph so the text of every line starts in the same column
+    let (prologue_width, continuation_width) = (glyph_width(options.prologue), glyph_width(options.continuation));
+    let glyph_width = prologue_width.max(continuation_width);
+    prologue.push_str(&" ".repeat(glyph_width - prologue_width));
+    continuation.push_str(&" ".repeat(glyph_width - continuation_width));
+
+    // the glyph and the space after it, then the timestamp or its padding, then the indent
+    let prefix_width = glyph_width + 1 + padding.len() + indent.len();
+    let width = options.width.map_or(usize::MAX, |width| width.saturating_sub(prefix_width));
+    let mut segments = message.lines().flat_map(|line| wrap(line, width));
+
+    if let Some(first) = segments.next() {
+        let _ = writeln!(rendered, "{prologue} {timestamp}{indent}{first}");
     }
+
+    for segment in segments {
+        let _ = writeln!(rendered, "{continuation} {padding}{indent}{segment}");
+    }
+
+    rendered
 }
 
-macro_rules! log_fn {
-    ($($vis:vis $fn_name:ident, $color:ident;)*) => {
+/// Logs `message` with the prologue glyph colored `C`, rather than the color of a log level.
+///
+/// ```
+/// alptk_log::custom::<alptk_log::colors::Magenta>("highlighted");
+/// ```
+pub fn custom<C: Color>(message: impl fmt::Display) {
+    global().custom::<C>(message)
+}
+
+/// Like [`custom`], but takes format arguments, with the color given by its name in [`colors`].
+///
+/// ```
+/// alptk_log::custom!(Magenta, "highlighted {}", 42);
+/// ```
+#[macro_export]
+macro_rules! custom {
+    ($color:ident, $($arg:tt)*) => {
+        $crate::custom::<$crate::colors::$color>(::std::format_args!($($arg)*));
+    };
+}
+
+macro_rules! global_log_fn {
+    ($($vis:vis $fn_name:ident;)*) => {
         $(
         $vis fn $fn_name(message: impl fmt::Display) {
-            log::<$color, _>(message);
+            global().$fn_name(message);
         }
         )*
     };
 }
 
-log_fn! {
-    pub info, Blue;
-    pub warn, Yellow;
-    pub error, Red;
-    pub tip, Green;
-    pub debug, Cyan;
+global_log_fn! {
+    pub info;
+    pub warn;
+    pub error;
+    pub tip;
+    pub debug;
 }
 
 macro_rules! log {
@@ -47,14 +627,321 @@ macro_rules! log {
         #[macro_export]
         macro_rules! $fn_name {
             ($d($d arg:tt)*) => {
-                $crate::log::$fn_name(::std::format_args!($d($d arg)*));
+                $crate::$fn_name(::std::format_args!($d($d arg)*));
             }
         }
         )*
+
+        /// Like [`std::dbg!`], but logs the expression and its value with [`tip`] instead, prefixed
+        /// by the file and line it is invoked on. Returns the value, so it can be used inline.
+        ///
+        /// ```
+        /// let doubled = alptk_log::dbg_tip!(21 * 2);
+        /// assert_eq!(doubled, 42);
+        /// ```
+        #[macro_export]
+        macro_rules! dbg_tip {
+            ($d value:expr $d(,)?) => {
+                match $d value {
+                    value => {
+                        $crate::tip(::std::format_args!(
+                            "[{}:{}] {} = {:#?}",
+                            ::std::file!(),
+                            ::std::line!(),
+                            ::std::stringify!($d value),
+                            &value,
+                        ));
+                        value
+                    }
+                }
+            };
+        }
     };
 }
 
 log! {
         some funny witty comment about the $ token;
         info, warn, error, tip, debug,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, UNIX_EPOCH};
+    use owo_colors::colors::{Blue, Magenta};
+    use crate::{glyph_width, render, rfc3339, ColorPolicy, Level, Logger, RenderOptions, DEFAULT_PROLOGUE, DEFAULT_PROLOGUE_CONTINUATION};
+
+    /// Held by tests which depend on the configuration of the global logger.
+    pub(crate) static GLOBAL_LOCK: Mutex<()> = Mutex::new(());
+
+    #[derive(Clone, Default)]
+    pub(crate) struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Capture {
+        pub(crate) fn logger() -> (Logger, Self) {
+            let capture = Self::default();
+
+            (Logger::new(capture.clone()), capture)
+        }
+
+        pub(crate) fn output(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn custom_glyphs_appear_in_output() {
+        let (logger, capture) = Capture::logger();
+        logger.set_prologue('|');
+        logger.set_continuation(':');
+        logger.info("first\nsecond");
+
+        let out = capture.output();
+        let mut lines = out.lines();
+
+        assert!(lines.next().unwrap().contains('|'));
+        assert!(lines.next().unwrap().contains(':'));
+        assert!(!out.contains(DEFAULT_PROLOGUE));
+    }
+
+    #[test]
+    fn independent_loggers() {
+        let (first, first_capture) = Capture::logger();
+        let (second, second_capture) = Capture::logger();
+        second.set_level(Level::Warn);
+        second.set_color_policy(ColorPolicy::Never);
+
+        first.info("to first");
+        second.info("discarded");
+        second.warn("to second");
+
+        let first_out = first_capture.output();
+        let second_out = second_capture.output();
+
+        assert!(first_out.contains("to first"));
+        assert!(!first_out.contains("to second"));
+        assert_eq!(second_out, format!("{DEFAULT_PROLOGUE} to second\n"));
+    }
+
+    #[test]
+    fn hexdump_layout() {
+        let (logger, capture) = Capture::logger();
+        logger.set_color_policy(ColorPolicy::Never);
+        logger.hexdump(b"Hello, world!\n\x00\x01\x02\xff");
+
+        assert_eq!(capture.output(), format!(
+            "{DEFAULT_PROLOGUE} 18 bytes\n\
+             {DEFAULT_PROLOGUE_CONTINUATION} 00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 01  |Hello, world!...|\n\
+             {DEFAULT_PROLOGUE_CONTINUATION} 00000010  02 ff                                             |..|\n",
+        ));
+    }
+
+    #[test]
+    fn text_column_is_aligned_across_glyph_widths() {
+        fn text_column(line: &str) -> usize {
+            let prefix = line.strip_suffix("text").unwrap();
+
+            prefix.chars().map(glyph_width).sum()
+        }
+
+        for (prologue, continuation) in [('┃', '='), ('全', '='), ('=', '🦀'), ('全', '🦀')] {
+            let (logger, capture) = Capture::logger();
+            logger.set_color_policy(ColorPolicy::Never);
+            logger.set_prologue(prologue);
+            logger.set_continuation(continuation);
+            logger.info("text\ntext");
+
+            let out = capture.output();
+            let columns = out.lines().map(text_column).collect::<Vec<_>>();
+
+            assert_eq!(columns.len(), 2);
+            assert_eq!(columns[0], columns[1], "{out}");
+        }
+    }
+
+    #[test]
+    fn rfc3339_formats_utc() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(UNIX_EPOCH + Duration::from_secs(1_000_000_000)), "2001-09-09T01:46:40Z");
+        assert_eq!(rfc3339(UNIX_EPOCH + Duration::from_secs(951_782_400)), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn timestamp_follows_prologue_and_continuations_align() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let options = RenderOptions { timestamp: Some(time), ..RenderOptions::default() };
+        let rendered = render::<Blue>("first\nsecond", &options);
+        let mut lines = rendered.lines();
+        let first = lines.next().unwrap();
+        let second = lines.next().unwrap();
+
+        assert!(first.ends_with(" 2001-09-09T01:46:40Z first"));
+        assert!(second.ends_with(&format!(" {}second", " ".repeat("2001-09-09T01:46:40Z ".len()))));
+    }
+
+    #[test]
+    fn long_lines_wrap_with_continuation_prefix() {
+        let options = RenderOptions { width: Some(12), ..RenderOptions::default() };
+        let rendered = render::<Blue>("abcdefghijklmnopqrstuvwxy\nshort", &options);
+        let lines: Vec<_> = rendered.lines().collect();
+
+        // 10 columns are left after the glyph and its space
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with(" abcdefghij"));
+        assert!(lines[1].ends_with(" klmnopqrst"));
+        assert!(lines[2].ends_with(" uvwxy"));
+        assert!(lines[3].ends_with(" short"));
+    }
+
+    #[test]
+    fn custom_color_is_emitted() {
+        let (logger, capture) = Capture::logger();
+        logger.custom::<Magenta>("highlighted");
+
+        let out = capture.output();
+
+        // SGR 35 is magenta
+        assert!(out.contains("\x1b[35m"));
+        assert!(!out.contains("\x1b[34m"));
+    }
+
+    #[test]
+    fn nested_scopes_increase_indentation() {
+        let (logger, capture) = Capture::logger();
+        logger.set_color_policy(ColorPolicy::Never);
+        logger.info("root");
+
+        {
+            let _outer = logger.scope("outer");
+            logger.info("one\ntwo");
+
+            {
+                let _inner = logger.scope("inner");
+                logger.info("three");
+            }
+
+            logger.info("four");
+        }
+
+        logger.info("five");
+
+        let out = capture.output();
+        let lines: Vec<_> = out.lines().collect();
+
+        assert_eq!(
+            lines,
+            [
+                "┃ root",
+                "┃ outer",
+                "┃   one",
+                "=   two",
+                "┃   inner",
+                "┃     three",
+                "┃   four",
+                "┃ five",
+            ],
+        );
+    }
+
+    // writes at most a few bytes per call, so unguarded messages would interleave
+    #[derive(Clone)]
+    struct ChoppySink(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for ChoppySink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(3);
+            self.0.lock().unwrap().extend_from_slice(&buf[..n]);
+            thread::yield_now();
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dbg_tip_returns_value_and_logs_it() {
+        let _lock = GLOBAL_LOCK.lock().unwrap();
+        let capture = Capture::default();
+        crate::set_writer(capture.clone());
+
+        let value = dbg_tip!(vec![1, 2].len() + 40);
+        crate::set_writer(io::stderr());
+
+        assert_eq!(value, 42);
+
+        let out = capture.output();
+        assert!(out.contains(&format!("[{}:", file!())), "{out}");
+        assert!(out.contains("vec![1, 2].len() + 40 = 42"), "{out}");
+    }
+
+    #[test]
+    fn capture_records_macros_of_the_global_logger() {
+        let _lock = GLOBAL_LOCK.lock().unwrap();
+        let capture = crate::capture();
+
+        info!("starting {}", 1);
+        error!("failed: {}", "oops");
+
+        assert_eq!(capture.records(), [
+            (Level::Info, "starting 1".to_owned()),
+            (Level::Error, "failed: oops".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn concurrent_messages_do_not_interleave() {
+        const THREADS: usize = 8;
+        const MESSAGES: usize = 20;
+        const LINES: usize = 4;
+
+        let sink = ChoppySink(Arc::new(Mutex::new(Vec::new())));
+        let logger = Arc::new(Logger::new(sink.clone()));
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let logger = Arc::clone(&logger);
+
+                thread::spawn(move || {
+                    for m in 0..MESSAGES {
+                        let message = (0..LINES)
+                            .map(|l| format!("thread {t} message {m} line {l}"))
+                            .collect::<Vec<_>>()
+                            .join("\n");
+
+                        logger.info(message);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let out = String::from_utf8(sink.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+
+        assert_eq!(lines.len(), THREADS * MESSAGES * LINES);
+
+        for message in lines.chunks(LINES) {
+            let id = message[0].split(" line ").next().unwrap().split_once("thread").unwrap().1;
+
+            for (l, line) in message.iter().enumerate() {
+                assert!(line.ends_with(&format!("thread{id} line {l}")), "interleaved line: {line:?}");
+            }
+        }
+    }
+}
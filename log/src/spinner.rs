@@ -0,0 +1,145 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::{env, fmt};
+use owo_colors::OwoColorize;
+use owo_colors::colors::Cyan;
+use crate::{ColorPolicy, Logger};
+
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const FRAME_INTERVAL: Duration = Duration::from_millis(80);
+
+/// A spinner shown while a long operation runs. Created by [`spinner`](crate::spinner).
+///
+/// The spinner is animated on the writer of its logger, unless colors are disabled, `NO_COLOR` is
+/// set, the writer isn't a terminal or the logger is [capturing](Logger::capture), in which case the
+/// label is logged once instead. Either way, finish it with
+/// [`finish_success`](Self::finish_success) or [`finish_error`](Self::finish_error) to log the
+/// outcome. Dropping it stops the animation without logging anything.
+pub struct Spinner {
+    logger: &'static Logger,
+    label: String,
+    animation: Option<(Arc<AtomicBool>, JoinHandle<()>)>,
+}
+
+impl Spinner {
+    pub(crate) fn new(logger: &'static Logger, label: String) -> Self {
+        let animate = logger.color_policy() == ColorPolicy::Always
+            && env::var_os("NO_COLOR").is_none()
+            && logger.terminal.load(Ordering::Relaxed)
+            && !logger.capturing();
+
+        if !animate {
+            logger.info(&label);
+
+            return Self { logger, label, animation: None }
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let (stop, label) = (Arc::clone(&stop), label.clone());
+
+            move || {
+                for frame in FRAMES.iter().cycle() {
+                    if stop.load(Ordering::Relaxed) {
+                        break
+                    }
+
+                    let _ = logger.write_rendered(format!("\r{} {label}", frame.bold().fg::<Cyan>()));
+
+                    thread::sleep(FRAME_INTERVAL);
+                }
+            }
+        });
+
+        Self { logger, label, animation: Some((stop, handle)) }
+    }
+
+    /// Stops the animation and clears its line, if it is animated.
+    fn stop(&mut self) {
+        if let Some((stop, handle)) = self.animation.take() {
+            stop.store(true, Ordering::Relaxed);
+            let _ = handle.join();
+
+            let _ = self.logger.write_rendered("\r\x1b[2K");
+        }
+    }
+
+    /// Stops the spinner and logs `label: message` with [`tip`](Logger::tip).
+    pub fn finish_success(mut self, message: impl fmt::Display) {
+        self.stop();
+        self.logger.tip(format_args!("{}: {message}", self.label));
+    }
+
+    /// Stops the spinner and logs `label: message` with [`error`](Logger::error).
+    pub fn finish_error(mut self, message: impl fmt::Display) {
+        self.stop();
+        self.logger.error(format_args!("{}: {message}", self.label));
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::sync::atomic::Ordering;
+    use crate::{ColorPolicy, Level, DEFAULT_PROLOGUE};
+    use crate::tests::Capture;
+
+    #[test]
+    fn static_spinner_logs_label_then_outcome() {
+        let (logger, capture) = Capture::logger();
+        let logger = Box::leak(Box::new(logger));
+        logger.set_color_policy(ColorPolicy::Never);
+
+        logger.spinner("fetching index").finish_success("42 packages");
+        logger.spinner("fetching index").finish_error("timed out");
+
+        assert_eq!(capture.output(), format!(
+            "{DEFAULT_PROLOGUE} fetching index\n\
+             {DEFAULT_PROLOGUE} fetching index: 42 packages\n\
+             {DEFAULT_PROLOGUE} fetching index\n\
+             {DEFAULT_PROLOGUE} fetching index: timed out\n",
+        ));
+    }
+
+    #[test]
+    fn animates_on_the_writer_of_the_logger() {
+        if env::var_os("NO_COLOR").is_some() {
+            return
+        }
+
+        let (logger, capture) = Capture::logger();
+        let logger = Box::leak(Box::new(logger));
+        logger.terminal.store(true, Ordering::Relaxed);
+
+        logger.spinner("fetching index").finish_success("42 packages");
+
+        let out = capture.output();
+        assert!(out.starts_with('\r'), "{out:?}");
+        assert!(out.contains("\r\x1b[2K"), "{out:?}");
+        assert!(out.ends_with(" fetching index: 42 packages\n"), "{out:?}");
+    }
+
+    #[test]
+    fn captured_spinners_are_not_animated() {
+        let (logger, capture) = Capture::logger();
+        let logger = Box::leak(Box::new(logger));
+        logger.terminal.store(true, Ordering::Relaxed);
+
+        let records = logger.capture();
+        logger.spinner("fetching index").finish_success("42 packages");
+
+        assert_eq!(records.records(), [
+            (Level::Info, "fetching index".to_owned()),
+            (Level::Tip, "fetching index: 42 packages".to_owned()),
+        ]);
+        assert!(capture.output().is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+//! A [`tracing_subscriber`] layer rendering events in the same style as the rest of this crate.
+
+use std::fmt::{self, Write as _};
+use std::io::Write as _;
+use owo_colors::colors::{Blue, Cyan, Green, Red, Yellow};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+use self::global::GlobalWriter;
+
+/// Renders every event with the prologue glyph colored by its level, indented by two spaces for
+/// every span the event is nested in.
+///
+/// Levels map to the colors of the log functions: `ERROR` to [`error`](crate::error), `WARN` to
+/// [`warn`](crate::warn), `INFO` to [`info`](crate::info), `DEBUG` to [`debug`](crate::debug) and
+/// `TRACE` to [`tip`](crate::tip).
+pub struct PrologueLayer<W = GlobalWriter> {
+    make_writer: W,
+}
+
+impl PrologueLayer {
+    /// Creates a layer writing through the [global](crate::global) logger, so events never
+    /// interleave with the messages logged by the rest of this crate.
+    pub fn new() -> Self {
+        Self { make_writer: GlobalWriter }
+    }
+}
+
+impl Default for PrologueLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W> PrologueLayer<W> {
+    /// Writes to whatever `make_writer` returns instead.
+    pub fn with_writer<W2: for<'w> MakeWriter<'w>>(self, make_writer: W2) -> PrologueLayer<W2> {
+        PrologueLayer { make_writer }
+    }
+}
+
+mod global {
+    use std::io;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    /// Writes through the [global](crate::global) logger, taking its lock once per write.
+    pub struct GlobalWriter;
+
+    impl MakeWriter<'_> for GlobalWriter {
+        type Writer = Self;
+
+        fn make_writer(&self) -> Self {
+            Self
+        }
+    }
+
+    impl io::Write for GlobalWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            crate::global().write_rendered(buf)?;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Default)]
+struct Message {
+    message: String,
+    fields: String,
+}
+
+impl Visit for Message {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+impl<S, W> Layer<S> for PrologueLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + 'static,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let depth = ctx.event_scope(event).map_or(0, |scope| scope.count());
+        let mut message = Message::default();
+        event.record(&mut message);
+
+        let indent = "  ".repeat(depth);
+        let message = format!("{}{}", message.message, message.fields)
+            .lines()
+            .map(|line| format!("{indent}{line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let logger = crate::global();
+        let rendered = match *event.metadata().level() {
+            Level::ERROR => logger.render::<Red>(message),
+            Level::WARN => logger.render::<Yellow>(message),
+            Level::INFO => logger.render::<Blue>(message),
+            Level::DEBUG => logger.render::<Cyan>(message),
+            Level::TRACE => logger.render::<Green>(message),
+        };
+
+        let _ = self.make_writer.make_writer().write_all(rendered.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+    use crate::tests::{Capture, GLOBAL_LOCK};
+    use crate::tracing::PrologueLayer;
+
+    #[test]
+    fn renders_events_indented_by_span_depth() {
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = Registry::default().with(PrologueLayer::new().with_writer(move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("top level");
+
+            let _outer = tracing::info_span!("outer").entered();
+            tracing::warn!(attempt = 2, "nested");
+        });
+
+        let out = capture.output();
+        let mut lines = out.lines();
+
+        assert!(lines.next().unwrap().ends_with(" top level"));
+        assert!(lines.next().unwrap().ends_with("   nested attempt=2"));
+    }
+
+    #[test]
+    fn writes_through_the_global_logger() {
+        let _lock = GLOBAL_LOCK.lock().unwrap();
+        let capture = Capture::default();
+        crate::set_writer(capture.clone());
+
+        tracing::subscriber::with_default(Registry::default().with(PrologueLayer::new()), || {
+            tracing::info!("from tracing");
+            crate::info("from the logger");
+        });
+        crate::set_writer(io::stderr());
+
+        let out = capture.output();
+        let mut lines = out.lines();
+
+        assert!(lines.next().unwrap().ends_with(" from tracing"), "{out}");
+        assert!(lines.next().unwrap().ends_with(" from the logger"), "{out}");
+    }
+}